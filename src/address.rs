@@ -1,4 +1,6 @@
-use std::{collections::HashMap, fmt::Display};
+use std::{collections::HashMap, fmt::Display, str::FromStr};
+
+use serde::Serialize;
 
 use crate::{lexer::Token, types::Pep8Word};
 
@@ -36,8 +38,8 @@ pub enum AddrLocation {
 impl AddrLocation {
     pub fn from_token(token: Token) -> Result<Self, Box<dyn std::error::Error>> {
         match token {
-            Token::Char(value) => Ok(Self::Memory(Pep8Word::try_from(value)?)),
-            Token::String(value) => Ok(Self::Memory(Pep8Word::try_from(value)?)),
+            Token::Char(value) => Ok(Self::Memory(Pep8Word::from(&value))),
+            Token::String(value) => Ok(Self::Memory(Pep8Word::try_from(&value)?)),
             Token::Number(value) => Ok(Self::Memory(Pep8Word::new(value))),
             Token::Identifier(value) => Ok(Self::Label(value)),
             _ => Err(Box::new(Error::InvalidAddressTokenType(token))),
@@ -45,6 +47,15 @@ impl AddrLocation {
     }
 }
 
+impl Display for AddrLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Memory(value) => write!(f, "{value}"),
+            Self::Label(label) => write!(f, "{label}"),
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum AddrMode {
     Immediate,
@@ -72,8 +83,10 @@ impl Display for AddrMode {
     }
 }
 
-impl AddrMode {
-    pub fn from_str(s: &str) -> Result<Self, Error> {
+impl FromStr for AddrMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
         match s {
             "i" => Ok(Self::Immediate),
             "d" => Ok(Self::Direct),
@@ -82,11 +95,14 @@ impl AddrMode {
             "sf" => Ok(Self::StackRelativeDeferred),
             "x" => Ok(Self::Indexed),
             "sx" => Ok(Self::StackIndexed),
-            "sxf" => Ok(Self::StackIndexedDeferred),
+            // Pep/9 spells the stack-deferred indexed mode `sfx`.
+            "sxf" | "sfx" => Ok(Self::StackIndexedDeferred),
             _ => Err(Error::InvalidAddrModeString(String::from(s))),
         }
     }
+}
 
+impl AddrMode {
     pub fn as_byte_short(&self) -> Result<u8, Error> {
         match &self {
             Self::Immediate => Ok(0),
@@ -115,6 +131,12 @@ pub struct Address {
     pub mode: AddrMode,
 }
 
+impl Display for Address {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{},{}", self.location, self.mode)
+    }
+}
+
 impl Address {
     pub fn from_tokens_short(tokens: &[Token]) -> Result<Self, Box<dyn std::error::Error>> {
         match tokens {
@@ -123,7 +145,7 @@ impl Address {
                 mode: AddrMode::Immediate,
             }),
             [address_token, Token::Comma, Token::Identifier(mode_value)] => {
-                let mode = AddrMode::from_str(&mode_value)?;
+                let mode = AddrMode::from_str(mode_value)?;
 
                 match mode {
                     AddrMode::Immediate | AddrMode::Indexed => Ok(Address {
@@ -139,13 +161,13 @@ impl Address {
 
     pub fn from_tokens_long(
         tokens: &[Token],
-        legal_addressing_modes: &[&str],
+        legal_addressing_modes: &[AddrMode],
     ) -> Result<Self, Box<dyn std::error::Error>> {
         match tokens {
             [address_token, Token::Comma, Token::Identifier(mode_value)] => {
-                let mode = AddrMode::from_str(&mode_value)?;
+                let mode = AddrMode::from_str(mode_value)?;
 
-                if legal_addressing_modes.contains(&mode_value.as_str()) {
+                if legal_addressing_modes.contains(&mode) {
                     Ok(Address {
                         location: AddrLocation::from_token(address_token.clone())?,
                         mode,
@@ -159,9 +181,57 @@ impl Address {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SymbolKind {
+    CodeLabel,
+    DataLabel,
+    Equate,
+    /// Symbol referenced but defined in another object file.
+    External,
+}
+
+impl Display for SymbolKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::CodeLabel => write!(f, "code"),
+            Self::DataLabel => write!(f, "data"),
+            Self::Equate => write!(f, "equate"),
+            Self::External => write!(f, "external"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SymbolDefinition {
+    pub value: Pep8Word,
+    pub kind: SymbolKind,
+    /// Line on which the symbol is defined.
+    pub line: usize,
+    /// Lines on which the symbol is used as an operand or by `.ADDRSS`.
+    pub references: Vec<usize>,
+}
+
+impl SymbolDefinition {
+    pub fn new(value: Pep8Word, kind: SymbolKind, line: usize) -> Self {
+        Self {
+            value,
+            kind,
+            line,
+            references: vec![],
+        }
+    }
+
+    /// Whether the target defines the symbol rather than the source, as for
+    /// the I/O ports of Pep/9, in which case it has no line.
+    pub fn is_predefined(&self) -> bool {
+        self.line == 0
+    }
+}
+
+#[derive(Clone, Debug, Default)]
 pub struct AddressTable {
-    table: HashMap<String, Pep8Word>,
+    table: HashMap<String, SymbolDefinition>,
 }
 
 impl AddressTable {
@@ -172,17 +242,40 @@ impl AddressTable {
     }
 
     pub fn get(&self, key: &String) -> Option<&Pep8Word> {
+        self.table.get(key).map(|definition| &definition.value)
+    }
+
+    pub fn definition(&self, key: &String) -> Option<&SymbolDefinition> {
         self.table.get(key)
     }
 
-    pub fn insert(&mut self, key: String, value: Pep8Word) -> Option<Pep8Word> {
+    pub fn insert(&mut self, key: String, value: SymbolDefinition) -> Option<SymbolDefinition> {
         self.table.insert(key, value)
     }
 
+    /// Records a use of `key` on `line`, returning false if it is undefined.
+    pub fn add_reference(&mut self, key: &String, line: usize) -> bool {
+        match self.table.get_mut(key) {
+            Some(definition) => {
+                definition.references.push(line);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Iterates over the symbols sorted by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &SymbolDefinition)> {
+        let mut symbols: Vec<_> = self.table.iter().collect();
+
+        symbols.sort_by(|a, b| a.0.cmp(b.0));
+        symbols.into_iter()
+    }
+
     pub fn resolve(&self, address: &Address) -> Option<Pep8Word> {
         match &address.location {
             AddrLocation::Memory(memory_address) => Some(*memory_address),
-            AddrLocation::Label(label) => self.get(&label).copied(),
+            AddrLocation::Label(label) => self.get(label).copied(),
         }
     }
 }
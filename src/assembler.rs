@@ -1,7 +1,18 @@
-use std::fmt::Display;
+use std::{collections::HashMap, fmt::Display};
 
 use crate::{
-    address::AddressTable, dotcommand::DotCommand, instruction::Instruction, lexer::Token,
+    address::{AddrLocation, AddressTable, SymbolDefinition, SymbolKind},
+    diagnostic::Warning,
+    dotcommand::DotCommand,
+    formatter::layout,
+    instruction::Instruction,
+    lexer::{self, SourceLine, Token, TraceTag},
+    macros,
+    object::{ObjectFile, Relocation, Symbol},
+    suggest::suggest,
+    target::Target,
+    trace,
+    traps::{self, Trap},
     types::Pep8Word,
 };
 
@@ -9,6 +20,28 @@ use crate::{
 pub enum Error {
     TokensEmpty,
     InvalidTokenType,
+    EquateWithoutLabel(usize),
+    DuplicateSymbol {
+        symbol: String,
+        first_line: usize,
+        second_line: usize,
+    },
+    UndefinedSymbol {
+        symbol: String,
+        line: usize,
+        suggestion: Option<String>,
+    },
+    SizeMismatch {
+        expected: usize,
+        actual: usize,
+    },
+    ImportedSymbolDefined {
+        symbol: String,
+        line: usize,
+        definition_line: usize,
+    },
+    OutOfMemory(usize),
+    AtLine(usize, Box<dyn std::error::Error>),
 }
 
 impl Display for Error {
@@ -16,6 +49,46 @@ impl Display for Error {
         match self {
             Self::TokensEmpty => write!(f, "tokens is empty"),
             Self::InvalidTokenType => write!(f, "invalid token type"),
+            Self::EquateWithoutLabel(line) => {
+                write!(f, "line {line}: .EQUATE requires a label")
+            }
+            Self::DuplicateSymbol {
+                symbol,
+                first_line,
+                second_line,
+            } => write!(
+                f,
+                "line {second_line}: duplicate symbol {symbol}, first defined on line {first_line}"
+            ),
+            Self::UndefinedSymbol {
+                symbol,
+                line,
+                suggestion: Some(suggestion),
+            } => write!(
+                f,
+                "line {line}: undefined symbol: {symbol} (did you mean {suggestion}?)"
+            ),
+            Self::UndefinedSymbol {
+                symbol,
+                line,
+                suggestion: None,
+            } => write!(f, "line {line}: undefined symbol: {symbol}"),
+            Self::SizeMismatch { expected, actual } => write!(
+                f,
+                "statement was sized as {expected} bytes but encoded as {actual} bytes"
+            ),
+            Self::ImportedSymbolDefined {
+                symbol,
+                line,
+                definition_line,
+            } => write!(
+                f,
+                "line {line}: imported symbol {symbol} is defined on line {definition_line}"
+            ),
+            Self::OutOfMemory(line) => {
+                write!(f, "line {line}: statement extends past the end of memory")
+            }
+            Self::AtLine(line, error) => write!(f, "line {line}: {error}"),
         }
     }
 }
@@ -29,17 +102,45 @@ pub enum Statement {
 }
 
 impl Statement {
-    pub fn from_tokens(tokens: &[Token]) -> Result<Self, Box<dyn std::error::Error>> {
+    pub fn from_tokens(
+        tokens: &[Token],
+        target: Target,
+        traps: &[Trap],
+    ) -> Result<Self, Box<dyn std::error::Error>> {
         match tokens.first() {
             None => Err(Box::new(Error::TokensEmpty)),
             Some(token) => match token {
-                Token::Identifier(_) => Ok(Self::Instruction(Instruction::from_tokens(tokens)?)),
-                Token::DotCommand(_) => Ok(Self::DotCommand(DotCommand::from_tokens(tokens)?)),
+                Token::Identifier(_) => Ok(Self::Instruction(Instruction::from_tokens(
+                    tokens, target, traps,
+                )?)),
+                Token::DotCommand(_) => {
+                    Ok(Self::DotCommand(DotCommand::from_tokens(tokens, target)?))
+                }
                 _ => Err(Box::new(Error::InvalidTokenType)),
             },
         }
     }
 
+    /// Mnemonic or dot command of the statement and its operands, spelled for
+    /// the target.
+    fn fields(&self, target: Target) -> (String, String) {
+        match self {
+            Self::Instruction(instruction) => (
+                instruction.mnemonic(target),
+                instruction.operand(target).unwrap_or_default(),
+            ),
+            Self::DotCommand(dotcommand) => (dotcommand.name().to_string(), dotcommand.operands()),
+        }
+    }
+
+    /// Source of the statement in the target's assembly language.
+    pub fn to_source(&self, target: Target) -> String {
+        match self {
+            Self::Instruction(instruction) => instruction.to_source(target),
+            Self::DotCommand(dotcommand) => dotcommand.to_string(),
+        }
+    }
+
     pub fn byte_size(&self) -> usize {
         match self {
             Self::Instruction(instruction) => instruction.byte_size(),
@@ -47,12 +148,41 @@ impl Statement {
         }
     }
 
+    /// Returns the symbol referenced by the statement along with the offset of
+    /// the 16-bit field holding its value, relative to the start of the
+    /// statement.
+    pub fn symbol_reference(&self) -> Option<(usize, &String)> {
+        match self {
+            Self::Instruction(instruction) => match instruction.address() {
+                Some(address) => match &address.location {
+                    AddrLocation::Label(label) => Some((1, label)),
+                    AddrLocation::Memory(_) => None,
+                },
+                None => None,
+            },
+            Self::DotCommand(DotCommand::ADDRSS(label)) => Some((0, label)),
+            Self::DotCommand(_) => None,
+        }
+    }
+
+    /// Returns the symbol named by a `.EXPORT` or `.SCALL` directive, which
+    /// must be defined by the program.
+    pub fn declared_symbol(&self) -> Option<&String> {
+        match self {
+            Self::DotCommand(DotCommand::EXPORT(symbol) | DotCommand::SCALL(symbol)) => {
+                Some(symbol)
+            }
+            _ => None,
+        }
+    }
+
     pub fn as_bytes(
         &self,
         address_table: &AddressTable,
+        target: Target,
     ) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
         match self {
-            Self::Instruction(instruction) => instruction.as_bytes(address_table),
+            Self::Instruction(instruction) => instruction.as_bytes(address_table, target),
             Self::DotCommand(dotcommand) => Ok(dotcommand.as_bytes(address_table)?),
         }
     }
@@ -62,47 +192,288 @@ impl Statement {
 pub struct StatementLine {
     label: Option<String>,
     statement: Statement,
+    line: usize,
+    address: u16,
+    trace_tags: Vec<TraceTag>,
 }
 
 impl StatementLine {
-    pub fn from_tokens(tokens: &[Token]) -> Result<Self, Box<dyn std::error::Error>> {
+    /// Statement built by a program rather than parsed from source, reported
+    /// as being on `line`.
+    pub fn new(label: Option<String>, statement: Statement, line: usize) -> Self {
+        Self {
+            label,
+            statement,
+            line,
+            address: 0,
+            trace_tags: vec![],
+        }
+    }
+
+    pub fn from_tokens(
+        tokens: &[Token],
+        line: usize,
+        target: Target,
+        traps: &[Trap],
+    ) -> Result<Self, Box<dyn std::error::Error>> {
         match tokens {
             [] => Err(Box::new(Error::TokensEmpty)),
             [Token::Label(label), tokens @ ..] => Ok(Self {
                 label: Some(label.clone()),
-                statement: Statement::from_tokens(tokens)?,
+                statement: Statement::from_tokens(tokens, target, traps)?,
+                line,
+                address: 0,
+                trace_tags: vec![],
             }),
-            [tokens @ ..] => Ok(Self {
+            tokens => Ok(Self {
                 label: None,
-                statement: Statement::from_tokens(tokens)?,
+                statement: Statement::from_tokens(tokens, target, traps)?,
+                line,
+                address: 0,
+                trace_tags: vec![],
             }),
         }
     }
 
+    pub fn label(&self) -> Option<&String> {
+        self.label.as_ref()
+    }
+
+    /// Source of the line in the target's assembly language, laid out on the
+    /// Pep/8 IDE columns.
+    pub fn to_source(&self, target: Target) -> String {
+        let (mnemonic, operands) = self.statement.fields(target);
+
+        layout(self.label.as_deref(), Some(&mnemonic), &operands, None)
+    }
+
+    pub fn statement(&self) -> &Statement {
+        &self.statement
+    }
+
+    /// Trace tags in the comment of the line.
+    pub fn trace_tags(&self) -> &[TraceTag] {
+        &self.trace_tags
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Address of the statement's first byte in the assembled program.
+    pub fn address(&self) -> u16 {
+        self.address
+    }
+
+    /// Kind of the symbol defined by this line's label.
+    pub fn symbol_kind(&self) -> SymbolKind {
+        match self.statement {
+            Statement::Instruction(_) => SymbolKind::CodeLabel,
+            Statement::DotCommand(DotCommand::EQUATE(_)) => SymbolKind::Equate,
+            Statement::DotCommand(_) => SymbolKind::DataLabel,
+        }
+    }
+
     pub fn byte_size(&self) -> usize {
         self.statement.byte_size()
     }
 
+    /// Encodes the statement, checking that the encoding has the size used to
+    /// lay out the program during the first pass.
     pub fn as_bytes(
         &self,
         address_table: &AddressTable,
+        target: Target,
     ) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-        self.statement.as_bytes(address_table)
+        let bytes = self.statement.as_bytes(address_table, target)?;
+
+        if bytes.len() != self.byte_size() {
+            return Err(Box::new(Error::SizeMismatch {
+                expected: self.byte_size(),
+                actual: bytes.len(),
+            }));
+        }
+
+        Ok(bytes)
     }
 }
 
-pub fn assemble(lines: Vec<Vec<Token>>) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-    let mut statement_lines = vec![];
-    let mut byte_code = vec![];
-    let mut address = 0;
+/// Splits an assembly or lexing error into the line it refers to and its
+/// message, for tools that report errors next to the source.
+pub fn locate_error(error: &(dyn std::error::Error + 'static)) -> Option<(usize, String)> {
+    let line = match error.downcast_ref::<Error>() {
+        Some(Error::AtLine(line, error)) => return Some((*line, error.to_string())),
+        Some(Error::EquateWithoutLabel(line)) => *line,
+        Some(Error::DuplicateSymbol { second_line, .. }) => *second_line,
+        Some(Error::UndefinedSymbol { line, .. }) => *line,
+        Some(Error::ImportedSymbolDefined { line, .. }) => *line,
+        Some(Error::OutOfMemory(line)) => *line,
+        Some(_) => return None,
+        None => match error.downcast_ref::<lexer::Error>() {
+            Some(lexer::Error::AtLine(line, error)) => return Some((*line, error.to_string())),
+            _ => return None,
+        },
+    };
+    let message = error.to_string();
+    let prefix = format!("line {line}: ");
 
+    Some((
+        line,
+        message
+            .strip_prefix(&prefix)
+            .unwrap_or(&message)
+            .to_string(),
+    ))
+}
+
+/// Bytes of memory addressable by the Pep/8.
+pub const MEMORY_SIZE: usize = 0x10000;
+
+/// Longest symbol accepted by the original Pep/8 assembler.
+const PEP8_SYMBOL_LENGTH: usize = 8;
+
+#[derive(Clone, Debug, Default)]
+pub struct Options {
+    /// Warn about symbols the original Pep/8 assembler would reject.
+    pub compatibility: bool,
+    pub target: Target,
+    /// Custom trap mnemonics, in addition to those defined with `.TRAP`.
+    pub traps: Vec<Trap>,
+    /// Address of the program's first byte. Objects always start at 0.
+    pub origin: u16,
+}
+
+/// Result of assembling a program into a Pep/8 image.
+#[derive(Debug)]
+pub struct Assembly {
+    pub byte_code: Vec<u8>,
+    pub address_table: AddressTable,
+    pub statement_lines: Vec<StatementLine>,
+    pub warnings: Vec<Warning>,
+    /// Address of the first byte of `byte_code`.
+    pub origin: u16,
+}
+
+impl Assembly {
+    /// Bytes emitted for a statement line of this assembly.
+    pub fn bytes_of(&self, statement_line: &StatementLine) -> &[u8] {
+        let start = (statement_line.address - self.origin) as usize;
+
+        &self.byte_code[start..start + statement_line.byte_size()]
+    }
+
+    /// Statement line assembled from the given source line, if any.
+    pub fn statement_at_line(&self, line: usize) -> Option<&StatementLine> {
+        self.statement_lines
+            .iter()
+            .find(|statement_line| statement_line.line == line)
+    }
+}
+
+/// Runs the first pass only, returning the symbols defined and referenced by
+/// the lines without encoding them.
+pub fn symbol_table(
+    lines: Vec<SourceLine>,
+    options: &Options,
+) -> Result<AddressTable, Box<dyn std::error::Error>> {
+    Ok(parse_statements(lines, options)?.1)
+}
+
+/// Gathers the statements of each section, keeping sections in the order
+/// they first appear. Statements before the first `.SECTION` belong to
+/// `.text`.
+fn arrange_sections(statement_lines: Vec<StatementLine>) -> Vec<StatementLine> {
+    let mut sections: Vec<(String, Vec<StatementLine>)> = vec![(String::from(".text"), vec![])];
+    let mut current = 0;
+
+    for statement_line in statement_lines {
+        if let Statement::DotCommand(DotCommand::SECTION(name)) = &statement_line.statement {
+            current = match sections.iter().position(|(section, _)| section == name) {
+                Some(index) => index,
+                None => {
+                    sections.push((name.clone(), vec![]));
+                    sections.len() - 1
+                }
+            };
+        }
+
+        sections[current].1.push(statement_line);
+    }
+
+    sections
+        .into_iter()
+        .flat_map(|(_, statement_lines)| statement_lines)
+        .collect()
+}
+
+/// Parses the lines, expanding macros and custom traps, and lays out the
+/// statements.
+fn parse_statements(
+    lines: Vec<SourceLine>,
+    options: &Options,
+) -> Result<(Vec<StatementLine>, AddressTable), Box<dyn std::error::Error>> {
+    let target = options.target;
+    let mut parsed_lines = vec![];
+    let lines = macros::expand(lines, target)
+        .map_err(|(line, error)| Error::AtLine(line, Box::new(error)))?;
+    let traps = traps::collect(&lines, &options.traps, target)
+        .map_err(|(line, error)| Error::AtLine(line, Box::new(error)))?;
+
+    for line in lines.iter().filter(|line| !line.tokens.is_empty()) {
+        let mut statement_line =
+            StatementLine::from_tokens(&line.tokens, line.number, target, &traps)
+                .map_err(|error| Error::AtLine(line.number, error))?;
+
+        statement_line.trace_tags = line.trace_tags.clone();
+        parsed_lines.push(statement_line);
+    }
+
+    lay_out(parsed_lines, options)
+}
+
+/// Arranges the statements by section and assigns their addresses,
+/// collecting the symbols they define along with the I/O ports and system
+/// calls of the target that the program does not define itself.
+fn lay_out(
+    parsed_lines: Vec<StatementLine>,
+    options: &Options,
+) -> Result<(Vec<StatementLine>, AddressTable), Box<dyn std::error::Error>> {
+    let target = options.target;
+    let mut statement_lines = vec![];
+    let mut address = options.origin as usize;
     let mut address_table = AddressTable::new();
 
-    for line in lines {
-        let statement_line = StatementLine::from_tokens(&line)?;
+    for mut statement_line in arrange_sections(parsed_lines) {
+        if address + statement_line.byte_size() > MEMORY_SIZE {
+            return Err(Box::new(Error::OutOfMemory(statement_line.line)));
+        }
+
+        statement_line.address = address as u16;
+
+        let value = match statement_line.statement {
+            Statement::DotCommand(DotCommand::EQUATE(value)) => value,
+            _ => Pep8Word::new(address as u16),
+        };
 
-        if let Some(ref label) = statement_line.label {
-            address_table.insert(label.clone(), Pep8Word::new(address as u16));
+        match statement_line.label {
+            Some(ref label) => {
+                if let Some(previous) = address_table.definition(label) {
+                    return Err(Box::new(Error::DuplicateSymbol {
+                        symbol: label.clone(),
+                        first_line: previous.line,
+                        second_line: statement_line.line,
+                    }));
+                }
+
+                address_table.insert(
+                    label.clone(),
+                    SymbolDefinition::new(value, statement_line.symbol_kind(), statement_line.line),
+                );
+            }
+            None if statement_line.symbol_kind() == SymbolKind::Equate => {
+                return Err(Box::new(Error::EquateWithoutLabel(statement_line.line)));
+            }
+            None => {}
         }
 
         address += statement_line.byte_size();
@@ -110,11 +481,472 @@ pub fn assemble(lines: Vec<Vec<Token>>) -> Result<Vec<u8>, Box<dyn std::error::E
         statement_lines.push(statement_line);
     }
 
-    for statement_line in statement_lines {
-        let bytes = statement_line.as_bytes(&address_table)?;
+    for (symbol, value) in target.io_ports().iter().chain(target.system_calls()) {
+        let symbol = symbol.to_string();
+
+        if address_table.definition(&symbol).is_none() {
+            address_table.insert(
+                symbol,
+                SymbolDefinition::new(Pep8Word::new(*value), SymbolKind::Equate, 0),
+            );
+        }
+    }
+
+    for statement_line in &statement_lines {
+        if let Some((_, symbol)) = statement_line.statement.symbol_reference() {
+            address_table.add_reference(symbol, statement_line.line);
+        }
+
+        if let Some(symbol) = statement_line.statement.declared_symbol() {
+            address_table.add_reference(symbol, statement_line.line);
+        }
+
+        if let Statement::DotCommand(DotCommand::IMPORT(symbol)) = &statement_line.statement {
+            if let Some(definition) = address_table.definition(symbol) {
+                return Err(Box::new(Error::ImportedSymbolDefined {
+                    symbol: symbol.clone(),
+                    line: statement_line.line,
+                    definition_line: definition.line,
+                }));
+            }
+        }
+    }
+
+    Ok((statement_lines, address_table))
+}
+
+/// Warns about labels that are probably mistakes: labels that differ from
+/// another only by case, labels too long for Pep/8 in compatibility mode and,
+/// if `report_unused` is set, data labels that are never referenced. Code
+/// labels such as the entry point or the head of a loop are often written
+/// without being branched to, so they are not reported.
+fn check_symbols(
+    address_table: &AddressTable,
+    options: &Options,
+    report_unused: bool,
+) -> Vec<Warning> {
+    let mut warnings = vec![];
+    let mut symbols: Vec<_> = address_table
+        .iter()
+        .filter(|(_, definition)| {
+            definition.kind != SymbolKind::External && !definition.is_predefined()
+        })
+        .collect();
+    let mut folded_symbols: HashMap<String, (&String, usize)> = HashMap::new();
+
+    symbols.sort_by_key(|(_, definition)| definition.line);
+
+    for (symbol, definition) in symbols {
+        match folded_symbols.get(&symbol.to_lowercase()) {
+            Some((other, line)) => warnings.push(Warning::new(
+                definition.line,
+                format!("symbol {symbol} differs only by case from {other} defined on line {line}"),
+            )),
+            None => {
+                folded_symbols.insert(symbol.to_lowercase(), (symbol, definition.line));
+            }
+        }
+
+        if options.compatibility && symbol.len() > PEP8_SYMBOL_LENGTH {
+            warnings.push(Warning::new(
+                definition.line,
+                format!(
+                    "symbol {symbol} is longer than the {PEP8_SYMBOL_LENGTH} characters allowed by Pep/8"
+                ),
+            ));
+        }
+
+        if report_unused
+            && definition.kind == SymbolKind::DataLabel
+            && definition.references.is_empty()
+        {
+            warnings.push(Warning::new(
+                definition.line,
+                format!("symbol {symbol} is never used"),
+            ));
+        }
+    }
+
+    warnings
+}
+
+pub fn assemble(
+    lines: Vec<SourceLine>,
+    options: &Options,
+) -> Result<Assembly, Box<dyn std::error::Error>> {
+    let (statement_lines, address_table) = parse_statements(lines, options)?;
+
+    encode(statement_lines, address_table, options)
+}
+
+/// Assembles statements built by a program rather than parsed from source,
+/// such as those of a `builder::Builder`.
+pub fn assemble_statements(
+    statement_lines: Vec<StatementLine>,
+    options: &Options,
+) -> Result<Assembly, Box<dyn std::error::Error>> {
+    let (statement_lines, address_table) = lay_out(statement_lines, options)?;
+
+    encode(statement_lines, address_table, options)
+}
+
+/// Checks that every symbol used is defined and encodes the laid out
+/// statements.
+fn encode(
+    statement_lines: Vec<StatementLine>,
+    address_table: AddressTable,
+    options: &Options,
+) -> Result<Assembly, Box<dyn std::error::Error>> {
+    let mut warnings = check_symbols(&address_table, options, true);
+    let mut byte_code = vec![];
+
+    for statement_line in &statement_lines {
+        let symbols = statement_line
+            .statement
+            .symbol_reference()
+            .map(|(_, symbol)| symbol)
+            .into_iter()
+            .chain(statement_line.statement.declared_symbol());
+
+        for symbol in symbols {
+            if address_table.get(symbol).is_none() {
+                return Err(Box::new(Error::UndefinedSymbol {
+                    symbol: symbol.clone(),
+                    line: statement_line.line,
+                    suggestion: suggest(
+                        symbol,
+                        address_table.iter().map(|(name, _)| name.as_str()),
+                    )
+                    .map(String::from),
+                }));
+            }
+        }
+    }
+
+    for statement_line in &statement_lines {
+        let bytes = statement_line
+            .as_bytes(&address_table, options.target)
+            .map_err(|error| Error::AtLine(statement_line.line, error))?;
 
         byte_code.extend(bytes);
     }
 
-    Ok(byte_code)
+    warnings.extend(trace::check(&statement_lines, &address_table));
+    warnings.sort_by_key(|warning| warning.line);
+
+    Ok(Assembly {
+        byte_code,
+        address_table,
+        statement_lines,
+        warnings,
+        origin: options.origin,
+    })
+}
+
+/// Assembles the lines into a relocatable object.
+///
+/// Only the symbols listed with `.EXPORT` are exported, so other labels stay
+/// private to the object, and every referenced label that is not defined is
+/// imported along with those listed with `.IMPORT`. Each operand or
+/// `.ADDRSS` that refers to a label gets a relocation entry so the linker can
+/// move the code. Symbols defined with `.EQUATE` are absolute and never
+/// relocated.
+///
+/// Unused labels are not reported since other objects may reference them.
+pub fn assemble_object(
+    lines: Vec<SourceLine>,
+    options: &Options,
+) -> Result<(ObjectFile, Vec<Warning>), Box<dyn std::error::Error>> {
+    let options = &Options {
+        origin: 0,
+        ..options.clone()
+    };
+    let (statement_lines, mut address_table) = parse_statements(lines, options)?;
+    let mut warnings = check_symbols(&address_table, options, false);
+    let mut object = ObjectFile::default();
+    let mut address = 0;
+    let listed_exports: Vec<&String> = statement_lines
+        .iter()
+        .filter_map(|statement_line| match &statement_line.statement {
+            Statement::DotCommand(DotCommand::EXPORT(symbol)) => Some(symbol),
+            _ => None,
+        })
+        .collect();
+
+    for statement_line in &statement_lines {
+        if let Some(symbol) = statement_line.statement.declared_symbol() {
+            if address_table.get(symbol).is_none() {
+                return Err(Box::new(Error::UndefinedSymbol {
+                    symbol: symbol.clone(),
+                    line: statement_line.line,
+                    suggestion: None,
+                }));
+            }
+        }
+
+        if let Statement::DotCommand(DotCommand::IMPORT(symbol)) = &statement_line.statement {
+            if !object.imports.contains(symbol) {
+                object.imports.push(symbol.clone());
+            }
+        }
+    }
+
+    object.exports = address_table
+        .iter()
+        .filter(|(_, definition)| !definition.is_predefined())
+        .filter(|(name, _)| listed_exports.contains(name))
+        .map(|(name, definition)| Symbol {
+            name: name.clone(),
+            value: definition.value,
+            absolute: definition.kind == SymbolKind::Equate,
+        })
+        .collect();
+    object.exports.sort_by_key(|symbol| symbol.value.as_u16());
+
+    for statement_line in &statement_lines {
+        if let Some((offset, symbol)) = statement_line.statement.symbol_reference() {
+            match address_table.definition(symbol) {
+                Some(definition) if definition.kind == SymbolKind::Equate => {}
+                Some(_) => object.relocations.push(Relocation {
+                    offset: (address + offset) as u16,
+                    symbol: None,
+                }),
+                None => {
+                    if !object.imports.contains(symbol) {
+                        object.imports.push(symbol.clone());
+                    }
+
+                    address_table.insert(
+                        symbol.clone(),
+                        SymbolDefinition::new(
+                            Pep8Word::new(0),
+                            SymbolKind::External,
+                            statement_line.line,
+                        ),
+                    );
+                    object.relocations.push(Relocation {
+                        offset: (address + offset) as u16,
+                        symbol: Some(symbol.clone()),
+                    });
+                }
+            }
+        }
+
+        address += statement_line.byte_size();
+    }
+
+    warnings.extend(trace::check(&statement_lines, &address_table));
+    warnings.sort_by_key(|warning| warning.line);
+
+    for statement_line in statement_lines {
+        let bytes = statement_line
+            .as_bytes(&address_table, options.target)
+            .map_err(|error| Error::AtLine(statement_line.line, error))?;
+
+        object.code.extend(bytes);
+    }
+
+    Ok((object, warnings))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::parse_source;
+
+    /// Source of each assembled line, rendered for the target.
+    fn canonical(source: &str, target: Target) -> String {
+        let options = Options {
+            target,
+            ..Options::default()
+        };
+        let assembly = assemble(parse_source(source).unwrap(), &options).unwrap();
+        let mut canonical = String::new();
+
+        for statement_line in &assembly.statement_lines {
+            canonical.push_str(&statement_line.to_source(target));
+            canonical.push('\n');
+        }
+
+        canonical
+    }
+
+    fn warnings(source: &str, options: &Options) -> Vec<String> {
+        assemble(parse_source(source).unwrap(), options)
+            .unwrap()
+            .warnings
+            .iter()
+            .map(|warning| format!("{}: {}", warning.line, warning.message))
+            .collect()
+    }
+
+    #[test]
+    fn warns_about_unused_data_labels_only() {
+        let source = "main:    LDA     used,d
+loop:    SUBA    1,i
+         STOP
+used:    .WORD   1
+unused:  .BLOCK  2
+offset:  .EQUATE 4
+         .END
+";
+
+        assert_eq!(
+            warnings(source, &Options::default()),
+            ["5: symbol unused is never used"]
+        );
+    }
+
+    #[test]
+    fn warns_about_symbols_differing_only_by_case() {
+        let source = "Num:     .WORD   1
+num:     .WORD   2
+main:    LDA     Num,d
+         ADDA    num,d
+         STOP
+         .END
+";
+
+        assert_eq!(
+            warnings(source, &Options::default()),
+            ["2: symbol num differs only by case from Num defined on line 1"]
+        );
+    }
+
+    #[test]
+    fn warns_about_long_symbols_in_compatibility_mode() {
+        let source = "longsymbol: .WORD 1
+main:    LDA     longsymbol,d
+         STOP
+         .END
+";
+        let compatibility = Options {
+            compatibility: true,
+            ..Options::default()
+        };
+
+        assert_eq!(warnings(source, &Options::default()), Vec::<String>::new());
+        assert_eq!(
+            warnings(source, &compatibility),
+            ["1: symbol longsymbol is longer than the 8 characters allowed by Pep/8"]
+        );
+    }
+
+    #[test]
+    fn rejects_duplicate_symbols() {
+        let source = "num:     .WORD   1
+num:     .WORD   2
+         .END
+";
+        let error = assemble(parse_source(source).unwrap(), &Options::default()).unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "line 2: duplicate symbol num, first defined on line 1"
+        );
+    }
+
+    #[test]
+    fn encodes_pep9_instructions_and_io_ports() {
+        let source = "main:    LDBA    charIn,d
+         STBA    charOut,d
+         STRO    msg,d
+         LDWX    5,sfx
+         CALL    sub
+         STOP
+sub:     RET
+msg:     .ASCII  \"hi\\x00\"
+         .END
+";
+        let options = Options {
+            target: Target::Pep9,
+            ..Options::default()
+        };
+        let assembly = assemble(parse_source(source).unwrap(), &options).unwrap();
+
+        assert_eq!(
+            assembly.byte_code,
+            [
+                0xD1, 0xFC, 0x15, // LDBA charIn,d
+                0xF1, 0xFC, 0x16, // STBA charOut,d
+                0x49, 0x00, 0x11, // STRO msg,d
+                0xCF, 0x00, 0x05, // LDWX 5,sfx
+                0x24, 0x00, 0x10, // CALL sub
+                0x00, // STOP
+                0x01, // RET
+                0x68, 0x69, 0x00, // .ASCII
+            ]
+        );
+    }
+
+    #[test]
+    fn renders_source_in_the_language_of_the_target() {
+        let pep8 = "main: LDA 0x0005,i\nLDBYTEX 'a',i\nRET0\n.END\n";
+        let pep9 = "main: LDWA 5,i\nLDBX 'a',i\nRET\n.END\n";
+
+        let rendered = canonical(pep8, Target::Pep8);
+        assert_eq!(
+            rendered,
+            "main:    LDA     5,i\n         LDBYTEX 97,i\n         RET0\n         .END\n"
+        );
+        assert_eq!(canonical(&rendered, Target::Pep8), rendered);
+
+        let rendered = canonical(pep9, Target::Pep9);
+        assert_eq!(
+            rendered,
+            "main:    LDWA    5,i\n         LDBX    97,i\n         RET\n         .END\n"
+        );
+        assert_eq!(canonical(&rendered, Target::Pep9), rendered);
+    }
+
+    #[test]
+    fn lays_out_pep10_sections_and_expands_system_calls() {
+        let source = "main:    @DECI   num,d
+         BR      done
+         .SECTION \"data\", \"rw\"
+num:     .BLOCK  2
+         .SECTION \".text\", \"rx\"
+done:    @DECO   num,d
+         RET
+         .END
+";
+        let options = Options {
+            target: Target::Pep10,
+            ..Options::default()
+        };
+        let assembly = assemble(parse_source(source).unwrap(), &options).unwrap();
+        let addresses: Vec<(usize, u16)> = assembly
+            .statement_lines
+            .iter()
+            .map(|statement_line| (statement_line.line(), statement_line.address()))
+            .collect();
+
+        assert_eq!(
+            assembly.byte_code,
+            [
+                0x38, 0x00, 0x00, // LDWT DECI,i
+                0x31, 0x00, 0x10, // SCALL num,d
+                0x1C, 0x00, 0x09, // BR done
+                0x38, 0x00, 0x01, // LDWT DECO,i
+                0x31, 0x00, 0x10, // SCALL num,d
+                0x00, // RET
+                0x00, 0x00, // .BLOCK 2
+            ]
+        );
+        assert_eq!(
+            addresses,
+            [
+                (1, 0),
+                (1, 3),
+                (2, 6),
+                (5, 9),
+                (6, 9),
+                (6, 12),
+                (7, 15),
+                (8, 16),
+                (3, 16),
+                (4, 16)
+            ]
+        );
+    }
 }
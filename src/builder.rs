@@ -0,0 +1,552 @@
+use std::fmt::Display;
+
+use crate::{
+    address::{AddrLocation, AddrMode, Address},
+    assembler::{assemble_statements, Assembly, Options, Statement, StatementLine},
+    dotcommand::DotCommand,
+    instruction::Instruction,
+    isa::{Opcode, Operand, Operation},
+    register::Register,
+    target::Target,
+    types::{Pep8Byte, Pep8Word},
+};
+
+#[derive(Debug)]
+pub enum Error {
+    InvalidLabel(String),
+    DuplicateLabel(String),
+    NoUniqueLabel(String),
+    LabelPlacedTwice(String),
+    UnplacedLabel(String),
+    NotInInstructionSet(Operation, Target),
+    MissingOperand(&'static str),
+    UnexpectedOperand(&'static str),
+    IllegalAddrMode {
+        mnemonic: &'static str,
+        mode: AddrMode,
+    },
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidLabel(name) => write!(f, "invalid label name: {name}"),
+            Self::DuplicateLabel(name) => write!(f, "label {name} already exists"),
+            Self::NoUniqueLabel(prefix) => write!(f, "no unused label name starts with {prefix}"),
+            Self::LabelPlacedTwice(name) => write!(f, "label {name} is placed more than once"),
+            Self::UnplacedLabel(name) => write!(f, "label {name} is used but never placed"),
+            Self::NotInInstructionSet(operation, target) => {
+                write!(f, "{operation:?} is not in the {target} instruction set")
+            }
+            Self::MissingOperand(mnemonic) => write!(f, "{mnemonic} requires an operand"),
+            Self::UnexpectedOperand(mnemonic) => write!(f, "{mnemonic} takes no operand"),
+            Self::IllegalAddrMode { mnemonic, mode } => {
+                write!(f, "illegal addressing mode for {mnemonic}: {mode}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Handle to a label of a program being built, which may be used as an
+/// operand before it is placed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Label(usize);
+
+/// Value of an operand or of a `.WORD`: a number, or the address of a label.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Value {
+    Number(u16),
+    Label(Label),
+}
+
+impl From<u16> for Value {
+    fn from(value: u16) -> Self {
+        Self::Number(value)
+    }
+}
+
+impl From<Label> for Value {
+    fn from(label: Label) -> Self {
+        Self::Label(label)
+    }
+}
+
+/// Instruction to emit: an operation along with the register or count its
+/// mnemonic selects, such as `LDr` with the accumulator for `LDA`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Op {
+    operation: Operation,
+    register: Option<Register>,
+    count: Option<u8>,
+}
+
+impl Op {
+    pub fn new(operation: Operation) -> Self {
+        Self {
+            operation,
+            register: None,
+            count: None,
+        }
+    }
+
+    pub fn register(self, register: Register) -> Self {
+        Self {
+            register: Some(register),
+            ..self
+        }
+    }
+
+    /// Sets the `n` of `NOPn` or `RETn`.
+    pub fn count(self, count: u8) -> Self {
+        Self {
+            count: Some(count),
+            ..self
+        }
+    }
+}
+
+impl From<Operation> for Op {
+    fn from(operation: Operation) -> Self {
+        Self::new(operation)
+    }
+}
+
+enum Item {
+    Instruction {
+        opcode: &'static Opcode,
+        operand: Option<(Value, AddrMode)>,
+    },
+    Ascii(Vec<u8>),
+    Block(usize),
+    Byte(u8),
+    Word(Value),
+    Equate(u16),
+}
+
+struct Entry {
+    label: Option<Label>,
+    item: Item,
+}
+
+/// Builds a program statement by statement, checking each instruction
+/// against the target's instruction set as it is emitted.
+///
+/// Labels are handles created up front and placed on the next statement
+/// emitted, so they can be used before they are placed. Labels placed at
+/// the same address share the name of the first one.
+pub struct Builder {
+    target: Target,
+    names: Vec<String>,
+    /// Label whose name each label is spelled with once placed.
+    placements: Vec<Option<Label>>,
+    pending: Option<Label>,
+    entries: Vec<Entry>,
+}
+
+/// Whether the lexer reads `name` as a single identifier.
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+impl Builder {
+    pub fn new(target: Target) -> Self {
+        Self {
+            target,
+            names: vec![],
+            placements: vec![],
+            pending: None,
+            entries: vec![],
+        }
+    }
+
+    fn add_label(&mut self, name: String) -> Label {
+        self.names.push(name);
+        self.placements.push(None);
+
+        Label(self.names.len() - 1)
+    }
+
+    /// Creates a label with the given name, to be placed later.
+    pub fn label(&mut self, name: &str) -> Result<Label, Error> {
+        if !is_identifier(name) {
+            return Err(Error::InvalidLabel(name.to_string()));
+        }
+
+        if self.names.iter().any(|other| other == name) {
+            return Err(Error::DuplicateLabel(name.to_string()));
+        }
+
+        Ok(self.add_label(name.to_string()))
+    }
+
+    /// Creates a label named after `prefix` followed by the first number
+    /// giving a name not used yet, such as `else3`.
+    pub fn unique_label(&mut self, prefix: &str) -> Result<Label, Error> {
+        if !is_identifier(prefix) {
+            return Err(Error::InvalidLabel(prefix.to_string()));
+        }
+
+        // One more number is tried than there are labels, so one of them
+        // gives an unused name unless the numbers overflow.
+        let first = self.names.len();
+        let name = (first..=first.saturating_mul(2))
+            .map(|number| format!("{prefix}{number}"))
+            .find(|name| !self.names.contains(name))
+            .ok_or_else(|| Error::NoUniqueLabel(prefix.to_string()))?;
+
+        Ok(self.add_label(name))
+    }
+
+    /// Places the label on the next statement emitted.
+    pub fn place(&mut self, label: Label) -> Result<&mut Self, Error> {
+        if self.placements[label.0].is_some() {
+            return Err(Error::LabelPlacedTwice(self.names[label.0].clone()));
+        }
+
+        let placement = *self.pending.get_or_insert(label);
+
+        self.placements[label.0] = Some(placement);
+
+        Ok(self)
+    }
+
+    fn push(&mut self, item: Item) -> &mut Self {
+        self.entries.push(Entry {
+            label: self.pending.take(),
+            item,
+        });
+
+        self
+    }
+
+    fn instruction(
+        &mut self,
+        op: Op,
+        operand: Option<(Value, AddrMode)>,
+    ) -> Result<&mut Self, Error> {
+        let opcode = self
+            .target
+            .opcodes()
+            .iter()
+            .find(|opcode| {
+                opcode.operation == op.operation
+                    && opcode.register == op.register
+                    && opcode.count == op.count
+            })
+            .ok_or(Error::NotInInstructionSet(op.operation, self.target))?;
+
+        match (opcode.operand, &operand) {
+            (Operand::None, Some(_)) => return Err(Error::UnexpectedOperand(opcode.mnemonic)),
+            (Operand::Short | Operand::Long, None) => {
+                return Err(Error::MissingOperand(opcode.mnemonic))
+            }
+            (_, Some((_, mode))) if !opcode.modes.contains(mode) => {
+                return Err(Error::IllegalAddrMode {
+                    mnemonic: opcode.mnemonic,
+                    mode: mode.clone(),
+                })
+            }
+            _ => {}
+        }
+
+        Ok(self.push(Item::Instruction { opcode, operand }))
+    }
+
+    /// Emits an instruction without an operand.
+    pub fn unary(&mut self, op: impl Into<Op>) -> Result<&mut Self, Error> {
+        self.instruction(op.into(), None)
+    }
+
+    /// Emits an instruction with an operand in the given addressing mode.
+    pub fn nonunary(
+        &mut self,
+        op: impl Into<Op>,
+        value: impl Into<Value>,
+        mode: AddrMode,
+    ) -> Result<&mut Self, Error> {
+        self.instruction(op.into(), Some((value.into(), mode)))
+    }
+
+    pub fn ascii(&mut self, value: &[u8]) -> &mut Self {
+        self.push(Item::Ascii(value.to_vec()))
+    }
+
+    pub fn block(&mut self, size: usize) -> &mut Self {
+        self.push(Item::Block(size))
+    }
+
+    pub fn byte(&mut self, value: u8) -> &mut Self {
+        self.push(Item::Byte(value))
+    }
+
+    /// Emits a word holding a number, or the address of a label as
+    /// `.ADDRSS` does.
+    pub fn word(&mut self, value: impl Into<Value>) -> &mut Self {
+        self.push(Item::Word(value.into()))
+    }
+
+    /// Defines a symbol for a constant with `.EQUATE`, leaving any label
+    /// placed for the next statement.
+    pub fn equate(&mut self, name: &str, value: u16) -> Result<Label, Error> {
+        let label = self.label(name)?;
+
+        self.placements[label.0] = Some(label);
+        self.entries.push(Entry {
+            label: Some(label),
+            item: Item::Equate(value),
+        });
+
+        Ok(label)
+    }
+
+    fn name(&self, label: Label) -> Result<String, Error> {
+        match self.placements[label.0] {
+            Some(placement) => Ok(self.names[placement.0].clone()),
+            None => Err(Error::UnplacedLabel(self.names[label.0].clone())),
+        }
+    }
+
+    fn location(&self, value: Value) -> Result<AddrLocation, Error> {
+        match value {
+            Value::Number(number) => Ok(AddrLocation::Memory(Pep8Word::new(number))),
+            Value::Label(label) => Ok(AddrLocation::Label(self.name(label)?)),
+        }
+    }
+
+    fn statement(&self, item: &Item) -> Result<Statement, Error> {
+        Ok(match item {
+            Item::Instruction { opcode, operand } => {
+                let address = match operand {
+                    Some((value, mode)) => Some(Address {
+                        location: self.location(*value)?,
+                        mode: mode.clone(),
+                    }),
+                    None => None,
+                };
+
+                Statement::Instruction(Instruction::from_opcode(opcode, address))
+            }
+            Item::Ascii(value) => Statement::DotCommand(DotCommand::ASCII(value.clone())),
+            Item::Block(size) => Statement::DotCommand(DotCommand::BLOCK(*size)),
+            Item::Byte(value) => Statement::DotCommand(DotCommand::BYTE(Pep8Byte::from(value))),
+            Item::Word(Value::Number(value)) => {
+                Statement::DotCommand(DotCommand::WORD(Pep8Word::new(*value)))
+            }
+            Item::Word(Value::Label(label)) => {
+                Statement::DotCommand(DotCommand::ADDRSS(self.name(*label)?))
+            }
+            Item::Equate(value) => Statement::DotCommand(DotCommand::EQUATE(Pep8Word::new(*value))),
+        })
+    }
+
+    /// Statements of the program, ending with `.END`, each reported as being
+    /// on the line it takes in `to_source`.
+    pub fn statement_lines(&self) -> Result<Vec<StatementLine>, Error> {
+        let mut statement_lines = vec![];
+
+        for (index, entry) in self.entries.iter().enumerate() {
+            let label = match entry.label {
+                Some(label) => Some(self.name(label)?),
+                None => None,
+            };
+
+            statement_lines.push(StatementLine::new(
+                label,
+                self.statement(&entry.item)?,
+                index + 1,
+            ));
+        }
+
+        let label = match self.pending {
+            Some(label) => Some(self.name(label)?),
+            None => None,
+        };
+
+        statement_lines.push(StatementLine::new(
+            label,
+            Statement::DotCommand(DotCommand::END),
+            self.entries.len() + 1,
+        ));
+
+        Ok(statement_lines)
+    }
+
+    /// Source of the program in the target's assembly language.
+    pub fn to_source(&self) -> Result<String, Error> {
+        let mut source = String::new();
+
+        for statement_line in self.statement_lines()? {
+            source.push_str(&statement_line.to_source(self.target));
+            source.push('\n');
+        }
+
+        Ok(source)
+    }
+
+    /// Assembles the program without going through its source.
+    pub fn assemble(&self) -> Result<Assembly, Box<dyn std::error::Error>> {
+        let options = Options {
+            target: self.target,
+            ..Options::default()
+        };
+
+        assemble_statements(self.statement_lines()?, &options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        address::AddrMode::*, assembler::assemble, isa::Operation::*, lexer::parse_source,
+        register::Register::*,
+    };
+
+    /// Checks that the program's source assembles to the bytes the builder
+    /// assembles, and returns the source.
+    fn round_trip(program: &Builder) -> String {
+        let source = program.to_source().unwrap();
+        let options = Options {
+            target: program.target,
+            ..Options::default()
+        };
+        let reassembled = assemble(parse_source(&source).unwrap(), &options).unwrap();
+
+        assert_eq!(reassembled.byte_code, program.assemble().unwrap().byte_code);
+
+        source
+    }
+
+    #[test]
+    fn builds_source_that_reassembles_to_the_same_bytes() {
+        let mut program = Builder::new(Target::Pep8);
+        let num = program.unique_label("num").unwrap();
+        let main = program.label("main").unwrap();
+        let done = program.unique_label("done").unwrap();
+        let message = program.label("msg").unwrap();
+
+        program.nonunary(BR, main, Immediate).unwrap();
+        program.place(num).unwrap().block(2);
+        program.place(message).unwrap().ascii(b"Hi\n\x00");
+        program.word(message).byte(7);
+        program.equate("limit", 10).unwrap();
+        program
+            .place(main)
+            .unwrap()
+            .nonunary(DECI, num, Direct)
+            .unwrap();
+        program
+            .nonunary(Op::new(CPr).register(Accumulator), 0x0A, Immediate)
+            .unwrap();
+        program.nonunary(BRGE, done, Immediate).unwrap();
+        program
+            .nonunary(Op::new(LDBYTEr).register(IndexRegister), num, Direct)
+            .unwrap();
+        program.unary(Op::new(RETn).count(2)).unwrap();
+        program
+            .place(done)
+            .unwrap()
+            .nonunary(STRO, message, Direct)
+            .unwrap();
+        program.unary(STOP).unwrap();
+
+        assert_eq!(
+            round_trip(&program),
+            "         BR      main,i
+num0:    .BLOCK  2
+msg:     .ASCII  \"Hi\\n\\x00\"
+         .ADDRSS msg
+         .BYTE   7
+limit:   .EQUATE 10
+main:    DECI    num0,d
+         CPA     10,i
+         BRGE    done2,i
+         LDBYTEX num0,d
+         RET2
+done2:   STRO    msg,d
+         STOP
+         .END
+"
+        );
+    }
+
+    #[test]
+    fn spells_instructions_for_the_target() {
+        let mut program = Builder::new(Target::Pep9);
+        let first = program.label("first").unwrap();
+        let second = program.label("second").unwrap();
+
+        program.place(first).unwrap().place(second).unwrap();
+        program
+            .nonunary(Op::new(LDr).register(Accumulator), second, Immediate)
+            .unwrap();
+        program
+            .nonunary(
+                Op::new(STr).register(IndexRegister),
+                4,
+                StackIndexedDeferred,
+            )
+            .unwrap();
+        program.unary(Op::new(RETn).count(0)).unwrap();
+
+        assert_eq!(
+            round_trip(&program),
+            "first:   LDWA    first,i
+         STWX    4,sfx
+         RET
+         .END
+"
+        );
+        assert!(matches!(
+            program.unary(CHARI),
+            Err(Error::NotInInstructionSet(CHARI, Target::Pep9))
+        ));
+    }
+
+    #[test]
+    fn reports_misused_labels_and_operands() {
+        let mut program = Builder::new(Target::Pep8);
+        let used = program.label("used").unwrap();
+
+        assert!(matches!(
+            program.label("used"),
+            Err(Error::DuplicateLabel(_))
+        ));
+        assert!(matches!(program.label("2x"), Err(Error::InvalidLabel(_))));
+        assert!(matches!(
+            program.unique_label("a b"),
+            Err(Error::InvalidLabel(_))
+        ));
+        assert!(matches!(
+            program.nonunary(STOP, 0, Immediate),
+            Err(Error::UnexpectedOperand("STOP"))
+        ));
+        assert!(matches!(
+            program.unary(DECI),
+            Err(Error::MissingOperand("DECI"))
+        ));
+        assert!(matches!(
+            program.nonunary(Op::new(STr).register(Accumulator), 0, Immediate),
+            Err(Error::IllegalAddrMode {
+                mnemonic: "STA",
+                ..
+            })
+        ));
+
+        program.nonunary(BR, used, Immediate).unwrap();
+        assert!(matches!(program.to_source(), Err(Error::UnplacedLabel(_))));
+
+        program.place(used).unwrap().unary(STOP).unwrap();
+        assert!(matches!(
+            program.place(used),
+            Err(Error::LabelPlacedTwice(_))
+        ));
+    }
+}
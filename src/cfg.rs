@@ -0,0 +1,331 @@
+use std::collections::{BTreeSet, HashMap};
+
+use crate::{
+    address::{AddrMode, AddressTable},
+    assembler::{Assembly, Statement},
+    instruction::Instruction,
+    isa::Operation,
+};
+
+/// How control leaves an instruction.
+///
+/// Targets are only known for operands given in immediate mode, and are
+/// `None` for jumps through tables or pointers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Exit {
+    /// Continues with the next statement.
+    Next,
+    Jump(Option<u16>),
+    /// Conditional branch, which may also continue with the next statement.
+    Branch(Option<u16>),
+    /// Subroutine call, which returns to the next statement.
+    Call(Option<u16>),
+    Return,
+    Stop,
+}
+
+impl Exit {
+    pub fn of(instruction: &Instruction, address_table: &AddressTable) -> Self {
+        let target = instruction
+            .address()
+            .and_then(|address| match address.mode {
+                AddrMode::Immediate => address_table.resolve(address).map(|value| value.as_u16()),
+                _ => None,
+            });
+
+        match instruction.operation {
+            Operation::STOP => Self::Stop,
+            Operation::RETTR | Operation::SRET | Operation::RETn => Self::Return,
+            Operation::BR => Self::Jump(target),
+            Operation::BRLE
+            | Operation::BRLT
+            | Operation::BREQ
+            | Operation::BRNE
+            | Operation::BRGE
+            | Operation::BRGT
+            | Operation::BRV
+            | Operation::BRC => Self::Branch(target),
+            Operation::CALL => Self::Call(target),
+            _ => Self::Next,
+        }
+    }
+}
+
+/// Main program or subroutine, entered at the statement `entry`.
+pub struct Routine {
+    pub name: String,
+    pub entry: usize,
+    /// Whether the routine is entered with `CALL` rather than being the main
+    /// program.
+    pub called: bool,
+}
+
+/// Run of instructions executed in sequence, only entered at the first one
+/// and only left after the last one.
+pub struct Block {
+    pub statements: Vec<usize>,
+}
+
+impl Block {
+    pub fn last(&self) -> usize {
+        *self.statements.last().expect("blocks are never empty")
+    }
+}
+
+/// Control flow between the statements of an assembly, identified by their
+/// index in `Assembly::statement_lines`.
+pub struct Graph<'a> {
+    assembly: &'a Assembly,
+    /// Index of the statement starting at each address that emits bytes.
+    statements: HashMap<u16, usize>,
+}
+
+impl<'a> Graph<'a> {
+    pub fn new(assembly: &'a Assembly) -> Self {
+        let statements = assembly
+            .statement_lines
+            .iter()
+            .enumerate()
+            .filter(|(_, statement_line)| statement_line.byte_size() > 0)
+            .map(|(index, statement_line)| (statement_line.address(), index))
+            .collect();
+
+        Self {
+            assembly,
+            statements,
+        }
+    }
+
+    /// Index of the statement emitting the byte at `address`, if one starts
+    /// there.
+    pub fn statement_at(&self, address: u16) -> Option<usize> {
+        self.statements.get(&address).copied()
+    }
+
+    pub fn instruction(&self, index: usize) -> Option<&'a Instruction> {
+        match self.assembly.statement_lines[index].statement() {
+            Statement::Instruction(instruction) => Some(instruction),
+            Statement::DotCommand(_) => None,
+        }
+    }
+
+    /// How control leaves the statement, or `None` if it is not an
+    /// instruction.
+    pub fn exit(&self, index: usize) -> Option<Exit> {
+        self.instruction(index)
+            .map(|instruction| Exit::of(instruction, &self.assembly.address_table))
+    }
+
+    /// Index of the statement laid out right after this one, skipping
+    /// statements that emit no bytes.
+    pub fn next(&self, index: usize) -> Option<usize> {
+        let statement_line = &self.assembly.statement_lines[index];
+        let address = statement_line.address() as usize + statement_line.byte_size();
+
+        u16::try_from(address)
+            .ok()
+            .and_then(|address| self.statement_at(address))
+    }
+
+    /// Statements that may execute right after this one within the same
+    /// subroutine. Calls are assumed to return to the next statement, and
+    /// jumps to unknown targets have no successors.
+    pub fn successors(&self, index: usize) -> Vec<usize> {
+        let target = |target: Option<u16>| target.and_then(|address| self.statement_at(address));
+
+        match self.exit(index) {
+            None | Some(Exit::Return) | Some(Exit::Stop) => vec![],
+            Some(Exit::Next) | Some(Exit::Call(_)) => self.next(index).into_iter().collect(),
+            Some(Exit::Jump(address)) => target(address).into_iter().collect(),
+            Some(Exit::Branch(address)) => {
+                let mut successors: Vec<_> = self.next(index).into_iter().collect();
+
+                successors.extend(target(address).filter(|target| !successors.contains(target)));
+                successors
+            }
+        }
+    }
+
+    /// Marks the statements reachable from `entry`, entering called
+    /// subroutines if `follow_calls` is set. Data statements reached by
+    /// control flow are marked but not followed.
+    pub fn reachable(&self, entry: usize, follow_calls: bool) -> Vec<bool> {
+        let mut reached = vec![false; self.assembly.statement_lines.len()];
+        let mut pending = vec![entry];
+
+        while let Some(index) = pending.pop() {
+            if std::mem::replace(&mut reached[index], true) {
+                continue;
+            }
+
+            pending.extend(self.successors(index));
+
+            if let Some(Exit::Call(Some(address))) = self.exit(index) {
+                if follow_calls {
+                    pending.extend(self.statement_at(address));
+                }
+            }
+        }
+
+        reached
+    }
+
+    /// Main program, starting at address 0, followed by the subroutines
+    /// called with an immediate operand, in address order.
+    pub fn routines(&self) -> Vec<Routine> {
+        let mut routines: Vec<_> = self
+            .statement_at(0)
+            .map(|entry| Routine {
+                name: String::from("main"),
+                entry,
+                called: false,
+            })
+            .into_iter()
+            .collect();
+        let targets: BTreeSet<u16> = (0..self.assembly.statement_lines.len())
+            .filter_map(|index| match self.exit(index) {
+                Some(Exit::Call(Some(address))) => Some(address),
+                _ => None,
+            })
+            .collect();
+
+        for address in targets {
+            if let Some(entry) = self.statement_at(address) {
+                routines.push(Routine {
+                    name: self.assembly.statement_lines[entry]
+                        .label()
+                        .cloned()
+                        .unwrap_or_else(|| format!("{address:04X}")),
+                    entry,
+                    called: true,
+                });
+            }
+        }
+
+        routines
+    }
+
+    /// Splits the instructions reachable from `entry` without entering calls
+    /// into basic blocks, in address order. Blocks start at the entry, at
+    /// labels and at branch targets, and end after any transfer of control.
+    pub fn blocks(&self, entry: usize) -> Vec<Block> {
+        let reachable = self.reachable(entry, false);
+        let mut leaders = vec![false; reachable.len()];
+
+        leaders[entry] = true;
+
+        for index in (0..reachable.len()).filter(|index| reachable[*index]) {
+            let statement_line = &self.assembly.statement_lines[index];
+
+            if statement_line.label().is_some() {
+                leaders[index] = true;
+            }
+
+            if self.exit(index) != Some(Exit::Next) {
+                for successor in self.successors(index) {
+                    leaders[successor] = true;
+                }
+            }
+        }
+
+        let mut blocks: Vec<Block> = vec![];
+
+        for index in (0..reachable.len()).filter(|index| reachable[*index]) {
+            if self.instruction(index).is_none() {
+                continue;
+            }
+
+            match blocks.last_mut() {
+                Some(block)
+                    if !leaders[index]
+                        && self.exit(block.last()) == Some(Exit::Next)
+                        && self.next(block.last()) == Some(index) =>
+                {
+                    block.statements.push(index)
+                }
+                _ => blocks.push(Block {
+                    statements: vec![index],
+                }),
+            }
+        }
+
+        blocks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        assembler::{assemble, Options},
+        lexer::parse_source,
+    };
+
+    const SOURCE: &str = "         BR      main
+num:     .WORD   0
+main:    LDA     0,i
+loop:    CALL    inc
+         CPA     5,i
+         BRLT    loop
+         STA     num,d
+         STOP
+inc:     ADDA    1,i
+         CALL    27
+         RET0
+         .END
+";
+
+    /// Source lines of each block of the routine entered at `entry`.
+    fn block_lines(graph: &Graph, assembly: &Assembly, entry: usize) -> Vec<Vec<usize>> {
+        graph
+            .blocks(entry)
+            .iter()
+            .map(|block| {
+                block
+                    .statements
+                    .iter()
+                    .map(|index| assembly.statement_lines[*index].line())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn finds_the_main_program_and_called_subroutines() {
+        let assembly = assemble(parse_source(SOURCE).unwrap(), &Options::default()).unwrap();
+        let graph = Graph::new(&assembly);
+        let routines: Vec<(String, usize, bool)> = graph
+            .routines()
+            .into_iter()
+            .map(|routine| {
+                let line = assembly.statement_lines[routine.entry].line();
+
+                (routine.name, line, routine.called)
+            })
+            .collect();
+
+        assert_eq!(
+            routines,
+            [
+                (String::from("main"), 1, false),
+                (String::from("inc"), 9, true),
+                (String::from("001B"), 11, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn splits_routines_into_basic_blocks() {
+        let assembly = assemble(parse_source(SOURCE).unwrap(), &Options::default()).unwrap();
+        let graph = Graph::new(&assembly);
+
+        assert_eq!(
+            block_lines(&graph, &assembly, graph.statement_at(0).unwrap()),
+            [vec![1], vec![3], vec![4], vec![5, 6], vec![7, 8]]
+        );
+        assert_eq!(
+            block_lines(&graph, &assembly, graph.statement_at(0x0015).unwrap()),
+            [vec![9, 10], vec![11]]
+        );
+    }
+}
@@ -0,0 +1,922 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    io::{BufRead, Write},
+    path::PathBuf,
+    sync::mpsc::{self, Receiver},
+};
+
+use serde_json::{json, Value};
+
+use crate::{
+    address::SymbolKind,
+    assembler::{assemble, Assembly, Options, Statement},
+    lexer::parse_file,
+    message::{read_message, write_message, Error},
+    simulator::{Simulator, Transfer},
+    trace::{Cell, MemoryTrace},
+};
+
+/// Instructions executed by a single request before the program is paused,
+/// so that an infinite loop cannot hang the debugger.
+const STEP_LIMIT: usize = 10_000_000;
+
+/// Instructions executed between checks for a `pause` request.
+const PAUSE_CHECK_INTERVAL: usize = 1000;
+
+const THREAD_ID: u64 = 1;
+
+const REGISTERS_REFERENCE: u64 = 1;
+const FLAGS_REFERENCE: u64 = 2;
+const GLOBALS_REFERENCE: u64 = 3;
+const STACK_REFERENCE: u64 = 4;
+const HEAP_REFERENCE: u64 = 5;
+/// Reference of the first stack frame drawn by the memory trace, the others
+/// following in order.
+const FRAMES_REFERENCE: u64 = 100;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum RunMode {
+    Continue,
+    StepIn,
+    StepOver,
+    StepOut,
+}
+
+enum Stop {
+    Paused(&'static str),
+    Halted,
+    Failed(String),
+}
+
+/// A subroutine entered with `CALL`.
+struct CallFrame {
+    entry: u16,
+    return_address: u16,
+}
+
+struct Session {
+    source: PathBuf,
+    assembly: Assembly,
+    simulator: Simulator,
+    trace: MemoryTrace,
+    breakpoints: HashSet<u16>,
+    call_stack: Vec<CallFrame>,
+    stop_on_entry: bool,
+    /// Address the program last stopped at, whose breakpoint is skipped
+    /// when it resumes from there.
+    stopped_at: Option<u16>,
+}
+
+impl Session {
+    fn launch(arguments: &Value) -> Result<Self, Box<dyn std::error::Error>> {
+        let source = PathBuf::from(arguments["program"].as_str().ok_or("missing program")?);
+        let assembly = assemble(parse_file(&source)?, &Options::default())?;
+        let input = arguments["input"].as_str().unwrap_or_default();
+        let simulator = Simulator::new(&assembly.byte_code, input.as_bytes())?;
+        let trace = MemoryTrace::new(&assembly);
+
+        Ok(Self {
+            source,
+            assembly,
+            simulator,
+            trace,
+            breakpoints: HashSet::new(),
+            call_stack: vec![],
+            stop_on_entry: arguments["stopOnEntry"] == true,
+            stopped_at: None,
+        })
+    }
+
+    /// Address of the first instruction on or after `line`, along with the
+    /// line it is on.
+    fn instruction_at_or_after(&self, line: usize) -> Option<(u16, usize)> {
+        self.assembly
+            .statement_lines
+            .iter()
+            .filter(|statement_line| {
+                matches!(statement_line.statement(), Statement::Instruction(_))
+            })
+            .find(|statement_line| statement_line.line() >= line)
+            .map(|statement_line| (statement_line.address(), statement_line.line()))
+    }
+
+    /// Replaces the breakpoints, moving each one to the first instruction on
+    /// or after its line.
+    fn set_breakpoints(&mut self, requested: &[Value]) -> Vec<Value> {
+        let mut breakpoints = vec![];
+
+        self.breakpoints.clear();
+
+        for breakpoint in requested {
+            let line = breakpoint["line"].as_u64().unwrap_or_default() as usize;
+
+            match self.instruction_at_or_after(line) {
+                Some((address, line)) => {
+                    self.breakpoints.insert(address);
+                    breakpoints.push(json!({ "verified": true, "line": line }));
+                }
+                None => breakpoints.push(json!({ "verified": false, "line": line })),
+            }
+        }
+
+        breakpoints
+    }
+
+    /// Source line of the instruction containing `address`.
+    fn line_of(&self, address: u16) -> Option<usize> {
+        self.assembly
+            .statement_lines
+            .iter()
+            .filter(|statement_line| statement_line.byte_size() > 0)
+            .take_while(|statement_line| statement_line.address() <= address)
+            .last()
+            .map(|statement_line| statement_line.line())
+    }
+
+    /// Name of the code label at `address`, or the address itself.
+    fn name_of(&self, address: u16) -> String {
+        self.assembly
+            .address_table
+            .iter()
+            .find(|(_, definition)| {
+                definition.kind == SymbolKind::CodeLabel && definition.value.as_u16() == address
+            })
+            .map(|(name, _)| name.clone())
+            .unwrap_or_else(|| format!("{address:04X}"))
+    }
+
+    fn step(&mut self) -> Result<(), String> {
+        let transfer = self
+            .trace
+            .step(&mut self.simulator)
+            .map_err(|error| error.to_string())?;
+
+        match transfer {
+            Transfer::Call { return_address } => self.call_stack.push(CallFrame {
+                entry: self.simulator.cpu.pc,
+                return_address,
+            }),
+            Transfer::Return => {
+                self.call_stack.pop();
+            }
+            Transfer::None => {}
+        }
+
+        Ok(())
+    }
+
+    /// Records that the program stopped at the current instruction.
+    fn stop(&mut self, reason: &'static str) -> Stop {
+        self.stopped_at = Some(self.simulator.cpu.pc);
+
+        Stop::Paused(reason)
+    }
+
+    /// Runs until the mode is done, a breakpoint is reached, or `paused`,
+    /// which is polled every few instructions, returns true. A breakpoint on
+    /// the first instruction stops the program unless it is resuming from a
+    /// stop there.
+    fn run(&mut self, mode: RunMode, mut paused: impl FnMut() -> bool) -> Stop {
+        let resumed_from = self.stopped_at.take();
+        let depth = self.call_stack.len();
+        let steps_over_call = mode == RunMode::StepOver
+            && self.simulator.read_byte(self.simulator.cpu.pc) & 0b11111110 == 0b00010110;
+
+        for count in 0..STEP_LIMIT {
+            if self.simulator.halted() {
+                return Stop::Halted;
+            }
+
+            let pc = self.simulator.cpu.pc;
+
+            if count > 0 && count % PAUSE_CHECK_INTERVAL == 0 && paused() {
+                return self.stop("pause");
+            }
+
+            if (count > 0 || resumed_from != Some(pc)) && self.breakpoints.contains(&pc) {
+                return self.stop("breakpoint");
+            }
+
+            if let Err(message) = self.step() {
+                return Stop::Failed(message);
+            }
+
+            if self.simulator.halted() {
+                return Stop::Halted;
+            }
+
+            let done = match mode {
+                RunMode::Continue => false,
+                RunMode::StepIn => true,
+                RunMode::StepOver => !steps_over_call || self.call_stack.len() <= depth,
+                RunMode::StepOut => self.call_stack.len() < depth,
+            };
+
+            if done {
+                return self.stop("step");
+            }
+        }
+
+        self.stop("pause")
+    }
+
+    fn stack_trace(&self) -> Vec<Value> {
+        let source = json!({
+            "name": self.source.file_name().map(|name| name.to_string_lossy()),
+            "path": self.source,
+        });
+        let mut frames = vec![];
+        let mut address = self.simulator.cpu.pc;
+
+        for (index, frame) in self.call_stack.iter().enumerate().rev() {
+            frames.push(json!({
+                "id": index + 1,
+                "name": self.name_of(frame.entry),
+                "line": self.line_of(address).unwrap_or(0),
+                "column": 1,
+                "source": source,
+            }));
+
+            address = frame.return_address.wrapping_sub(3);
+        }
+
+        frames.push(json!({
+            "id": 0,
+            "name": "main",
+            "line": self.line_of(address).unwrap_or(0),
+            "column": 1,
+            "source": source,
+        }));
+
+        frames
+    }
+
+    fn variables(&self, reference: u64) -> Vec<Value> {
+        let cpu = &self.simulator.cpu;
+        let word = |name: &str, value: u16| {
+            json!({
+                "name": name,
+                "value": format!("0x{value:04X} ({})", value as i16),
+                "variablesReference": 0,
+            })
+        };
+        let flag = |name: &str, value: bool| json!({ "name": name, "value": (value as u8).to_string(), "variablesReference": 0 });
+        let cell = |cell: &Cell| {
+            json!({
+                "name": cell.name,
+                "value": cell.value(&self.simulator),
+                "variablesReference": 0,
+            })
+        };
+
+        match reference {
+            REGISTERS_REFERENCE => vec![
+                word("A", cpu.a),
+                word("X", cpu.x),
+                word("SP", cpu.sp),
+                word("PC", cpu.pc),
+            ],
+            FLAGS_REFERENCE => vec![
+                flag("N", cpu.flags.n),
+                flag("Z", cpu.flags.z),
+                flag("V", cpu.flags.v),
+                flag("C", cpu.flags.c),
+            ],
+            GLOBALS_REFERENCE => self
+                .assembly
+                .address_table
+                .iter()
+                .filter(|(_, definition)| definition.kind == SymbolKind::DataLabel)
+                .map(|(name, definition)| {
+                    match self
+                        .trace
+                        .globals()
+                        .iter()
+                        .find(|global| &global.name == name)
+                    {
+                        Some(global) => cell(global),
+                        None => word(name, self.simulator.read_word(definition.value.as_u16())),
+                    }
+                })
+                .collect(),
+            STACK_REFERENCE => self
+                .trace
+                .frames()
+                .iter()
+                .enumerate()
+                .rev()
+                .map(|(index, frame)| {
+                    json!({
+                        "name": frame.name,
+                        "value": format!("{} cells", frame.cells.len()),
+                        "variablesReference": FRAMES_REFERENCE + index as u64,
+                    })
+                })
+                .collect(),
+            HEAP_REFERENCE => self.trace.heap().iter().map(cell).collect(),
+            reference => reference
+                .checked_sub(FRAMES_REFERENCE)
+                .and_then(|index| self.trace.frames().get(index as usize))
+                .map(|frame| frame.cells.iter().map(cell).collect())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Result of reading a request, `None` once the input is closed.
+type Incoming = Result<Option<Value>, Error>;
+
+/// Debug adapter running Pep/8 programs in the simulator, speaking DAP over
+/// any reader and writer pair.
+pub struct Server<W: Write> {
+    writer: W,
+    seq: u64,
+    session: Option<Session>,
+    /// Breakpoints set before the program is launched, applied on launch.
+    pending_breakpoints: Vec<Value>,
+    /// Requests read on another thread, so that a `pause` can be seen while
+    /// the program runs.
+    incoming: Option<Receiver<Incoming>>,
+    /// Requests read while the program ran, to be handled once it stops.
+    deferred: VecDeque<Incoming>,
+}
+
+impl<W: Write> Server<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            seq: 0,
+            session: None,
+            pending_breakpoints: vec![],
+            incoming: None,
+            deferred: VecDeque::new(),
+        }
+    }
+
+    /// Handles requests until the client disconnects or closes the input.
+    pub fn run<R: BufRead + Send + 'static>(&mut self, mut reader: R) -> Result<(), Error> {
+        let (sender, receiver) = mpsc::channel();
+
+        std::thread::spawn(move || loop {
+            let incoming = read_message(&mut reader);
+            let more = matches!(incoming, Ok(Some(_)));
+
+            if sender.send(incoming).is_err() || !more {
+                break;
+            }
+        });
+        self.incoming = Some(receiver);
+
+        loop {
+            let incoming = match self.deferred.pop_front() {
+                Some(incoming) => incoming,
+                None => match self
+                    .incoming
+                    .as_ref()
+                    .and_then(|receiver| receiver.recv().ok())
+                {
+                    Some(incoming) => incoming,
+                    None => break,
+                },
+            };
+
+            match incoming? {
+                Some(request) if self.handle(&request)? => {}
+                _ => break,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn send(&mut self, mut message: Value) -> Result<(), Error> {
+        self.seq += 1;
+        message["seq"] = json!(self.seq);
+
+        write_message(&mut self.writer, &message)
+    }
+
+    fn respond(&mut self, request: &Value, body: Result<Value, String>) -> Result<(), Error> {
+        let mut response = json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": body.is_ok(),
+        });
+
+        match body {
+            Ok(body) => response["body"] = body,
+            Err(message) => response["message"] = json!(message),
+        }
+
+        self.send(response)
+    }
+
+    fn event(&mut self, event: &str, body: Value) -> Result<(), Error> {
+        self.send(json!({ "type": "event", "event": event, "body": body }))
+    }
+
+    /// Handles one request, returning false once the session is over.
+    fn handle(&mut self, request: &Value) -> Result<bool, Error> {
+        let arguments = &request["arguments"];
+        let command = request["command"].as_str().unwrap_or_default();
+
+        match command {
+            "initialize" => {
+                self.respond(
+                    request,
+                    Ok(json!({ "supportsConfigurationDoneRequest": true })),
+                )?;
+            }
+            // Breakpoints can only be placed on instructions once the
+            // program is assembled, so the client is told to configure them
+            // after the launch.
+            "launch" => match Session::launch(arguments) {
+                Ok(mut session) => {
+                    session.set_breakpoints(&std::mem::take(&mut self.pending_breakpoints));
+                    self.session = Some(session);
+                    self.respond(request, Ok(json!({})))?;
+                    self.event("initialized", json!({}))?;
+                }
+                Err(error) => self.respond(request, Err(error.to_string()))?,
+            },
+            "setBreakpoints" => {
+                let lines = arguments["breakpoints"].as_array().cloned().unwrap_or_default();
+                let breakpoints = match self.session.as_mut() {
+                    Some(session) => session.set_breakpoints(&lines),
+                    None => {
+                        let breakpoints = lines
+                            .iter()
+                            .map(|breakpoint| {
+                                json!({ "verified": false, "line": breakpoint["line"] })
+                            })
+                            .collect();
+
+                        self.pending_breakpoints = lines;
+
+                        breakpoints
+                    }
+                };
+
+                self.respond(request, Ok(json!({ "breakpoints": breakpoints })))?;
+            }
+            "setExceptionBreakpoints" => self.respond(request, Ok(json!({})))?,
+            "configurationDone" => {
+                self.respond(request, Ok(json!({})))?;
+
+                let stop_on_entry = self.session.as_ref().is_some_and(|session| session.stop_on_entry);
+
+                if stop_on_entry {
+                    if let Some(session) = self.session.as_mut() {
+                        session.stop("entry");
+                    }
+
+                    self.stopped("entry")?;
+                } else {
+                    self.execute(RunMode::Continue)?;
+                }
+            }
+            "threads" => self.respond(
+                request,
+                Ok(json!({ "threads": [{ "id": THREAD_ID, "name": "main" }] })),
+            )?,
+            "stackTrace" => {
+                let frames = self
+                    .session
+                    .as_ref()
+                    .map(Session::stack_trace)
+                    .unwrap_or_default();
+
+                self.respond(
+                    request,
+                    Ok(json!({ "stackFrames": frames, "totalFrames": frames.len() })),
+                )?;
+            }
+            "scopes" => self.respond(
+                request,
+                Ok(json!({ "scopes": [
+                    { "name": "Registers", "variablesReference": REGISTERS_REFERENCE, "expensive": false },
+                    { "name": "Flags", "variablesReference": FLAGS_REFERENCE, "expensive": false },
+                    { "name": "Globals", "variablesReference": GLOBALS_REFERENCE, "expensive": false },
+                    { "name": "Stack", "variablesReference": STACK_REFERENCE, "expensive": false },
+                    { "name": "Heap", "variablesReference": HEAP_REFERENCE, "expensive": false },
+                ] })),
+            )?,
+            "variables" => {
+                let reference = arguments["variablesReference"].as_u64().unwrap_or_default();
+                let variables = self
+                    .session
+                    .as_ref()
+                    .map(|session| session.variables(reference))
+                    .unwrap_or_default();
+
+                self.respond(request, Ok(json!({ "variables": variables })))?;
+            }
+            "continue" | "next" | "stepIn" | "stepOut" => {
+                let mode = match command {
+                    "continue" => RunMode::Continue,
+                    "next" => RunMode::StepOver,
+                    "stepIn" => RunMode::StepIn,
+                    _ => RunMode::StepOut,
+                };
+
+                self.respond(request, Ok(json!({ "allThreadsContinued": true })))?;
+                self.execute(mode)?;
+            }
+            // A running program is paused by `execute`, so the program is
+            // already stopped here.
+            "pause" => {
+                self.respond(request, Ok(json!({})))?;
+
+                if let Some(session) = self.session.as_mut() {
+                    session.stop("pause");
+                    self.stopped("pause")?;
+                }
+            }
+            "disconnect" | "terminate" => {
+                self.respond(request, Ok(json!({})))?;
+
+                return Ok(false);
+            }
+            _ => self.respond(request, Err(format!("unsupported request: {command}")))?,
+        }
+
+        Ok(true)
+    }
+
+    fn stopped(&mut self, reason: &str) -> Result<(), Error> {
+        self.event(
+            "stopped",
+            json!({ "reason": reason, "threadId": THREAD_ID, "allThreadsStopped": true }),
+        )
+    }
+
+    fn execute(&mut self, mode: RunMode) -> Result<(), Error> {
+        let Self {
+            session,
+            incoming,
+            deferred,
+            ..
+        } = self;
+        let Some(session) = session.as_mut() else {
+            return Ok(());
+        };
+        let mut pause = None;
+        let stop = session.run(mode, || {
+            while let Some(message) = incoming
+                .as_ref()
+                .and_then(|receiver| receiver.try_recv().ok())
+            {
+                match message {
+                    Ok(Some(request)) if request["command"] == "pause" => {
+                        pause = Some(request);
+
+                        return true;
+                    }
+                    message => deferred.push_back(message),
+                }
+            }
+
+            false
+        });
+        let output = session.simulator.take_output();
+
+        if let Some(request) = pause {
+            self.respond(&request, Ok(json!({})))?;
+        }
+
+        if !output.is_empty() {
+            self.event(
+                "output",
+                json!({ "category": "stdout", "output": String::from_utf8_lossy(&output) }),
+            )?;
+        }
+
+        match stop {
+            Stop::Paused(reason) => self.stopped(reason),
+            Stop::Halted => {
+                self.event("exited", json!({ "exitCode": 0 }))?;
+                self.event("terminated", json!({}))
+            }
+            Stop::Failed(message) => {
+                self.event(
+                    "output",
+                    json!({ "category": "stderr", "output": format!("{message}\n") }),
+                )?;
+                self.event("exited", json!({ "exitCode": 1 }))?;
+                self.event("terminated", json!({}))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes the source to a file of its own and returns its path.
+    fn program(name: &str, source: &str) -> String {
+        let path = std::env::temp_dir().join(format!("dap-{}-{name}.pep", std::process::id()));
+
+        std::fs::write(&path, source).unwrap();
+
+        path.to_string_lossy().into_owned()
+    }
+
+    /// Runs the adapter over the requests, numbered in order, and returns
+    /// every message it sends.
+    fn transcript(requests: &[Value]) -> Vec<Value> {
+        let mut input = vec![];
+
+        for (seq, request) in requests.iter().enumerate() {
+            let mut request = request.clone();
+
+            request["seq"] = json!(seq + 1);
+            request["type"] = json!("request");
+            write_message(&mut input, &request).unwrap();
+        }
+
+        let mut output = vec![];
+
+        Server::new(&mut output)
+            .run(std::io::Cursor::new(input))
+            .unwrap();
+
+        let mut reader = output.as_slice();
+        let mut messages = vec![];
+
+        while let Some(message) = read_message(&mut reader).unwrap() {
+            messages.push(message);
+        }
+
+        messages
+    }
+
+    /// Command of each response and event of each event, in order.
+    fn kinds(messages: &[Value]) -> Vec<String> {
+        messages
+            .iter()
+            .map(|message| match message["type"].as_str() {
+                Some("event") => format!("event {}", message["event"].as_str().unwrap()),
+                _ => message["command"].as_str().unwrap().to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn runs_to_a_breakpoint_set_before_launch() {
+        let path = program(
+            "breakpoint",
+            "         BR      main
+num:     .WORD   0
+main:    DECI    num,d
+         LDA     num,d
+         ADDA    1,i
+         STA     num,d
+         DECO    num,d
+         STOP
+         .END
+",
+        );
+        let messages = transcript(&[
+            json!({ "command": "initialize", "arguments": { "adapterID": "pep8" } }),
+            json!({
+                "command": "setBreakpoints",
+                "arguments": { "source": { "path": path }, "breakpoints": [{ "line": 5 }] },
+            }),
+            json!({ "command": "launch", "arguments": { "program": path, "input": "41" } }),
+            json!({ "command": "configurationDone" }),
+            json!({ "command": "stackTrace", "arguments": { "threadId": THREAD_ID } }),
+            json!({
+                "command": "variables",
+                "arguments": { "variablesReference": REGISTERS_REFERENCE },
+            }),
+            json!({ "command": "continue", "arguments": { "threadId": THREAD_ID } }),
+            json!({ "command": "disconnect" }),
+        ]);
+
+        assert_eq!(
+            kinds(&messages),
+            [
+                "initialize",
+                "setBreakpoints",
+                "launch",
+                "event initialized",
+                "configurationDone",
+                "event stopped",
+                "stackTrace",
+                "variables",
+                "continue",
+                "event output",
+                "event exited",
+                "event terminated",
+                "disconnect",
+            ]
+        );
+        assert!(messages.iter().all(|message| message["success"] != false));
+        assert_eq!(
+            messages[1]["body"]["breakpoints"],
+            json!([{ "verified": false, "line": 5 }])
+        );
+        assert_eq!(messages[5]["body"]["reason"], "breakpoint");
+        assert_eq!(messages[6]["body"]["stackFrames"][0]["line"], 5);
+        assert_eq!(
+            messages[7]["body"]["variables"][0],
+            json!({ "name": "A", "value": "0x0029 (41)", "variablesReference": 0 })
+        );
+        assert_eq!(messages[9]["body"]["output"], "42");
+        assert_eq!(messages[10]["body"]["exitCode"], 0);
+    }
+
+    #[test]
+    fn stops_at_a_breakpoint_on_the_first_instruction() {
+        let path = program(
+            "first",
+            "main:    LDA     1,i
+         STOP
+         .END
+",
+        );
+        let stops = |stop_on_entry: bool| {
+            let messages = transcript(&[
+                json!({ "command": "initialize" }),
+                json!({
+                    "command": "launch",
+                    "arguments": { "program": path, "stopOnEntry": stop_on_entry },
+                }),
+                json!({
+                    "command": "setBreakpoints",
+                    "arguments": { "source": { "path": path }, "breakpoints": [{ "line": 1 }] },
+                }),
+                json!({ "command": "configurationDone" }),
+                json!({ "command": "stackTrace", "arguments": { "threadId": THREAD_ID } }),
+                json!({ "command": "continue", "arguments": { "threadId": THREAD_ID } }),
+                json!({ "command": "disconnect" }),
+            ]);
+
+            let trace = messages
+                .iter()
+                .find(|message| message["command"] == "stackTrace")
+                .unwrap();
+
+            assert_eq!(trace["body"]["stackFrames"][0]["line"], 1);
+
+            messages
+                .iter()
+                .filter_map(|message| match message["event"].as_str() {
+                    Some("stopped") => message["body"]["reason"].as_str(),
+                    Some("exited") => Some("exited"),
+                    _ => None,
+                })
+                .map(String::from)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(stops(false), ["breakpoint", "exited"]);
+        assert_eq!(stops(true), ["entry", "exited"]);
+    }
+
+    #[test]
+    fn shows_globals_stack_and_heap_from_trace_tags() {
+        let path = program(
+            "memory",
+            "         BR      main
+glob:    .WORD   7           ;#2d
+buffer:  .BLOCK  2
+local:   .EQUATE 0           ;#2d
+value:   .EQUATE 0           ;#2h
+next:    .EQUATE 2           ;#2d
+main:    SUBSP   2,i         ;#local
+         LDA     5,i
+         STA     local,s
+         CALL    new         ;#value #next
+         CALL    new         ;#value #next
+         STOP
+new:     LDX     heap,i
+         RET0
+heap:    .WORD   18
+         .WORD   3
+         .END
+",
+        );
+        let variables = |reference: u64| json!({ "command": "variables", "arguments": { "variablesReference": reference } });
+        let messages = transcript(&[
+            json!({ "command": "initialize" }),
+            json!({ "command": "launch", "arguments": { "program": path } }),
+            json!({
+                "command": "setBreakpoints",
+                "arguments": { "source": { "path": path }, "breakpoints": [{ "line": 14 }] },
+            }),
+            json!({ "command": "configurationDone" }),
+            json!({ "command": "continue", "arguments": { "threadId": THREAD_ID } }),
+            variables(GLOBALS_REFERENCE),
+            variables(STACK_REFERENCE),
+            variables(FRAMES_REFERENCE),
+            variables(FRAMES_REFERENCE + 1),
+            variables(HEAP_REFERENCE),
+            json!({ "command": "disconnect" }),
+        ]);
+        let bodies: Vec<&Value> = messages
+            .iter()
+            .filter(|message| message["command"] == "variables")
+            .map(|message| &message["body"]["variables"])
+            .collect();
+        let variable = |name: &str, value: &str, reference: u64| json!({ "name": name, "value": value, "variablesReference": reference });
+
+        assert_eq!(
+            bodies,
+            [
+                &json!([
+                    variable("buffer", "0x0000 (0)", 0),
+                    variable("glob", "7", 0),
+                    variable("heap", "0x0012 (18)", 0),
+                ]),
+                &json!([
+                    variable("new", "1 cells", FRAMES_REFERENCE + 1),
+                    variable("main", "1 cells", FRAMES_REFERENCE),
+                ]),
+                &json!([variable("local", "5", 0)]),
+                &json!([variable("retAddr", "0x0016", 0)]),
+                &json!([variable("value", "0x0012", 0), variable("next", "3", 0)]),
+            ]
+        );
+    }
+
+    #[test]
+    fn steps_over_calls_and_out_of_subroutines() {
+        let path = program(
+            "step",
+            "main:    CALL    sub
+         STOP
+sub:     LDA     1,i
+         RET0
+         .END
+",
+        );
+        let messages = transcript(&[
+            json!({ "command": "initialize" }),
+            json!({ "command": "launch", "arguments": { "program": path, "stopOnEntry": true } }),
+            json!({ "command": "configurationDone" }),
+            json!({ "command": "stepIn", "arguments": { "threadId": THREAD_ID } }),
+            json!({ "command": "stackTrace", "arguments": { "threadId": THREAD_ID } }),
+            json!({ "command": "stepOut", "arguments": { "threadId": THREAD_ID } }),
+            json!({ "command": "stackTrace", "arguments": { "threadId": THREAD_ID } }),
+            json!({ "command": "disconnect" }),
+        ]);
+        let stops: Vec<&Value> = messages
+            .iter()
+            .filter(|message| message["event"] == "stopped")
+            .map(|message| &message["body"]["reason"])
+            .collect();
+        let traces: Vec<&Value> = messages
+            .iter()
+            .filter(|message| message["command"] == "stackTrace")
+            .map(|message| &message["body"]["stackFrames"])
+            .collect();
+
+        assert_eq!(stops, ["entry", "step", "step"]);
+        assert_eq!(traces[0][0]["name"], "sub");
+        assert_eq!(traces[0][0]["line"], 3);
+        assert_eq!(traces[0][1]["line"], 1);
+        assert_eq!(traces[1].as_array().unwrap().len(), 1);
+        assert_eq!(traces[1][0]["line"], 2);
+    }
+
+    #[test]
+    fn pauses_a_running_program() {
+        let path = program("pause", "loop:    BR      loop\n         .END\n");
+        let messages = transcript(&[
+            json!({ "command": "initialize" }),
+            json!({ "command": "launch", "arguments": { "program": path } }),
+            json!({ "command": "configurationDone" }),
+            json!({ "command": "pause", "arguments": { "threadId": THREAD_ID } }),
+            json!({ "command": "disconnect" }),
+        ]);
+
+        assert_eq!(
+            kinds(&messages),
+            [
+                "initialize",
+                "launch",
+                "event initialized",
+                "configurationDone",
+                "pause",
+                "event stopped",
+                "disconnect",
+            ]
+        );
+        assert_eq!(messages[5]["body"]["reason"], "pause");
+    }
+
+    #[test]
+    fn reports_a_failed_launch() {
+        let messages = transcript(&[
+            json!({ "command": "launch", "arguments": {} }),
+            json!({ "command": "disconnect" }),
+        ]);
+
+        assert_eq!(kinds(&messages), ["launch", "disconnect"]);
+        assert_eq!(messages[0]["success"], false);
+        assert_eq!(messages[0]["message"], "missing program");
+    }
+}
@@ -0,0 +1,20 @@
+use std::fmt::Display;
+
+/// A message about a source line that does not stop assembly.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Warning {
+    pub line: usize,
+    pub message: String,
+}
+
+impl Warning {
+    pub fn new(line: usize, message: String) -> Self {
+        Self { line, message }
+    }
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: warning: {}", self.line, self.message)
+    }
+}
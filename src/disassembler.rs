@@ -0,0 +1,147 @@
+use crate::{
+    formatter::{pad_to, COMMENT_COLUMN, MNEMONIC_COLUMN, OPERAND_COLUMN},
+    isa,
+    target::Target,
+};
+
+/// Disassembles a program image loaded at `origin` for the target into
+/// source, with one statement per instruction and the address and bytes of
+/// each in its comment. Bytes that start no instruction of the target, or an
+/// instruction cut off by the end of the image, become `.BYTE` statements.
+pub fn disassemble(bytes: &[u8], origin: u16, target: Target) -> String {
+    let mut source = String::new();
+    let mut address = 0;
+
+    while address < bytes.len() {
+        let decoded = isa::decode(bytes[address], target)
+            .filter(|(opcode, _)| address + opcode.byte_size() <= bytes.len());
+        let (size, mnemonic, operand) = match decoded {
+            Some((opcode, None)) => (1, opcode.mnemonic.to_string(), String::new()),
+            Some((opcode, Some(mode))) => {
+                let value = u16::from_be_bytes([bytes[address + 1], bytes[address + 2]]);
+
+                (
+                    opcode.byte_size(),
+                    opcode.mnemonic.to_string(),
+                    format!("0x{value:04X},{}", target.mode_name(&mode)),
+                )
+            }
+            None => (
+                1,
+                String::from(".BYTE"),
+                format!("0x{:02X}", bytes[address]),
+            ),
+        };
+        let mut line = String::new();
+
+        pad_to(&mut line, MNEMONIC_COLUMN);
+        line.push_str(&mnemonic);
+
+        if !operand.is_empty() {
+            pad_to(&mut line, OPERAND_COLUMN);
+            line.push_str(&operand);
+        }
+
+        let encoding: Vec<_> = bytes[address..address + size]
+            .iter()
+            .map(|byte| format!("{byte:02X}"))
+            .collect();
+
+        pad_to(&mut line, COMMENT_COLUMN);
+        line.push_str(&format!(
+            ";{:04X}: {}\n",
+            origin as usize + address,
+            encoding.join(" ")
+        ));
+        source.push_str(&line);
+        address += size;
+    }
+
+    source
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        assembler::{assemble, Options},
+        lexer::parse_source,
+    };
+
+    fn byte_code(source: &str, target: Target) -> Vec<u8> {
+        let options = Options {
+            target,
+            ..Options::default()
+        };
+
+        assemble(parse_source(source).unwrap(), &options)
+            .unwrap()
+            .byte_code
+    }
+
+    /// Disassembles the assembled source, checks that the statements match
+    /// it and that they assemble to the same bytes.
+    fn round_trip(source: &str, target: Target) {
+        let bytes = byte_code(source, target);
+        let disassembly = disassemble(&bytes, 0, target);
+        let statements: Vec<&str> = disassembly
+            .lines()
+            .map(|line| line.split(';').next().unwrap().trim_end())
+            .collect();
+
+        assert_eq!(statements, source.lines().collect::<Vec<_>>());
+        assert_eq!(byte_code(&disassembly, target), bytes);
+    }
+
+    #[test]
+    fn disassembles_pep8_programs() {
+        round_trip(
+            "         LDA     0x0005,i
+         STA     0x0020,sxf
+         CHARO   0x0041,s
+         ASLA
+         BR      0x0000,x
+         RET3
+         STOP",
+            Target::Pep8,
+        );
+    }
+
+    #[test]
+    fn disassembles_pep9_programs() {
+        round_trip(
+            "         LDWA    0x0005,i
+         STBA    0x0020,sfx
+         DECO    0x0041,s
+         ASLA
+         BR      0x0000,x
+         RET
+         STOP",
+            Target::Pep9,
+        );
+    }
+
+    #[test]
+    fn disassembles_pep10_programs() {
+        round_trip(
+            "         LDWA    0x0005,i
+         LDWT    0x0001,i
+         SCALL   0x0020,d
+         ASLA
+         BRNE    0x0000,i
+         RET",
+            Target::Pep10,
+        );
+    }
+
+    #[test]
+    fn disassembles_unknown_and_cut_off_instructions_as_bytes() {
+        assert_eq!(
+            disassemble(&[0x08, 0xC0, 0x00], 0x0100, Target::Pep10),
+            "         .BYTE   0x08        ;0100: 08
+         .BYTE   0xC0        ;0101: C0
+         RET                 ;0102: 00
+"
+        );
+    }
+}
@@ -0,0 +1,256 @@
+use std::fmt::Write;
+
+use crate::{
+    assembler::Assembly,
+    cfg::{Exit, Graph, Routine},
+    lexer::SourceLine,
+};
+
+/// Escapes text for a double-quoted DOT string.
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Writes the control-flow graph of a routine, with one node per basic block
+/// listing its source lines.
+///
+/// Calls are drawn as dashed edges to a node per callee, and returns, stops
+/// and jumps to targets only known at run time as edges to terminal nodes.
+pub fn write_routine(lines: &[SourceLine], assembly: &Assembly, routine: &Routine) -> String {
+    let graph = Graph::new(assembly);
+    let routines = graph.routines();
+    let blocks = graph.blocks(routine.entry);
+    let block_of = |index: usize| {
+        blocks
+            .iter()
+            .position(|block| block.statements.first() == Some(&index))
+    };
+    let mut dot = String::new();
+
+    writeln!(dot, "digraph {} {{", quote(&routine.name)).unwrap();
+    writeln!(dot, "    node [shape=box, fontname=\"monospace\"];").unwrap();
+
+    for (number, block) in blocks.iter().enumerate() {
+        let label: String = block
+            .statements
+            .iter()
+            .map(|index| {
+                let line = assembly.statement_lines[*index].line();
+
+                format!("{}\n", lines[line - 1].text.trim_end())
+            })
+            .collect();
+        // Each line is left-justified by ending it with `\l`.
+        let label = quote(&label).replace('\n', "\\l");
+
+        writeln!(dot, "    b{number} [label={label}];").unwrap();
+    }
+
+    let mut terminals = vec![];
+    let mut callees = vec![];
+
+    for (number, block) in blocks.iter().enumerate() {
+        let last = block.last();
+        let next = graph.next(last).and_then(block_of);
+        let target = |address: u16| graph.statement_at(address).and_then(block_of);
+        let mut edge = |to: String, attributes: &str| {
+            writeln!(dot, "    b{number} -> {to}{attributes};").unwrap();
+        };
+
+        match graph.exit(last) {
+            Some(Exit::Next) => {
+                if let Some(next) = next {
+                    edge(format!("b{next}"), "");
+                }
+            }
+            Some(Exit::Jump(address)) => match address.and_then(target) {
+                Some(target) => edge(format!("b{target}"), ""),
+                None => {
+                    edge(String::from("indirect"), "");
+                    terminals.push("indirect");
+                }
+            },
+            Some(Exit::Branch(address)) => {
+                match address.and_then(target) {
+                    Some(target) => edge(format!("b{target}"), " [label=\"taken\"]"),
+                    None => {
+                        edge(String::from("indirect"), " [label=\"taken\"]");
+                        terminals.push("indirect");
+                    }
+                }
+
+                if let Some(next) = next {
+                    edge(format!("b{next}"), " [label=\"not taken\"]");
+                }
+            }
+            Some(Exit::Call(address)) => {
+                let callee = address
+                    .and_then(|address| graph.statement_at(address))
+                    .and_then(|entry| routines.iter().find(|routine| routine.entry == entry))
+                    .map_or("?", |routine| routine.name.as_str());
+                let node = quote(&format!("call {callee}"));
+
+                edge(node.clone(), " [style=dashed]");
+                callees.push(node);
+
+                if let Some(next) = next {
+                    edge(format!("b{next}"), "");
+                }
+            }
+            Some(Exit::Return) => {
+                edge(String::from("return"), "");
+                terminals.push("return");
+            }
+            Some(Exit::Stop) => {
+                edge(String::from("stop"), "");
+                terminals.push("stop");
+            }
+            None => {}
+        }
+    }
+
+    terminals.sort();
+    terminals.dedup();
+
+    callees.sort();
+    callees.dedup();
+
+    for terminal in terminals {
+        writeln!(dot, "    {terminal} [shape=ellipse];").unwrap();
+    }
+
+    for callee in callees {
+        writeln!(dot, "    {callee} [shape=ellipse, style=dashed];").unwrap();
+    }
+
+    writeln!(dot, "}}").unwrap();
+
+    dot
+}
+
+/// Writes the call graph between the main program and the subroutines it
+/// calls, directly or not.
+pub fn write_call_graph(assembly: &Assembly) -> String {
+    let graph = Graph::new(assembly);
+    let routines = graph.routines();
+    let mut dot = String::new();
+
+    writeln!(dot, "digraph calls {{").unwrap();
+
+    for routine in &routines {
+        writeln!(dot, "    {};", quote(&routine.name)).unwrap();
+
+        let reachable = graph.reachable(routine.entry, false);
+        let mut callees = vec![];
+
+        for index in (0..reachable.len()).filter(|index| reachable[*index]) {
+            if let Some(Exit::Call(Some(address))) = graph.exit(index) {
+                let callee = routines
+                    .iter()
+                    .find(|callee| Some(callee.entry) == graph.statement_at(address));
+
+                if let Some(callee) = callee {
+                    if !callees.contains(&&callee.name) {
+                        callees.push(&callee.name);
+                    }
+                }
+            }
+        }
+
+        for callee in callees {
+            writeln!(dot, "    {} -> {};", quote(&routine.name), quote(callee)).unwrap();
+        }
+    }
+
+    writeln!(dot, "}}").unwrap();
+
+    dot
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        assembler::{assemble, Options},
+        lexer::parse_source,
+    };
+
+    const SOURCE: &str = "main:    DECI    0x0100,d
+         LDA     0x0100,d
+         BREQ    done
+         CALL    sub
+done:    STOP
+sub:     CALL    print
+         RET0
+print:   STRO    msg,d       ;say \"hi\" \\ bye
+         RET0
+msg:     .ASCII  \"hi\\x00\"
+         .END
+";
+
+    #[test]
+    fn writes_the_blocks_and_edges_of_each_routine() {
+        let lines = parse_source(SOURCE).unwrap();
+        let assembly = assemble(lines.clone(), &Options::default()).unwrap();
+        let graphs: Vec<String> = Graph::new(&assembly)
+            .routines()
+            .iter()
+            .map(|routine| write_routine(&lines, &assembly, routine))
+            .collect();
+
+        assert_eq!(
+            graphs,
+            [
+                r#"digraph "main" {
+    node [shape=box, fontname="monospace"];
+    b0 [label="main:    DECI    0x0100,d\l         LDA     0x0100,d\l         BREQ    done\l"];
+    b1 [label="         CALL    sub\l"];
+    b2 [label="done:    STOP\l"];
+    b0 -> b2 [label="taken"];
+    b0 -> b1 [label="not taken"];
+    b1 -> "call sub" [style=dashed];
+    b1 -> b2;
+    b2 -> stop;
+    stop [shape=ellipse];
+    "call sub" [shape=ellipse, style=dashed];
+}
+"#,
+                r#"digraph "sub" {
+    node [shape=box, fontname="monospace"];
+    b0 [label="sub:     CALL    print\l"];
+    b1 [label="         RET0\l"];
+    b0 -> "call print" [style=dashed];
+    b0 -> b1;
+    b1 -> return;
+    return [shape=ellipse];
+    "call print" [shape=ellipse, style=dashed];
+}
+"#,
+                r#"digraph "print" {
+    node [shape=box, fontname="monospace"];
+    b0 [label="print:   STRO    msg,d       ;say \"hi\" \\ bye\l         RET0\l"];
+    b0 -> return;
+    return [shape=ellipse];
+}
+"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn writes_the_call_graph() {
+        let assembly = assemble(parse_source(SOURCE).unwrap(), &Options::default()).unwrap();
+
+        assert_eq!(
+            write_call_graph(&assembly),
+            r#"digraph calls {
+    "main";
+    "main" -> "sub";
+    "sub";
+    "sub" -> "print";
+    "print";
+}
+"#
+        );
+    }
+}
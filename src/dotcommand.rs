@@ -2,7 +2,9 @@ use std::fmt::Display;
 
 use crate::{
     address::{self, AddressTable},
-    lexer::Token,
+    lexer::{escape, Token},
+    target::Target,
+    traps::Trap,
     types::{Pep8Byte, Pep8Word},
 };
 
@@ -10,6 +12,7 @@ use crate::{
 pub enum Error {
     InvalidArguments(String),
     InvalidDotCommand(String),
+    UnsupportedDotCommand { name: String, target: Target },
 }
 
 impl Display for Error {
@@ -21,31 +24,75 @@ impl Display for Error {
             Self::InvalidDotCommand(name) => {
                 write!(f, "invalid dot command: {name}")
             }
+            Self::UnsupportedDotCommand { name, target } => {
+                write!(f, "{name} is not supported on {target}")
+            }
         }
     }
 }
 
 impl std::error::Error for Error {}
 
+/// Every dot command accepted by `DotCommand::from_tokens` for Pep/8 and
+/// Pep/9.
+pub const DOT_COMMANDS: &[&str] = &[
+    ".ADDRSS", ".ASCII", ".BLOCK", ".BYTE", ".END", ".EQUATE", ".EXPORT", ".IMPORT", ".TRAP",
+    ".WORD",
+];
+
+/// Every dot command accepted by `DotCommand::from_tokens` for Pep/10.
+pub const PEP10_DOT_COMMANDS: &[&str] = &[
+    ".ADDRSS", ".ASCII", ".BLOCK", ".BYTE", ".END", ".EQUATE", ".EXPORT", ".IMPORT", ".SCALL",
+    ".SECTION", ".TRAP", ".WORD",
+];
+
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug)]
 pub enum DotCommand {
     ADDRSS(String),
-    ASCII(String),
+    ASCII(Vec<u8>),
     BLOCK(usize),
     // BURN,
     BYTE(Pep8Byte),
     END,
-    // EQUATE,
+    EQUATE(Pep8Word),
+    EXPORT(String),
+    IMPORT(String),
+    /// Declares a system call, making the `@name` macro available.
+    SCALL(String),
+    /// Places the following statements in the named section. Its `r`, `w`
+    /// and `x` access flags are checked but do not affect the layout.
+    SECTION(String),
+    TRAP(Trap),
     WORD(Pep8Word),
 }
 
+impl Display for DotCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.operands() {
+            operands if operands.is_empty() => write!(f, "{}", self.name()),
+            operands => write!(f, "{} {operands}", self.name()),
+        }
+    }
+}
+
 impl DotCommand {
-    pub fn from_tokens(tokens: &[Token]) -> Result<Self, Box<dyn std::error::Error>> {
+    pub fn from_tokens(
+        tokens: &[Token],
+        target: Target,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
         let [Token::DotCommand(dotcommand), other_tokens @ ..] = tokens else {
             return Err(Box::from("missing dot command token"));
         };
+        let name = dotcommand.to_uppercase();
+
+        if PEP10_DOT_COMMANDS.contains(&name.as_str())
+            && !target.dot_commands().contains(&name.as_str())
+        {
+            return Err(Box::new(Error::UnsupportedDotCommand { name, target }));
+        }
 
-        match &dotcommand.to_uppercase()[..] {
+        match &name[..] {
             ".ADDRSS" => match other_tokens {
                 [Token::Identifier(label)] => Ok(DotCommand::ADDRSS(label.clone())),
                 _ => Err(Box::new(Error::InvalidArguments(String::from(
@@ -66,8 +113,8 @@ impl DotCommand {
             },
             // ".BURN" => ,
             ".BYTE" => match other_tokens {
-                [Token::Char(value)] => Ok(Self::BYTE(Pep8Byte::try_from(value)?)),
-                [Token::Number(value)] => Ok(Self::BYTE(Pep8Byte::try_from(value)?)),
+                [Token::Char(value)] => Ok(Self::BYTE(Pep8Byte::from(value))),
+                [Token::Number(value)] => Ok(Self::BYTE(Pep8Byte::from(value))),
                 [Token::String(value)] => Ok(Self::BYTE(Pep8Byte::try_from(value)?)),
                 _ => Err(Box::new(Error::InvalidArguments(String::from(
                     "char, number or string argument required",
@@ -79,12 +126,55 @@ impl DotCommand {
                     "no arguments expected",
                 )))),
             },
-            // ".EQUATE" => match other_tokens {
-            //
-            // },
+            ".EQUATE" => match other_tokens {
+                [Token::Char(value)] => Ok(Self::EQUATE(Pep8Word::from(value))),
+                [Token::Number(value)] => Ok(Self::EQUATE(Pep8Word::from(value))),
+                [Token::String(value)] => Ok(Self::EQUATE(Pep8Word::try_from(value)?)),
+                _ => Err(Box::new(Error::InvalidArguments(String::from(
+                    "char, number or string argument required",
+                )))),
+            },
+            ".EXPORT" => match other_tokens {
+                [Token::Identifier(symbol)] => Ok(Self::EXPORT(symbol.clone())),
+                _ => Err(Box::new(Error::InvalidArguments(String::from(
+                    "symbol argument required",
+                )))),
+            },
+            ".IMPORT" => match other_tokens {
+                [Token::Identifier(symbol)] => Ok(Self::IMPORT(symbol.clone())),
+                _ => Err(Box::new(Error::InvalidArguments(String::from(
+                    "symbol argument required",
+                )))),
+            },
+            ".SCALL" => match other_tokens {
+                [Token::Identifier(symbol)] => Ok(Self::SCALL(symbol.clone())),
+                _ => Err(Box::new(Error::InvalidArguments(String::from(
+                    "symbol argument required",
+                )))),
+            },
+            ".SECTION" => {
+                let (name, flags) = match other_tokens {
+                    [Token::String(name)] => (name, &b"rwx"[..]),
+                    [Token::String(name), Token::Comma, Token::String(flags)] => (name, &flags[..]),
+                    _ => {
+                        return Err(Box::new(Error::InvalidArguments(String::from(
+                            "section name string and optional flags string required",
+                        ))))
+                    }
+                };
+
+                if flags.is_empty() || !flags.iter().all(|flag| b"rwx".contains(flag)) {
+                    return Err(Box::new(Error::InvalidArguments(String::from(
+                        "section flags must be a combination of r, w and x",
+                    ))));
+                }
+
+                Ok(Self::SECTION(String::from_utf8_lossy(name).into_owned()))
+            }
+            ".TRAP" => Ok(Self::TRAP(Trap::from_tokens(other_tokens, target)?)),
             ".WORD" => match other_tokens {
-                [Token::Char(value)] => Ok(Self::WORD(Pep8Word::try_from(value)?)),
-                [Token::Number(value)] => Ok(Self::WORD(Pep8Word::try_from(value)?)),
+                [Token::Char(value)] => Ok(Self::WORD(Pep8Word::from(value))),
+                [Token::Number(value)] => Ok(Self::WORD(Pep8Word::from(value))),
                 [Token::String(value)] => Ok(Self::WORD(Pep8Word::try_from(value)?)),
                 _ => Err(Box::new(Error::InvalidArguments(String::from(
                     "char, number or string argument required",
@@ -94,18 +184,64 @@ impl DotCommand {
         }
     }
 
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::ADDRSS(_) => ".ADDRSS",
+            Self::ASCII(_) => ".ASCII",
+            Self::BLOCK(_) => ".BLOCK",
+            Self::BYTE(_) => ".BYTE",
+            Self::END => ".END",
+            Self::EQUATE(_) => ".EQUATE",
+            Self::EXPORT(_) => ".EXPORT",
+            Self::IMPORT(_) => ".IMPORT",
+            Self::SCALL(_) => ".SCALL",
+            Self::SECTION(_) => ".SECTION",
+            Self::TRAP(_) => ".TRAP",
+            Self::WORD(_) => ".WORD",
+        }
+    }
+
+    /// Operands in canonical form, with strings escaped and numbers in
+    /// decimal, or an empty string if there are none.
+    pub fn operands(&self) -> String {
+        let quoted = |bytes: &[u8]| {
+            let escaped: String = bytes.iter().map(|byte| escape(*byte, b'"')).collect();
+
+            format!("\"{escaped}\"")
+        };
+
+        match self {
+            Self::ADDRSS(symbol)
+            | Self::EXPORT(symbol)
+            | Self::IMPORT(symbol)
+            | Self::SCALL(symbol) => symbol.clone(),
+            Self::ASCII(value) => quoted(value),
+            Self::BLOCK(size) => size.to_string(),
+            Self::BYTE(value) => value.to_string(),
+            Self::END => String::new(),
+            Self::EQUATE(value) | Self::WORD(value) => value.to_string(),
+            Self::SECTION(name) => quoted(name.as_bytes()),
+            Self::TRAP(trap) => trap.to_string(),
+        }
+    }
+
     pub fn as_bytes(&self, address_table: &AddressTable) -> Result<Vec<u8>, address::Error> {
         match self {
             Self::ADDRSS(value) => address_table
                 .get(value)
                 .ok_or(address::Error::UndefinedSymbol(value.clone()))
-                .and_then(|word| Ok(word.as_bytes().to_vec())),
-            Self::ASCII(value) => Ok(value.as_bytes().to_vec()),
+                .map(|word| word.as_bytes().to_vec()),
+            Self::ASCII(value) => Ok(value.clone()),
             Self::BLOCK(size) => Ok(vec![0; *size]),
             // Self::BURN => ?, // WTF IS THIS LOL
             Self::BYTE(value) => Ok(vec![value.as_byte()]),
             Self::END => Ok(vec![]),
-            // Self::EQUATE ,
+            Self::EQUATE(_) => Ok(vec![]),
+            Self::EXPORT(_)
+            | Self::IMPORT(_)
+            | Self::SCALL(_)
+            | Self::SECTION(_)
+            | Self::TRAP(_) => Ok(vec![]),
             Self::WORD(value) => Ok(value.as_bytes().to_vec()),
         }
     }
@@ -113,12 +249,17 @@ impl DotCommand {
     pub fn byte_size(&self) -> usize {
         match self {
             Self::ADDRSS(_) => 2,
-            Self::ASCII(s) => s.len(), // FIXME this is clearly wrong
+            Self::ASCII(value) => value.len(),
             Self::BLOCK(size) => *size,
             // Self::BURN => ?, // WTF IS THIS LOL
             Self::BYTE(_) => 1,
-            Self::END => 1,
-            // Self::EQUATE => 0,
+            Self::END => 0,
+            Self::EQUATE(_) => 0,
+            Self::EXPORT(_)
+            | Self::IMPORT(_)
+            | Self::SCALL(_)
+            | Self::SECTION(_)
+            | Self::TRAP(_) => 0,
             Self::WORD(_) => 2,
         }
     }
@@ -0,0 +1,204 @@
+use std::io::Write;
+
+use clap::ValueEnum;
+
+use crate::assembler::Assembly;
+
+/// Language of the source that embeds a program's bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Language {
+    /// A C `unsigned char` array.
+    C,
+    /// A Rust `const` byte slice.
+    Rust,
+    /// A Verilog `$readmemh` file.
+    Verilog,
+}
+
+/// Most bytes written on one row.
+const ROW_LENGTH: usize = 8;
+
+impl Language {
+    fn byte(self, byte: u8) -> String {
+        match self {
+            Self::C | Self::Rust => format!("0x{byte:02X},"),
+            Self::Verilog => format!("{byte:02X}"),
+        }
+    }
+
+    /// Comment holding the text. C gets a block comment, since a line
+    /// comment ending in a backslash would continue onto the next line.
+    fn comment(self, text: &str) -> String {
+        match self {
+            Self::C => format!("/* {} */", text.replace("*/", "* /")),
+            Self::Rust | Self::Verilog => format!("// {text}"),
+        }
+    }
+
+    fn write_header<W: Write>(
+        self,
+        assembly: &Assembly,
+        name: &str,
+        writer: &mut W,
+    ) -> std::io::Result<()> {
+        let origin = assembly.origin;
+        let size = assembly.byte_code.len();
+
+        match self {
+            Self::C => {
+                writeln!(
+                    writer,
+                    "const unsigned short {name}_origin = 0x{origin:04X};"
+                )?;
+                writeln!(writer, "const unsigned char {name}[{size}] = {{")
+            }
+            Self::Rust => {
+                let name = name.to_uppercase();
+
+                writeln!(writer, "pub const {name}_ORIGIN: u16 = 0x{origin:04X};")?;
+                writeln!(writer, "pub const {name}: &[u8] = &[")
+            }
+            Self::Verilog => {
+                writeln!(writer, "// {name}: {size} bytes")?;
+                writeln!(writer, "@{origin:04X}")
+            }
+        }
+    }
+
+    fn write_footer<W: Write>(self, writer: &mut W) -> std::io::Result<()> {
+        match self {
+            Self::C => writeln!(writer, "}};"),
+            Self::Rust => writeln!(writer, "];"),
+            Self::Verilog => Ok(()),
+        }
+    }
+
+    /// Writes the bytes from `address` on, one row at a time, with the
+    /// address of each row and `comment` after the first.
+    fn write_rows<W: Write>(
+        self,
+        bytes: &[u8],
+        address: u16,
+        comment: Option<String>,
+        writer: &mut W,
+    ) -> std::io::Result<()> {
+        let indent = match self {
+            Self::C | Self::Rust => "    ",
+            Self::Verilog => "",
+        };
+        let width = self.byte(0).len() * ROW_LENGTH + ROW_LENGTH - 1;
+        let mut comment = comment;
+
+        for (index, row) in bytes.chunks(ROW_LENGTH).enumerate() {
+            let row_address = address.wrapping_add((index * ROW_LENGTH) as u16);
+            let row: Vec<String> = row.iter().map(|byte| self.byte(*byte)).collect();
+            let line = match comment.take() {
+                Some(comment) => self.comment(&format!("{row_address:04X}: {comment}")),
+                None => self.comment(&format!("{row_address:04X}")),
+            };
+
+            writeln!(writer, "{indent}{:width$} {line}", row.join(" "))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes the bytes of the assembly as source in the language, declaring
+/// them under `name` along with the address they are loaded at. Each
+/// statement's bytes are commented with their address and the line of
+/// `source` they were assembled from.
+pub fn write<W: Write>(
+    assembly: &Assembly,
+    source: &str,
+    language: Language,
+    name: &str,
+    writer: &mut W,
+) -> std::io::Result<()> {
+    let source_lines: Vec<&str> = source.lines().collect();
+    let mut statement_lines: Vec<_> = assembly
+        .statement_lines
+        .iter()
+        .filter(|statement_line| statement_line.byte_size() > 0)
+        .collect();
+    let origin = assembly.origin as usize;
+    let mut address = origin;
+
+    statement_lines.sort_by_key(|statement_line| statement_line.address());
+    language.write_header(assembly, name, writer)?;
+
+    for statement_line in statement_lines {
+        let start = statement_line.address() as usize;
+
+        // Bytes not emitted by any statement, such as padding between
+        // sections, are written without a source line.
+        if start > address {
+            let gap = &assembly.byte_code[address - origin..start - origin];
+
+            language.write_rows(gap, address as u16, None, writer)?;
+        }
+
+        let line = statement_line.line();
+        let text = line
+            .checked_sub(1)
+            .and_then(|index| source_lines.get(index))
+            .map_or("", |text| text.trim());
+
+        language.write_rows(
+            assembly.bytes_of(statement_line),
+            statement_line.address(),
+            Some(format!("line {line}: {text}")),
+            writer,
+        )?;
+        address = start + statement_line.byte_size();
+    }
+
+    if address < origin + assembly.byte_code.len() {
+        let rest = &assembly.byte_code[address - origin..];
+
+        language.write_rows(rest, address as u16, None, writer)?;
+    }
+
+    language.write_footer(writer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        assembler::{assemble, Options},
+        lexer::parse_source,
+    };
+
+    fn emit(source: &str, language: Language) -> String {
+        let assembly = assemble(parse_source(source).unwrap(), &Options::default()).unwrap();
+        let mut output = vec![];
+
+        write(&assembly, source, language, "program", &mut output).unwrap();
+
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn writes_only_the_declaration_of_the_bytes() {
+        let source = "main: LDA 5,i\n STOP\n .END\n";
+
+        assert_eq!(
+            emit(source, Language::C),
+            "const unsigned short program_origin = 0x0000;
+const unsigned char program[4] = {
+    0xC0, 0x00, 0x05,                               /* 0000: line 1: main: LDA 5,i */
+    0x00,                                           /* 0003: line 2: STOP */
+};
+"
+        );
+        assert_eq!(
+            emit(source, Language::Verilog),
+            "// program: 4 bytes
+@0000
+C0 00 05                // 0000: line 1: main: LDA 5,i
+00                      // 0003: line 2: STOP
+"
+        );
+    }
+}
@@ -0,0 +1,153 @@
+use crate::lexer::{SourceLine, Token};
+
+/// Columns of the mnemonic, operand and comment fields in the Pep/8 IDE.
+pub const MNEMONIC_COLUMN: usize = 9;
+pub const OPERAND_COLUMN: usize = 17;
+pub const COMMENT_COLUMN: usize = 29;
+
+/// Pads `line` with spaces up to `column`, or with a single space if it
+/// already reaches it.
+pub fn pad_to(line: &mut String, column: usize) {
+    if line.is_empty() {
+        line.push_str(&" ".repeat(column));
+    } else if line.len() < column {
+        line.push_str(&" ".repeat(column - line.len()));
+    } else {
+        line.push(' ');
+    }
+}
+
+/// Lays out the fields of a line on the Pep/8 IDE columns. Comment-only
+/// lines start at the first column.
+pub fn layout(
+    label: Option<&str>,
+    mnemonic: Option<&str>,
+    operands: &str,
+    comment: Option<&str>,
+) -> String {
+    let mut line = String::new();
+
+    if let Some(label) = label {
+        line.push_str(label);
+        line.push(':');
+    }
+
+    if let Some(mnemonic) = mnemonic {
+        pad_to(&mut line, MNEMONIC_COLUMN);
+        line.push_str(mnemonic);
+
+        if !operands.is_empty() {
+            pad_to(&mut line, OPERAND_COLUMN);
+            line.push_str(operands);
+        }
+    }
+
+    let mut line = line.trim_end().to_string();
+
+    if let Some(comment) = comment {
+        if !line.is_empty() {
+            pad_to(&mut line, COMMENT_COLUMN);
+        }
+
+        line.push(';');
+        line.push_str(comment);
+    }
+
+    line
+}
+
+fn format_line(line: &SourceLine) -> String {
+    let mut fields = line.tokens.iter().zip(&line.spans).peekable();
+    let label = match fields.peek() {
+        Some((Token::Label(label), _)) => {
+            fields.next();
+            Some(label.as_str())
+        }
+        _ => None,
+    };
+    let mut mnemonic = None;
+    let mut formatted = String::new();
+
+    if let Some((token, span)) = fields.next() {
+        let is_instruction = matches!(token, Token::Identifier(_) | Token::Macro(_));
+
+        mnemonic = Some(line.text[span.clone()].to_uppercase());
+
+        let operands: Vec<_> = fields.collect();
+
+        for (index, (token, span)) in operands.iter().enumerate() {
+            let text = &line.text[(*span).clone()];
+
+            // Operands separated only by spaces, as in `.TRAP`, stay apart.
+            if index > 0 && token != &&Token::Comma && operands[index - 1].0 != &Token::Comma {
+                formatted.push(' ');
+            }
+
+            match token {
+                Token::Identifier(mode)
+                    if is_instruction && index > 0 && operands[index - 1].0 == &Token::Comma =>
+                {
+                    formatted.push_str(&mode.to_lowercase())
+                }
+                _ => formatted.push_str(text),
+            }
+        }
+    }
+
+    layout(
+        label,
+        mnemonic.as_deref(),
+        &formatted,
+        line.comment.as_deref(),
+    )
+}
+
+/// Re-emits the source with labels, mnemonics, operands and comments aligned
+/// on the columns used by the Pep/8 IDE.
+///
+/// Mnemonics and dot commands are upper-cased and addressing modes
+/// lower-cased. Operands keep their original spelling and comments are kept
+/// verbatim. Comment-only lines start at the first column. Each line ends
+/// with `newline`.
+pub fn format_source(lines: &[SourceLine], newline: &str) -> String {
+    let mut formatted = String::new();
+
+    for line in lines {
+        formatted.push_str(&format_line(line));
+        formatted.push_str(newline);
+    }
+
+    formatted
+}
+
+/// Line ending used by the source: `\r\n` if its first line ends with one,
+/// and `\n` otherwise.
+pub fn newline(source: &str) -> &'static str {
+    match source.find('\n') {
+        Some(index) if source[..index].ends_with('\r') => "\r\n",
+        _ => "\n",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::parse_source;
+
+    #[test]
+    fn keeps_the_line_ending_of_the_source() {
+        let source = "main: lda 1,I ;x\r\n .END\r\n";
+        let lines = parse_source(source).unwrap();
+        let formatted = format_source(&lines, newline(source));
+
+        assert_eq!(
+            formatted,
+            "main:    LDA     1,i         ;x\r\n         .END\r\n"
+        );
+
+        let lines = parse_source(&formatted).unwrap();
+
+        assert_eq!(format_source(&lines, newline(&formatted)), formatted);
+        assert_eq!(newline("main: STOP\n"), "\n");
+    }
+}
@@ -0,0 +1,615 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    fmt::Display,
+    io::{BufRead, Write},
+    sync::mpsc::{self, Receiver},
+};
+
+use crate::simulator::{Flags, Simulator};
+
+/// Instructions executed by a single `c` packet before the target reports an
+/// interrupt, so that an infinite loop cannot hang the debugger.
+const STEP_LIMIT: usize = 10_000_000;
+
+/// Instructions executed between checks for an interrupt from the debugger.
+const INTERRUPT_CHECK_INTERVAL: usize = 1000;
+
+/// Longest packet the stub accepts or sends, as advertised to the debugger.
+const PACKET_SIZE: usize = 0x4000;
+
+/// Byte sent by the debugger to interrupt a running target.
+const INTERRUPT: u8 = 0x03;
+
+const SIGINT: u8 = 2;
+const SIGILL: u8 = 4;
+const SIGTRAP: u8 = 5;
+
+/// Registers in the order of the `g` packet, as described to GDB by
+/// `target.xml`.
+const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <feature name="org.pep8.cpu">
+    <reg name="a" bitsize="16" type="int16" regnum="0"/>
+    <reg name="x" bitsize="16" type="int16"/>
+    <reg name="sp" bitsize="16" type="data_ptr"/>
+    <reg name="pc" bitsize="16" type="code_ptr"/>
+    <reg name="nzvc" bitsize="16" type="int16"/>
+  </feature>
+</target>
+"#;
+
+const REGISTER_COUNT: usize = 5;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok())
+        .collect()
+}
+
+/// Parses an `address,length` pair of hex numbers.
+fn parse_range(arguments: &str) -> Option<(u16, usize)> {
+    let (address, length) = arguments.split_once(',')?;
+
+    Some((
+        u16::from_str_radix(address, 16).ok()?,
+        usize::from_str_radix(length, 16).ok()?,
+    ))
+}
+
+/// Stub exposing a simulated Pep/8 machine to debuggers speaking the GDB
+/// remote serial protocol.
+///
+/// Registers are numbered A, X, SP, PC and then the status flags packed as
+/// `NZVC`, and are transferred big-endian like the rest of Pep/8 memory.
+pub struct Stub {
+    simulator: Simulator,
+    breakpoints: HashSet<u16>,
+    last_packet: String,
+    /// Bytes read on another thread, so that an interrupt can be seen while
+    /// the program runs.
+    incoming: Option<Receiver<std::io::Result<u8>>>,
+    /// Bytes read while the program ran, to be handled once it stops.
+    deferred: VecDeque<std::io::Result<u8>>,
+}
+
+impl Stub {
+    pub fn new(simulator: Simulator) -> Self {
+        Self {
+            simulator,
+            breakpoints: HashSet::new(),
+            last_packet: String::new(),
+            incoming: None,
+            deferred: VecDeque::new(),
+        }
+    }
+
+    /// Next byte from the debugger, or `None` once the connection is closed.
+    fn next_byte(&mut self) -> Result<Option<u8>, Error> {
+        let byte = match self.deferred.pop_front() {
+            Some(byte) => Some(byte),
+            None => self
+                .incoming
+                .as_ref()
+                .and_then(|receiver| receiver.recv().ok()),
+        };
+
+        Ok(byte.transpose()?)
+    }
+
+    /// Whether the debugger sent an interrupt since the program was resumed.
+    fn interrupted(&mut self) -> bool {
+        while let Some(byte) = self
+            .incoming
+            .as_ref()
+            .and_then(|receiver| receiver.try_recv().ok())
+        {
+            match byte {
+                Ok(INTERRUPT) => return true,
+                byte => self.deferred.push_back(byte),
+            }
+        }
+
+        false
+    }
+
+    /// Serves packets until the debugger detaches, kills the target or closes
+    /// the connection.
+    pub fn serve<R: BufRead + Send + 'static, W: Write>(
+        &mut self,
+        reader: R,
+        mut writer: W,
+    ) -> Result<(), Error> {
+        let (sender, receiver) = mpsc::channel();
+
+        std::thread::spawn(move || {
+            for byte in reader.bytes() {
+                let failed = byte.is_err();
+
+                if sender.send(byte).is_err() || failed {
+                    break;
+                }
+            }
+        });
+        self.incoming = Some(receiver);
+
+        loop {
+            let Some(byte) = self.next_byte()? else {
+                return Ok(());
+            };
+
+            match byte {
+                b'$' => {}
+                b'-' => {
+                    let packet = std::mem::take(&mut self.last_packet);
+                    self.send(&mut writer, &packet)?;
+                    continue;
+                }
+                // Acknowledgements and interrupts received while stopped.
+                _ => continue,
+            }
+
+            let mut data = vec![];
+
+            loop {
+                match self.next_byte()? {
+                    None => return Ok(()),
+                    Some(b'#') => break,
+                    Some(byte) => data.push(byte),
+                }
+            }
+
+            let mut checksum = [0; 2];
+
+            for digit in &mut checksum {
+                match self.next_byte()? {
+                    None => return Ok(()),
+                    Some(byte) => *digit = byte,
+                }
+            }
+
+            let expected = std::str::from_utf8(&checksum)
+                .ok()
+                .and_then(|checksum| u8::from_str_radix(checksum, 16).ok());
+
+            if expected != Some(data.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte))) {
+                writer.write_all(b"-")?;
+                writer.flush()?;
+                continue;
+            }
+
+            writer.write_all(b"+")?;
+
+            let packet = String::from_utf8_lossy(&data);
+
+            if !self.handle(&packet, &mut writer)? {
+                return Ok(());
+            }
+        }
+    }
+
+    fn send<W: Write>(&mut self, writer: &mut W, packet: &str) -> Result<(), Error> {
+        let checksum = packet.bytes().fold(0u8, |sum, byte| sum.wrapping_add(byte));
+
+        write!(writer, "${packet}#{checksum:02x}")?;
+        writer.flush()?;
+        self.last_packet = packet.to_string();
+
+        Ok(())
+    }
+
+    fn registers(&self) -> [u16; REGISTER_COUNT] {
+        let cpu = &self.simulator.cpu;
+
+        [cpu.a, cpu.x, cpu.sp, cpu.pc, cpu.flags.as_u16()]
+    }
+
+    fn set_register(&mut self, number: usize, value: u16) -> bool {
+        let cpu = &mut self.simulator.cpu;
+
+        match number {
+            0 => cpu.a = value,
+            1 => cpu.x = value,
+            2 => cpu.sp = value,
+            3 => cpu.pc = value,
+            4 => cpu.flags = Flags::from_u16(value),
+            _ => return false,
+        }
+
+        true
+    }
+
+    /// Handles one packet, returning false once the session is over.
+    fn handle<W: Write>(&mut self, packet: &str, writer: &mut W) -> Result<bool, Error> {
+        let command = packet.get(..1).unwrap_or_default();
+        let arguments = packet.get(1..).unwrap_or_default();
+
+        let reply = match command {
+            "?" => format!("S{SIGTRAP:02x}"),
+            "g" => self
+                .registers()
+                .iter()
+                .map(|register| to_hex(&register.to_be_bytes()))
+                .collect(),
+            "G" => match from_hex(arguments) {
+                Some(bytes) if bytes.len() == 2 * REGISTER_COUNT => {
+                    for (number, value) in bytes.chunks(2).enumerate() {
+                        self.set_register(number, u16::from_be_bytes([value[0], value[1]]));
+                    }
+
+                    String::from("OK")
+                }
+                _ => String::from("E01"),
+            },
+            "p" => match usize::from_str_radix(arguments, 16) {
+                Ok(number) if number < REGISTER_COUNT => {
+                    to_hex(&self.registers()[number].to_be_bytes())
+                }
+                _ => String::from("E01"),
+            },
+            "P" => {
+                let register = arguments.split_once('=').and_then(|(number, value)| {
+                    let value = from_hex(value)?;
+
+                    Some((
+                        usize::from_str_radix(number, 16).ok()?,
+                        u16::from_be_bytes(value.try_into().ok()?),
+                    ))
+                });
+
+                match register {
+                    Some((number, value)) if self.set_register(number, value) => String::from("OK"),
+                    _ => String::from("E01"),
+                }
+            }
+            "m" => match parse_range(arguments) {
+                Some((address, length)) => {
+                    // Longer reads are cut short to fit one packet as hex,
+                    // and the debugger asks for the rest.
+                    let bytes: Vec<u8> = (0..length.min(PACKET_SIZE / 2))
+                        .map(|offset| {
+                            self.simulator
+                                .read_byte(address.wrapping_add(offset as u16))
+                        })
+                        .collect();
+
+                    to_hex(&bytes)
+                }
+                None => String::from("E01"),
+            },
+            "M" => {
+                let write = arguments.split_once(':').and_then(|(range, data)| {
+                    let (address, length) = parse_range(range)?;
+                    let bytes = from_hex(data)?;
+
+                    (bytes.len() == length).then_some((address, bytes))
+                });
+
+                match write {
+                    Some((address, bytes)) => {
+                        for (offset, byte) in bytes.into_iter().enumerate() {
+                            self.simulator
+                                .write_byte(address.wrapping_add(offset as u16), byte);
+                        }
+
+                        String::from("OK")
+                    }
+                    None => String::from("E01"),
+                }
+            }
+            "Z" | "z" => match arguments.strip_prefix("0,").and_then(parse_range) {
+                Some((address, _)) => {
+                    if command == "Z" {
+                        self.breakpoints.insert(address);
+                    } else {
+                        self.breakpoints.remove(&address);
+                    }
+
+                    String::from("OK")
+                }
+                // Only software breakpoints are supported.
+                None => String::new(),
+            },
+            "s" | "c" => {
+                if let Ok(address) = u16::from_str_radix(arguments, 16) {
+                    self.simulator.cpu.pc = address;
+                }
+
+                self.resume(command == "s", writer)?
+            }
+            "H" => String::from("OK"),
+            "k" => return Ok(false),
+            "D" => {
+                self.send(writer, "OK")?;
+                return Ok(false);
+            }
+            _ => self.query(packet),
+        };
+
+        self.send(writer, &reply)?;
+
+        Ok(true)
+    }
+
+    /// Reply to the general query packets, or an empty reply for anything
+    /// unsupported.
+    fn query(&self, packet: &str) -> String {
+        if packet.starts_with("qSupported") {
+            return format!("PacketSize={PACKET_SIZE:x};qXfer:features:read+;swbreak+");
+        }
+
+        if packet == "qAttached" {
+            return String::from("1");
+        }
+
+        if let Some(range) = packet.strip_prefix("qXfer:features:read:target.xml:") {
+            return match parse_range(range) {
+                Some((offset, length)) => {
+                    let rest = TARGET_XML.get(offset as usize..).unwrap_or_default();
+
+                    if rest.len() > length {
+                        format!("m{}", &rest[..length])
+                    } else {
+                        format!("l{rest}")
+                    }
+                }
+                None => String::from("E01"),
+            };
+        }
+
+        String::new()
+    }
+
+    /// Runs the program for one instruction or until a breakpoint or an
+    /// interrupt, forwarding its output to the debugger console, and returns
+    /// the stop reply.
+    fn resume<W: Write>(&mut self, single_step: bool, writer: &mut W) -> Result<String, Error> {
+        let mut stop = format!("S{SIGINT:02x}");
+        let mut failure = None;
+
+        for count in 0..STEP_LIMIT {
+            if self.simulator.halted() {
+                stop = String::from("W00");
+                break;
+            }
+
+            if count > 0 && self.breakpoints.contains(&self.simulator.cpu.pc) {
+                stop = format!("T{SIGTRAP:02x}swbreak:;");
+                break;
+            }
+
+            if count > 0 && count % INTERRUPT_CHECK_INTERVAL == 0 && self.interrupted() {
+                break;
+            }
+
+            if let Err(error) = self.simulator.step() {
+                failure = Some(format!("{error}\n"));
+                stop = format!("S{SIGILL:02x}");
+                break;
+            }
+
+            if self.simulator.halted() {
+                stop = String::from("W00");
+                break;
+            }
+
+            if single_step {
+                stop = format!("S{SIGTRAP:02x}");
+                break;
+            }
+        }
+
+        let mut output = self.simulator.take_output();
+
+        output.extend(failure.unwrap_or_default().bytes());
+
+        if !output.is_empty() {
+            self.send(writer, &format!("O{}", to_hex(&output)))?;
+        }
+
+        Ok(stop)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        assembler::{assemble, Options},
+        lexer::parse_source,
+    };
+
+    fn stub(source: &str, input: &[u8]) -> Stub {
+        let assembly = assemble(parse_source(source).unwrap(), &Options::default()).unwrap();
+
+        Stub::new(Simulator::new(&assembly.byte_code, input).unwrap())
+    }
+
+    fn packet(data: &str) -> String {
+        let checksum = data.bytes().fold(0u8, |sum, byte| sum.wrapping_add(byte));
+
+        format!("${data}#{checksum:02x}")
+    }
+
+    /// Serves the packets, with any extra bytes given between them, and
+    /// returns everything the stub sent.
+    fn transcript(stub: &mut Stub, input: &[&str]) -> String {
+        let input: String = input
+            .iter()
+            .map(|data| match data.strip_prefix('!') {
+                Some(raw) => raw.to_string(),
+                None => packet(data),
+            })
+            .collect();
+        let mut output = vec![];
+
+        stub.serve(std::io::Cursor::new(input.into_bytes()), &mut output)
+            .unwrap();
+
+        String::from_utf8(output).unwrap()
+    }
+
+    /// Replies in the output, without acknowledgements and framing.
+    fn replies(output: &str) -> Vec<&str> {
+        output
+            .split('$')
+            .skip(1)
+            .map(|reply| reply.split_once('#').unwrap().0)
+            .collect()
+    }
+
+    const PROGRAM: &str = "         LDA     5,i
+         ADDA    2,i
+         CHARO   'H',i
+         STOP
+         .END
+";
+
+    #[test]
+    fn frames_packets_and_rejects_bad_checksums() {
+        let mut stub = stub(PROGRAM, b"");
+        let output = transcript(&mut stub, &["!$g#00", "!+", "g", "!-", "D"]);
+
+        assert_eq!(
+            output,
+            format!(
+                "-+{}{}+{}",
+                packet("00000000fbcf00000000"),
+                packet("00000000fbcf00000000"),
+                packet("OK")
+            )
+        );
+    }
+
+    #[test]
+    fn reads_and_writes_registers_and_memory() {
+        let mut stub = stub(PROGRAM, b"");
+        let output = transcript(
+            &mut stub,
+            &[
+                "G00010002000300040008",
+                "g",
+                "p3",
+                "P0=1234",
+                "p0",
+                "P9=0000",
+                "m0,4",
+                "M1,2:abcd",
+                "m0,4",
+                "M1,2:ab",
+                "m0,10000",
+                "k",
+            ],
+        );
+        let mut replies = replies(&output);
+        let long_read = replies.pop().unwrap();
+
+        assert_eq!(long_read.len(), PACKET_SIZE);
+        assert!(long_read.starts_with("c0abcd70"));
+        assert_eq!(
+            replies,
+            [
+                "OK",
+                "00010002000300040008",
+                "0004",
+                "OK",
+                "1234",
+                "E01",
+                "c0000570",
+                "OK",
+                "c0abcd70",
+                "E01",
+            ]
+        );
+        assert_eq!(stub.simulator.cpu.sp, 3);
+        assert!(stub.simulator.cpu.flags.n);
+    }
+
+    #[test]
+    fn stops_at_breakpoints_and_after_single_steps() {
+        let mut stub = stub(PROGRAM, b"");
+        let output = transcript(
+            &mut stub,
+            &["Z0,3,1", "c", "p0", "z0,3,1", "s", "p3", "Z1,0,1", "c", "k"],
+        );
+
+        assert_eq!(
+            replies(&output),
+            [
+                "OK",
+                "T05swbreak:;",
+                "0005",
+                "OK",
+                "S05",
+                "0006",
+                "",
+                "O48",
+                "W00",
+            ]
+        );
+    }
+
+    #[test]
+    fn stops_when_interrupted() {
+        let mut stub = stub(
+            "         LDX     0,i
+loop:    SUBX    1,i
+         BRNE    loop
+         STOP
+         .END
+",
+            b"",
+        );
+        let output = transcript(&mut stub, &["c", "!\x03", "?", "k"]);
+
+        assert_eq!(replies(&output), ["S02", "S05"]);
+        assert_ne!(stub.simulator.cpu.x, 0);
+    }
+
+    #[test]
+    fn describes_the_registers() {
+        let mut stub = stub(PROGRAM, b"");
+        let output = transcript(
+            &mut stub,
+            &[
+                "qSupported:swbreak+",
+                "qXfer:features:read:target.xml:0,10",
+                "k",
+            ],
+        );
+        let replies = replies(&output);
+
+        assert_eq!(replies[0], "PacketSize=4000;qXfer:features:read+;swbreak+");
+        assert_eq!(replies[1], format!("m{}", &TARGET_XML[..16]));
+    }
+}
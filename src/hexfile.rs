@@ -0,0 +1,360 @@
+use std::{fmt::Display, io::Write, path::Path};
+
+use clap::ValueEnum;
+
+use crate::assembler::MEMORY_SIZE;
+
+#[derive(Debug)]
+pub enum Error {
+    MalformedRecord(usize),
+    ChecksumMismatch(usize),
+    UnsupportedRecord { line: usize, kind: String },
+    AddressOutOfRange(usize),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MalformedRecord(line) => write!(f, "line {line}: malformed record"),
+            Self::ChecksumMismatch(line) => write!(f, "line {line}: checksum mismatch"),
+            Self::UnsupportedRecord { line, kind } => {
+                write!(f, "line {line}: unsupported record type {kind}")
+            }
+            Self::AddressOutOfRange(line) => {
+                write!(f, "line {line}: data extends past the end of memory")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Format of a file holding a program's bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// The bytes alone, loaded at address 0.
+    Binary,
+    /// Intel HEX records.
+    Ihex,
+    /// Motorola S-records.
+    Srec,
+}
+
+impl Format {
+    /// Format of a file with the usual extension of Intel HEX or S-record
+    /// files.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+
+        match &extension[..] {
+            "hex" | "ihx" | "ihex" => Some(Self::Ihex),
+            "srec" | "s19" | "mot" => Some(Self::Srec),
+            _ => None,
+        }
+    }
+}
+
+/// Longest record written, limited by the S-record byte count that also
+/// covers the address and checksum.
+pub const MAX_RECORD_LENGTH: usize = 252;
+
+/// Bytes read from a file, starting at `origin`. Gaps between records are
+/// zero.
+#[derive(Debug, Default)]
+pub struct Program {
+    pub origin: u16,
+    pub bytes: Vec<u8>,
+}
+
+/// Writes the bytes as Intel HEX data records of at most `record_length`
+/// bytes, the first one at `base`, followed by an end of file record.
+pub fn write_intel_hex<W: Write>(
+    bytes: &[u8],
+    base: u16,
+    record_length: usize,
+    writer: &mut W,
+) -> std::io::Result<()> {
+    assert!((1..=MAX_RECORD_LENGTH).contains(&record_length));
+
+    let mut address = base;
+
+    for chunk in bytes.chunks(record_length) {
+        let mut record = vec![chunk.len() as u8];
+
+        record.extend(address.to_be_bytes());
+        record.push(0x00);
+        record.extend(chunk);
+        write_intel_hex_record(&record, writer)?;
+        address = address.wrapping_add(chunk.len() as u16);
+    }
+
+    write_intel_hex_record(&[0x00, 0x00, 0x00, 0x01], writer)
+}
+
+fn write_intel_hex_record<W: Write>(record: &[u8], writer: &mut W) -> std::io::Result<()> {
+    let sum = record.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+
+    write!(writer, ":")?;
+
+    for byte in record {
+        write!(writer, "{byte:02X}")?;
+    }
+
+    writeln!(writer, "{:02X}", sum.wrapping_neg())
+}
+
+/// Writes the bytes as S1 records of at most `record_length` bytes, the
+/// first one at `base`, between an S0 header and an S9 record giving `base`
+/// as the start address.
+pub fn write_srecord<W: Write>(
+    bytes: &[u8],
+    base: u16,
+    record_length: usize,
+    writer: &mut W,
+) -> std::io::Result<()> {
+    assert!((1..=MAX_RECORD_LENGTH).contains(&record_length));
+
+    write_srecord_record('0', 0, b"pep8", writer)?;
+
+    let mut address = base;
+
+    for chunk in bytes.chunks(record_length) {
+        write_srecord_record('1', address, chunk, writer)?;
+        address = address.wrapping_add(chunk.len() as u16);
+    }
+
+    write_srecord_record('9', base, &[], writer)
+}
+
+fn write_srecord_record<W: Write>(
+    kind: char,
+    address: u16,
+    data: &[u8],
+    writer: &mut W,
+) -> std::io::Result<()> {
+    let mut record = vec![(data.len() + 3) as u8];
+
+    record.extend(address.to_be_bytes());
+    record.extend(data);
+
+    let sum = record.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+
+    write!(writer, "S{kind}")?;
+
+    for byte in record {
+        write!(writer, "{byte:02X}")?;
+    }
+
+    writeln!(writer, "{:02X}", !sum)
+}
+
+/// Decodes the hexadecimal digits of a record.
+fn record_bytes(digits: &str, line: usize) -> Result<Vec<u8>, Error> {
+    if !digits.len().is_multiple_of(2) || !digits.is_ascii() {
+        return Err(Error::MalformedRecord(line));
+    }
+
+    (0..digits.len())
+        .step_by(2)
+        .map(|index| {
+            u8::from_str_radix(&digits[index..index + 2], 16)
+                .map_err(|_| Error::MalformedRecord(line))
+        })
+        .collect()
+}
+
+/// Collects the data of the records into one run of bytes from the lowest
+/// address written.
+fn assemble_chunks(chunks: Vec<(usize, Vec<u8>)>) -> Program {
+    let Some(origin) = chunks.iter().map(|(address, _)| *address).min() else {
+        return Program::default();
+    };
+    let end = chunks
+        .iter()
+        .map(|(address, data)| address + data.len())
+        .max()
+        .unwrap_or(origin);
+    let mut bytes = vec![0; end - origin];
+
+    for (address, data) in chunks {
+        bytes[address - origin..address - origin + data.len()].copy_from_slice(&data);
+    }
+
+    Program {
+        origin: origin as u16,
+        bytes,
+    }
+}
+
+/// Reads the data records of an Intel HEX file, up to its end of file
+/// record. Extended address records must keep the data within 64 KiB.
+pub fn read_intel_hex(text: &str) -> Result<Program, Error> {
+    let mut chunks = vec![];
+    let mut upper_address = 0;
+
+    for (index, text_line) in text.lines().enumerate() {
+        let line = index + 1;
+        let text_line = text_line.trim();
+
+        if text_line.is_empty() {
+            continue;
+        }
+
+        let digits = text_line
+            .strip_prefix(':')
+            .ok_or(Error::MalformedRecord(line))?;
+        let record = record_bytes(digits, line)?;
+
+        if record.len() < 5 || record.len() != record[0] as usize + 5 {
+            return Err(Error::MalformedRecord(line));
+        }
+
+        if record.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)) != 0 {
+            return Err(Error::ChecksumMismatch(line));
+        }
+
+        let address = u16::from_be_bytes([record[1], record[2]]) as usize;
+        let data = &record[4..record.len() - 1];
+
+        match record[3] {
+            0x00 => {
+                let address = upper_address + address;
+
+                if address + data.len() > MEMORY_SIZE {
+                    return Err(Error::AddressOutOfRange(line));
+                }
+
+                chunks.push((address, data.to_vec()));
+            }
+            0x01 => break,
+            0x02 | 0x04 if data.len() == 2 => {
+                let value = u16::from_be_bytes([data[0], data[1]]) as usize;
+
+                upper_address = if record[3] == 0x02 {
+                    value << 4
+                } else {
+                    value << 16
+                };
+            }
+            // Start addresses do not affect what is loaded.
+            0x03 | 0x05 => {}
+            kind => {
+                return Err(Error::UnsupportedRecord {
+                    line,
+                    kind: format!("{kind:02X}"),
+                })
+            }
+        }
+    }
+
+    Ok(assemble_chunks(chunks))
+}
+
+/// Reads the data records of an S-record file, with addresses of any width
+/// as long as the data stays within 64 KiB.
+pub fn read_srecord(text: &str) -> Result<Program, Error> {
+    let mut chunks = vec![];
+
+    for (index, text_line) in text.lines().enumerate() {
+        let line = index + 1;
+        let text_line = text_line.trim();
+
+        if text_line.is_empty() {
+            continue;
+        }
+
+        let (kind, digits) = match text_line.strip_prefix(['S', 's']) {
+            Some(rest) if !rest.is_empty() && rest.is_char_boundary(1) => rest.split_at(1),
+            _ => return Err(Error::MalformedRecord(line)),
+        };
+        let record = record_bytes(digits, line)?;
+
+        if record.len() < 2 || record.len() != record[0] as usize + 1 {
+            return Err(Error::MalformedRecord(line));
+        }
+
+        if record.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)) != 0xFF {
+            return Err(Error::ChecksumMismatch(line));
+        }
+
+        let address_size = match kind {
+            "0" | "1" | "5" | "9" => 2,
+            "2" | "6" | "8" => 3,
+            "3" | "7" => 4,
+            _ => {
+                return Err(Error::UnsupportedRecord {
+                    line,
+                    kind: format!("S{kind}"),
+                })
+            }
+        };
+
+        if record.len() < address_size + 2 {
+            return Err(Error::MalformedRecord(line));
+        }
+
+        if let "1" | "2" | "3" = kind {
+            let address = record[1..=address_size]
+                .iter()
+                .fold(0usize, |address, byte| address << 8 | *byte as usize);
+            let data = &record[address_size + 1..record.len() - 1];
+
+            if address + data.len() > MEMORY_SIZE {
+                return Err(Error::AddressOutOfRange(line));
+            }
+
+            chunks.push((address, data.to_vec()));
+        }
+    }
+
+    Ok(assemble_chunks(chunks))
+}
+
+/// Reads a program file in the given format.
+pub fn read(path: &Path, format: Format) -> Result<Program, Box<dyn std::error::Error>> {
+    Ok(match format {
+        Format::Binary => Program {
+            origin: 0,
+            bytes: std::fs::read(path)?,
+        },
+        Format::Ihex => read_intel_hex(&std::fs::read_to_string(path)?)?,
+        Format::Srec => read_srecord(&std::fs::read_to_string(path)?)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_back_the_records_it_writes() {
+        let bytes: Vec<u8> = (0..=40).collect();
+
+        let mut records = vec![];
+        write_intel_hex(&bytes, 0x0100, 16, &mut records).unwrap();
+        let text = String::from_utf8(records).unwrap();
+        assert!(text.lines().all(|line| line.starts_with(':')));
+        assert_eq!(text.lines().last(), Some(":00000001FF"));
+        let program = read_intel_hex(&text).unwrap();
+        assert_eq!((program.origin, program.bytes), (0x0100, bytes.clone()));
+
+        let mut records = vec![];
+        write_srecord(&bytes, 0x0100, 16, &mut records).unwrap();
+        let text = String::from_utf8(records).unwrap();
+        assert!(text.lines().all(|line| line.starts_with('S')));
+        let program = read_srecord(&text).unwrap();
+        assert_eq!((program.origin, program.bytes), (0x0100, bytes));
+    }
+
+    #[test]
+    fn rejects_records_with_a_bad_checksum() {
+        assert!(matches!(
+            read_intel_hex(":0100000041BF\n:00000001FF\n"),
+            Err(Error::ChecksumMismatch(1))
+        ));
+        assert!(matches!(
+            read_srecord("S1040000410A\n"),
+            Err(Error::ChecksumMismatch(1))
+        ));
+    }
+}
@@ -0,0 +1,229 @@
+use std::{fmt::Display, io::Write};
+
+use crate::assembler::{Assembly, Statement, MEMORY_SIZE};
+
+#[derive(Debug)]
+pub enum Error {
+    OsTooLarge(usize),
+    OsOverlap { program_end: u16, os_start: u16 },
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::OsTooLarge(size) => {
+                write!(f, "operating system of {size} bytes does not fit in memory")
+            }
+            Self::OsOverlap {
+                program_end,
+                os_start,
+            } => write!(
+                f,
+                "program ends at 0x{program_end:04X}, past the operating system at 0x{os_start:04X}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RegionKind {
+    Code,
+    Data,
+    Os,
+}
+
+impl Display for RegionKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Code => write!(f, "code"),
+            Self::Data => write!(f, "data"),
+            Self::Os => write!(f, "os"),
+        }
+    }
+}
+
+/// Range of memory holding one kind of contents, from `start` to `end`
+/// inclusive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Region {
+    pub start: u16,
+    pub end: u16,
+    pub kind: RegionKind,
+}
+
+/// Contents of the whole memory once the program, and the operating system
+/// if any, are loaded.
+pub struct Image {
+    pub memory: Vec<u8>,
+    /// Regions in address order. Memory outside of them holds the fill
+    /// value.
+    pub regions: Vec<Region>,
+}
+
+impl Image {
+    /// Loads the program at its origin and the operating system ROM at the
+    /// top of memory, filling the rest of memory with `fill`.
+    pub fn new(assembly: &Assembly, os: Option<&[u8]>, fill: u8) -> Result<Self, Error> {
+        let mut memory = vec![fill; MEMORY_SIZE];
+        let mut regions: Vec<Region> = vec![];
+        let origin = assembly.origin as usize;
+        let program_end = origin + assembly.byte_code.len();
+
+        memory[origin..program_end].copy_from_slice(&assembly.byte_code);
+
+        for statement_line in &assembly.statement_lines {
+            let size = statement_line.byte_size();
+
+            if size == 0 {
+                continue;
+            }
+
+            let kind = match statement_line.statement() {
+                Statement::Instruction(_) => RegionKind::Code,
+                Statement::DotCommand(_) => RegionKind::Data,
+            };
+            let start = statement_line.address();
+            let end = start + (size - 1) as u16;
+
+            match regions.last_mut() {
+                Some(region)
+                    if region.kind == kind && region.end as usize + 1 == start as usize =>
+                {
+                    region.end = end
+                }
+                _ => regions.push(Region { start, end, kind }),
+            }
+        }
+
+        if let Some(os) = os {
+            if os.len() > MEMORY_SIZE {
+                return Err(Error::OsTooLarge(os.len()));
+            }
+
+            let os_start = MEMORY_SIZE - os.len();
+
+            if program_end > os_start {
+                return Err(Error::OsOverlap {
+                    program_end: (program_end - 1) as u16,
+                    os_start: os_start as u16,
+                });
+            }
+
+            if !os.is_empty() {
+                memory[os_start..].copy_from_slice(os);
+                regions.push(Region {
+                    start: os_start as u16,
+                    end: (MEMORY_SIZE - 1) as u16,
+                    kind: RegionKind::Os,
+                });
+            }
+        }
+
+        Ok(Self { memory, regions })
+    }
+
+    /// Writes one line per region, giving its address range and kind.
+    pub fn write_region_map<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        for region in &self.regions {
+            let size = region.end as usize - region.start as usize + 1;
+
+            writeln!(
+                writer,
+                "{:04X}-{:04X}  {:<4}  {size} bytes",
+                region.start,
+                region.end,
+                region.kind.to_string()
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        assembler::{assemble, Options},
+        lexer::parse_source,
+    };
+
+    fn assembly(source: &str, origin: u16) -> Assembly {
+        let options = Options {
+            origin,
+            ..Options::default()
+        };
+
+        assemble(parse_source(source).unwrap(), &options).unwrap()
+    }
+
+    fn region(start: u16, end: u16, kind: RegionKind) -> Region {
+        Region { start, end, kind }
+    }
+
+    const PROGRAM: &str = "         BR      main
+num:     .BLOCK  2
+msg:     .ASCII  \"hi\"
+main:    LDA     num,d
+         STOP
+         .END
+";
+
+    #[test]
+    fn loads_the_program_and_os_over_the_fill_value() {
+        let image = Image::new(&assembly(PROGRAM, 0x8000), Some(&[0xAA; 4]), 0xFF).unwrap();
+
+        assert_eq!(image.memory.len(), MEMORY_SIZE);
+        assert_eq!(
+            &image.memory[0x7FFF..0x8004],
+            [0xFF, 0x04, 0x80, 0x07, 0x00]
+        );
+        assert_eq!(&image.memory[0x800A..0x800C], [0x00, 0xFF]);
+        assert_eq!(&image.memory[0xFFFB..], [0xFF, 0xAA, 0xAA, 0xAA, 0xAA]);
+        assert!(image.memory[..0x8000].iter().all(|byte| *byte == 0xFF));
+        assert_eq!(
+            image.regions,
+            [
+                region(0x8000, 0x8002, RegionKind::Code),
+                region(0x8003, 0x8006, RegionKind::Data),
+                region(0x8007, 0x800A, RegionKind::Code),
+                region(0xFFFC, 0xFFFF, RegionKind::Os),
+            ]
+        );
+
+        let mut map = vec![];
+        image.write_region_map(&mut map).unwrap();
+        assert_eq!(
+            String::from_utf8(map).unwrap(),
+            "8000-8002  code  3 bytes
+8003-8006  data  4 bytes
+8007-800A  code  4 bytes
+FFFC-FFFF  os    4 bytes
+"
+        );
+    }
+
+    #[test]
+    fn leaves_out_an_empty_os() {
+        let image = Image::new(&assembly(PROGRAM, 0), Some(&[]), 0).unwrap();
+
+        assert_eq!(image.regions.last().unwrap().kind, RegionKind::Code);
+    }
+
+    #[test]
+    fn rejects_an_os_that_does_not_fit() {
+        assert!(matches!(
+            Image::new(&assembly(PROGRAM, 0xFFF0), Some(&[0; 8]), 0),
+            Err(Error::OsOverlap {
+                program_end: 0xFFFA,
+                os_start: 0xFFF8
+            })
+        ));
+        assert!(matches!(
+            Image::new(&assembly(PROGRAM, 0), Some(&vec![0; MEMORY_SIZE + 1]), 0),
+            Err(Error::OsTooLarge(size)) if size == MEMORY_SIZE + 1
+        ));
+    }
+}
@@ -1,422 +1,241 @@
-use crate::address::{Address, AddressTable};
+use std::fmt::Display;
+
+use clap::ValueEnum;
+
+use crate::address::{AddrMode, Address, AddressTable};
+use crate::isa::{self, Opcode, Operand, Operation};
 use crate::lexer::Token;
 use crate::register::Register;
-use crate::types::Pep8Byte;
+use crate::suggest::suggest;
+use crate::target::Target;
+use crate::traps::Trap;
 
 #[derive(Debug)]
-pub enum Instruction {
-    STOP,
-    RETTR,
-    MOVSPA,
-    MOVFLGA,
-    BR(Address),
-    BRLE(Address),
-    BRLT(Address),
-    BREQ(Address),
-    BRNE(Address),
-    BRGE(Address),
-    BRGT(Address),
-    BRV(Address),
-    BRC(Address),
-    CALL(Address),
-    NOTr(Register),
-    NEGr(Register),
-    ASLr(Register),
-    ASRr(Register),
-    ROLr(Register),
-    RORr(Register),
-    NOPn(Pep8Byte),
-    NOP(Address),
-    DECI(Address),
-    DECO(Address),
-    STRO(Address),
-    CHARI(Address),
-    CHARO(Address),
-    RETn(Pep8Byte),
-    ADDSP(Address),
-    SUBSP(Address),
-    ADDr(Register, Address),
-    SUBr(Register, Address),
-    ANDr(Register, Address),
-    ORr(Register, Address),
-    CPr(Register, Address),
-    LDr(Register, Address),
-    LDBYTEr(Register, Address),
-    STr(Register, Address),
-    STBYTEr(Register, Address),
+pub enum Error {
+    InvalidMnemonic {
+        mnemonic: String,
+        suggestion: Option<&'static str>,
+    },
+    MissingRegisterSuffix(String),
+    NotInInstructionSet(Operation, Target),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidMnemonic {
+                mnemonic,
+                suggestion: Some(suggestion),
+            } => write!(
+                f,
+                "invalid instruction mnemonic: {mnemonic} (did you mean {suggestion}?)"
+            ),
+            Self::InvalidMnemonic {
+                mnemonic,
+                suggestion: None,
+            } => write!(f, "invalid instruction mnemonic: {mnemonic}"),
+            Self::MissingRegisterSuffix(mnemonic) => write!(
+                f,
+                "{mnemonic} requires a register suffix (did you mean {mnemonic}A or {mnemonic}X?)"
+            ),
+            Self::NotInInstructionSet(operation, target) => {
+                write!(f, "{operation:?} is not in the {target} instruction set")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Addressing modes accepted by a mnemonic on the target, empty for unary
+/// instructions and unknown mnemonics.
+pub fn addr_modes(mnemonic: &str, target: Target) -> &'static [AddrMode] {
+    isa::lookup(mnemonic, target).map_or(&[], |opcode| opcode.modes)
+}
+
+#[derive(Debug)]
+pub struct Instruction {
+    pub operation: Operation,
+    pub register: Option<Register>,
+    /// Value of the `n` field of `NOPn` and `RETn`.
+    pub count: Option<u8>,
+    pub address: Option<Address>,
+    /// Mnemonic of the custom trap the instruction was written with, if any.
+    pub trap: Option<String>,
 }
 
 impl Instruction {
-    pub fn from_tokens(tokens: &[Token]) -> Result<Self, Box<dyn std::error::Error>> {
+    /// Parses an instruction, accepting only the mnemonics and addressing
+    /// modes of the target. Mnemonics that different targets give to the same
+    /// operation, such as `LDA` and `LDWA`, give the same instruction. Custom
+    /// traps are parsed as the trap they replace.
+    pub fn from_tokens(
+        tokens: &[Token],
+        target: Target,
+        traps: &[Trap],
+    ) -> Result<Self, Box<dyn std::error::Error>> {
         let [Token::Identifier(instruction), other_tokens @ ..] = tokens else {
             panic!("missing instruction token");
         };
+        let mnemonic = instruction.to_uppercase();
+
+        if let Some(trap) = traps.iter().find(|trap| trap.mnemonic == mnemonic) {
+            let address = Self::parse_operand(other_tokens, trap.slot.operand, &trap.modes)?;
 
-        match &instruction.to_uppercase()[..] {
-            "STOP" => Ok(Self::STOP),
-            "RETTR" => Ok(Self::RETTR),
-            "MOVSPA" => Ok(Self::MOVSPA),
-            "MOVFLGA" => Ok(Self::MOVFLGA),
-            "BR" => Ok(Self::BR(Address::from_tokens_short(other_tokens)?)),
-            "BRLE" => Ok(Self::BRLE(Address::from_tokens_short(other_tokens)?)),
-            "BRLT" => Ok(Self::BRLT(Address::from_tokens_short(other_tokens)?)),
-            "BREQ" => Ok(Self::BREQ(Address::from_tokens_short(other_tokens)?)),
-            "BRNE" => Ok(Self::BRNE(Address::from_tokens_short(other_tokens)?)),
-            "BRGE" => Ok(Self::BRGE(Address::from_tokens_short(other_tokens)?)),
-            "BRGT" => Ok(Self::BRGT(Address::from_tokens_short(other_tokens)?)),
-            "BRV" => Ok(Self::BRV(Address::from_tokens_short(other_tokens)?)),
-            "BRC" => Ok(Self::BRC(Address::from_tokens_short(other_tokens)?)),
-            "CALL" => Ok(Self::CALL(Address::from_tokens_short(other_tokens)?)),
-            "NOTA" => Ok(Self::NOTr(Register::Accumulator)),
-            "NOTX" => Ok(Self::NOTr(Register::IndexRegister)),
-            "NEGA" => Ok(Self::NEGr(Register::Accumulator)),
-            "NEGX" => Ok(Self::NEGr(Register::IndexRegister)),
-            "ASLA" => Ok(Self::ASLr(Register::Accumulator)),
-            "ASLX" => Ok(Self::ASLr(Register::IndexRegister)),
-            "ASRA" => Ok(Self::ASRr(Register::Accumulator)),
-            "ASRX" => Ok(Self::ASRr(Register::IndexRegister)),
-            "ROLA" => Ok(Self::ROLr(Register::Accumulator)),
-            "ROLX" => Ok(Self::ROLr(Register::IndexRegister)),
-            "RORA" => Ok(Self::RORr(Register::Accumulator)),
-            "RORX" => Ok(Self::RORr(Register::IndexRegister)),
-            "NOP0" => Ok(Self::NOPn(Pep8Byte::new(0))),
-            "NOP1" => Ok(Self::NOPn(Pep8Byte::new(1))),
-            "NOP2" => Ok(Self::NOPn(Pep8Byte::new(2))),
-            "NOP3" => Ok(Self::NOPn(Pep8Byte::new(3))),
-            "NOP" => Ok(Self::NOP(Address::from_tokens_long(other_tokens, &["i"])?)),
-            "DECI" => Ok(Self::DECI(Address::from_tokens_long(
-                other_tokens,
-                &["d", "n", "s", "sf", "x", "sx", "sxf"],
-            )?)),
-            "DECO" => Ok(Self::DECO(Address::from_tokens_long(
-                other_tokens,
-                &["i", "d", "n", "s", "sf", "x", "sx", "sxf"],
-            )?)),
-            "STRO" => Ok(Self::STRO(Address::from_tokens_long(
-                other_tokens,
-                &["d", "n", "sf"],
-            )?)),
-            "CHARI" => Ok(Self::CHARI(Address::from_tokens_long(
-                other_tokens,
-                &["d", "n", "s", "sf", "x", "sx", "sxf"],
-            )?)),
-            "CHARO" => Ok(Self::CHARO(Address::from_tokens_long(
-                other_tokens,
-                &["i", "d", "n", "s", "sf", "x", "sx", "sxf"],
-            )?)),
-            "RET0" => Ok(Self::RETn(Pep8Byte::new(0))),
-            "RET1" => Ok(Self::RETn(Pep8Byte::new(1))),
-            "RET2" => Ok(Self::RETn(Pep8Byte::new(2))),
-            "RET3" => Ok(Self::RETn(Pep8Byte::new(3))),
-            "RET4" => Ok(Self::RETn(Pep8Byte::new(4))),
-            "RET5" => Ok(Self::RETn(Pep8Byte::new(5))),
-            "RET6" => Ok(Self::RETn(Pep8Byte::new(6))),
-            "RET7" => Ok(Self::RETn(Pep8Byte::new(7))),
-            "ADDSP" => Ok(Self::ADDSP(Address::from_tokens_long(
-                other_tokens,
-                &["i", "d", "n", "s", "sf", "x", "sx", "sxf"],
-            )?)),
-            "SUBSP" => Ok(Self::SUBSP(Address::from_tokens_long(
-                other_tokens,
-                &["i", "d", "n", "s", "sf", "x", "sx", "sxf"],
-            )?)),
-            "ADDA" => Ok(Self::ADDr(
-                Register::Accumulator,
-                Address::from_tokens_long(
-                    other_tokens,
-                    &["i", "d", "n", "s", "sf", "x", "sx", "sxf"],
-                )?,
-            )),
-            "ADDX" => Ok(Self::ADDr(
-                Register::IndexRegister,
-                Address::from_tokens_long(
-                    other_tokens,
-                    &["i", "d", "n", "s", "sf", "x", "sx", "sxf"],
-                )?,
-            )),
-            "SUBA" => Ok(Self::SUBr(
-                Register::Accumulator,
-                Address::from_tokens_long(
-                    other_tokens,
-                    &["i", "d", "n", "s", "sf", "x", "sx", "sxf"],
-                )?,
-            )),
-            "SUBX" => Ok(Self::SUBr(
-                Register::IndexRegister,
-                Address::from_tokens_long(
-                    other_tokens,
-                    &["i", "d", "n", "s", "sf", "x", "sx", "sxf"],
-                )?,
-            )),
-            "ANDA" => Ok(Self::ANDr(
-                Register::Accumulator,
-                Address::from_tokens_long(
-                    other_tokens,
-                    &["i", "d", "n", "s", "sf", "x", "sx", "sxf"],
-                )?,
-            )),
-            "ANDX" => Ok(Self::ANDr(
-                Register::IndexRegister,
-                Address::from_tokens_long(
-                    other_tokens,
-                    &["i", "d", "n", "s", "sf", "x", "sx", "sxf"],
-                )?,
-            )),
-            "ORA" => Ok(Self::ORr(
-                Register::Accumulator,
-                Address::from_tokens_long(
-                    other_tokens,
-                    &["i", "d", "n", "s", "sf", "x", "sx", "sxf"],
-                )?,
-            )),
-            "ORX" => Ok(Self::ORr(
-                Register::IndexRegister,
-                Address::from_tokens_long(
-                    other_tokens,
-                    &["i", "d", "n", "s", "sf", "x", "sx", "sxf"],
-                )?,
-            )),
-            "CPA" => Ok(Self::CPr(
-                Register::Accumulator,
-                Address::from_tokens_long(
-                    other_tokens,
-                    &["i", "d", "n", "s", "sf", "x", "sx", "sxf"],
-                )?,
-            )),
-            "CPX" => Ok(Self::CPr(
-                Register::IndexRegister,
-                Address::from_tokens_long(
-                    other_tokens,
-                    &["i", "d", "n", "s", "sf", "x", "sx", "sxf"],
-                )?,
-            )),
-            "LDA" => Ok(Self::LDr(
-                Register::Accumulator,
-                Address::from_tokens_long(
-                    other_tokens,
-                    &["i", "d", "n", "s", "sf", "x", "sx", "sxf"],
-                )?,
-            )),
-            "LDX" => Ok(Self::LDr(
-                Register::IndexRegister,
-                Address::from_tokens_long(
-                    other_tokens,
-                    &["i", "d", "n", "s", "sf", "x", "sx", "sxf"],
-                )?,
-            )),
-            "LDBYTEA" => Ok(Self::LDBYTEr(
-                Register::Accumulator,
-                Address::from_tokens_long(
-                    other_tokens,
-                    &["i", "d", "n", "s", "sf", "x", "sx", "sxf"],
-                )?,
-            )),
-            "LDBYTEX" => Ok(Self::LDBYTEr(
-                Register::IndexRegister,
-                Address::from_tokens_long(
-                    other_tokens,
-                    &["i", "d", "n", "s", "sf", "x", "sx", "sxf"],
-                )?,
-            )),
-            "STA" => Ok(Self::STr(
-                Register::Accumulator,
-                Address::from_tokens_long(other_tokens, &["d", "n", "s", "sf", "x", "sx", "sxf"])?,
-            )),
-            "STX" => Ok(Self::STr(
-                Register::IndexRegister,
-                Address::from_tokens_long(other_tokens, &["d", "n", "s", "sf", "x", "sx", "sxf"])?,
-            )),
-            "STBYTEA" => Ok(Self::STBYTEr(
-                Register::Accumulator,
-                Address::from_tokens_long(other_tokens, &["d", "n", "s", "sf", "x", "sx", "sxf"])?,
-            )),
-            "STBYTEX" => Ok(Self::STBYTEr(
-                Register::IndexRegister,
-                Address::from_tokens_long(other_tokens, &["d", "n", "s", "sf", "x", "sx", "sxf"])?,
-            )),
-            _ => Err(Box::from("invalid instruction token")),
+            return Ok(Self {
+                trap: Some(trap.mnemonic.clone()),
+                ..Self::from_opcode(trap.slot, address)
+            });
         }
+
+        let Some(opcode) = isa::lookup(&mnemonic, target) else {
+            if isa::lookup(&format!("{mnemonic}A"), target).is_some() {
+                return Err(Box::new(Error::MissingRegisterSuffix(mnemonic)));
+            }
+
+            return Err(Box::new(Error::InvalidMnemonic {
+                mnemonic: instruction.clone(),
+                suggestion: suggest(&mnemonic, target.mnemonics()),
+            }));
+        };
+
+        let address = Self::parse_operand(other_tokens, opcode.operand, opcode.modes)?;
+
+        Ok(Self::from_opcode(opcode, address))
     }
 
-    pub fn get_specifier(&self) -> u8 {
-        match self {
-            Self::STOP => 0b00000000,        // 00000000
-            Self::RETTR => 0b00000001,       // 00000001
-            Self::MOVSPA => 0b00000010,      // 00000010
-            Self::MOVFLGA => 0b00000011,     // 00000011
-            Self::BR(..) => 0b00000100,      // 0000010a
-            Self::BRLE(..) => 0b00000110,    // 0000011a
-            Self::BRLT(..) => 0b00001000,    // 0000100a
-            Self::BREQ(..) => 0b00001010,    // 0000101a
-            Self::BRNE(..) => 0b00001100,    // 0000110a
-            Self::BRGE(..) => 0b00001110,    // 0000111a
-            Self::BRGT(..) => 0b00010000,    // 0001000a
-            Self::BRV(..) => 0b00010010,     // 0001001a
-            Self::BRC(..) => 0b00010100,     // 0001010a
-            Self::CALL(..) => 0b00010110,    // 0001011a
-            Self::NOTr(..) => 0b00011000,    // 0001100r
-            Self::NEGr(..) => 0b00011010,    // 0001101r
-            Self::ASLr(..) => 0b00011100,    // 0001110r
-            Self::ASRr(..) => 0b00011110,    // 0001111r
-            Self::ROLr(..) => 0b00100000,    // 0010000r
-            Self::RORr(..) => 0b00100010,    // 0010001r
-            Self::NOPn(..) => 0b00100100,    // 001001nn
-            Self::NOP(..) => 0b00101000,     // 00101aaa
-            Self::DECI(..) => 0b00110000,    // 00110aaa
-            Self::DECO(..) => 0b00111000,    // 00111aaa
-            Self::STRO(..) => 0b01000000,    // 01000aaa
-            Self::CHARI(..) => 0b01001000,   // 01001aaa
-            Self::CHARO(..) => 0b01010000,   // 01010aaa
-            Self::RETn(..) => 0b01011000,    // 01011nnn
-            Self::ADDSP(..) => 0b01100000,   // 01100aaa
-            Self::SUBSP(..) => 0b01101000,   // 01101aaa
-            Self::ADDr(..) => 0b01110000,    // 0111raaa
-            Self::SUBr(..) => 0b10000000,    // 1000raaa
-            Self::ANDr(..) => 0b10010000,    // 1001raaa
-            Self::ORr(..) => 0b10100000,     // 1010raaa
-            Self::CPr(..) => 0b10110000,     // 1011raaa
-            Self::LDr(..) => 0b11000000,     // 1100raaa
-            Self::LDBYTEr(..) => 0b11010000, // 1101raaa
-            Self::STr(..) => 0b11100000,     // 1110raaa
-            Self::STBYTEr(..) => 0b11110000, // 1111raaa
+    fn parse_operand(
+        tokens: &[Token],
+        operand: Operand,
+        modes: &[AddrMode],
+    ) -> Result<Option<Address>, Box<dyn std::error::Error>> {
+        Ok(match operand {
+            Operand::None => None,
+            Operand::Short => Some(Address::from_tokens_short(tokens)?),
+            Operand::Long => Some(Address::from_tokens_long(tokens, modes)?),
+        })
+    }
+
+    pub fn from_opcode(opcode: &Opcode, address: Option<Address>) -> Self {
+        Self {
+            operation: opcode.operation,
+            register: opcode.register,
+            count: opcode.count,
+            address,
+            trap: None,
+        }
+    }
+
+    /// Opcode of the target performing this instruction, if it has one.
+    pub fn opcode(&self, target: Target) -> Option<&'static Opcode> {
+        target.opcodes().iter().find(|opcode| {
+            opcode.operation == self.operation
+                && opcode.register == self.register
+                && opcode.count == self.count
+        })
+    }
+
+    /// Mnemonic of the instruction on the target. Instructions the target
+    /// lacks are spelled as on the first target that has them.
+    pub fn mnemonic(&self, target: Target) -> String {
+        if let Some(trap) = &self.trap {
+            return trap.clone();
+        }
+
+        std::iter::once(target)
+            .chain(Target::value_variants().iter().copied())
+            .find_map(|target| self.opcode(target))
+            .expect("every operation is in an instruction set")
+            .mnemonic
+            .to_string()
+    }
+
+    /// Operand with the target's spelling of its addressing mode.
+    pub fn operand(&self, target: Target) -> Option<String> {
+        self.address
+            .as_ref()
+            .map(|address| format!("{},{}", address.location, target.mode_name(&address.mode)))
+    }
+
+    /// Source of the instruction in the target's assembly language.
+    pub fn to_source(&self, target: Target) -> String {
+        match self.operand(target) {
+            Some(operand) => format!("{} {operand}", self.mnemonic(target)),
+            None => self.mnemonic(target),
         }
     }
 
+    pub fn address(&self) -> Option<&Address> {
+        self.address.as_ref()
+    }
+
     pub fn byte_size(&self) -> usize {
-        match self {
-            Self::STOP
-            | Self::RETTR
-            | Self::MOVSPA
-            | Self::MOVFLGA
-            | Self::NOTr(..)
-            | Self::NEGr(..)
-            | Self::ASLr(..)
-            | Self::ASRr(..)
-            | Self::ROLr(..)
-            | Self::RORr(..)
-            | Self::NOPn(..)
-            | Self::RETn(..) => 1,
-            Self::BR(..)
-            | Self::BRLE(..)
-            | Self::BRLT(..)
-            | Self::BREQ(..)
-            | Self::BRNE(..)
-            | Self::BRGE(..)
-            | Self::BRGT(..)
-            | Self::BRV(..)
-            | Self::BRC(..)
-            | Self::CALL(..)
-            | Self::NOP(..)
-            | Self::DECI(..)
-            | Self::DECO(..)
-            | Self::STRO(..)
-            | Self::CHARI(..)
-            | Self::CHARO(..)
-            | Self::ADDSP(..)
-            | Self::SUBSP(..)
-            | Self::ADDr(..)
-            | Self::SUBr(..)
-            | Self::ANDr(..)
-            | Self::ORr(..)
-            | Self::CPr(..)
-            | Self::LDr(..)
-            | Self::LDBYTEr(..)
-            | Self::STr(..)
-            | Self::STBYTEr(..) => 3,
+        match self.address {
+            Some(_) => 3,
+            None => 1,
         }
     }
 
     pub fn as_bytes(
         &self,
         address_table: &AddressTable,
+        target: Target,
     ) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let opcode = self
+            .opcode(target)
+            .ok_or(Error::NotInInstructionSet(self.operation, target))?;
         let mut bytes = vec![];
 
-        match self {
-            Self::STOP | Self::RETTR | Self::MOVSPA | Self::MOVFLGA => {
-                bytes.push(self.get_specifier())
-            }
-            Self::BR(address)
-            | Self::BRLE(address)
-            | Self::BRLT(address)
-            | Self::BREQ(address)
-            | Self::BRNE(address)
-            | Self::BRGE(address)
-            | Self::BRGT(address)
-            | Self::BRV(address)
-            | Self::BRC(address)
-            | Self::CALL(address) => {
-                let address_location = address_table
-                    .resolve(address)
-                    .ok_or::<Box<dyn std::error::Error>>(Box::from(format!(
-                        "cannot resolve address {:?}",
-                        address
-                    )))?;
-
-                bytes.push(self.get_specifier() + address.mode.as_byte_short()?);
-                bytes.extend_from_slice(&address_location.as_bytes());
-            }
-            Self::NOTr(register)
-            | Self::NEGr(register)
-            | Self::ASLr(register)
-            | Self::ASRr(register)
-            | Self::ROLr(register)
-            | Self::RORr(register) => bytes.push(0b00100010 + register.as_byte()), // 0010001r
-            Self::NOPn(n) => {
-                if *n > Pep8Byte::new(0b11) {
-                    panic!("count for NOP is too large: {}", n.as_byte());
-                } else {
-                    bytes.push((Pep8Byte::new(0b00100100) + n).as_byte());
-                }
-            }
-            Self::NOP(address)
-            | Self::DECI(address)
-            | Self::DECO(address)
-            | Self::STRO(address)
-            | Self::CHARI(address)
-            | Self::CHARO(address)
-            | Self::ADDSP(address)
-            | Self::SUBSP(address) => {
+        match (opcode.operand, &self.address) {
+            (Operand::None, _) => bytes.push(opcode.specifier),
+            (operand, Some(address)) => {
                 let address_location = address_table
                     .resolve(address)
                     .ok_or::<Box<dyn std::error::Error>>(Box::from(format!(
                         "cannot resolve address {:?}",
                         address
                     )))?;
+                let mode = match operand {
+                    Operand::Short => address.mode.as_byte_short()?,
+                    _ => address.mode.as_byte_long(),
+                };
 
-                bytes.push(self.get_specifier() + address.mode.as_byte_long());
-                bytes.extend_from_slice(&address_location.as_bytes());
-            }
-            Self::RETn(n) => {
-                if *n > Pep8Byte::new(0b111) {
-                    panic!("count for RET is too large: {}", n.as_byte());
-                } else {
-                    bytes.push((Pep8Byte::new(0b01011000) + n).as_byte());
-                }
-            }
-            Self::ADDr(register, address)
-            | Self::SUBr(register, address)
-            | Self::ANDr(register, address)
-            | Self::ORr(register, address)
-            | Self::CPr(register, address)
-            | Self::LDr(register, address)
-            | Self::LDBYTEr(register, address)
-            | Self::STr(register, address)
-            | Self::STBYTEr(register, address) => {
-                let address_location = address_table
-                    .resolve(address)
-                    .ok_or::<Box<dyn std::error::Error>>(Box::from(format!(
-                        "cannot resolve address {:?}",
-                        address
-                    )))?;
-
-                bytes.push(
-                    self.get_specifier() + (register.as_byte() << 3) + address.mode.as_byte_long(),
-                );
+                bytes.push(opcode.specifier | mode);
                 bytes.extend_from_slice(&address_location.as_bytes());
             }
+            (_, None) => return Err(Box::new(crate::address::Error::MalformedAddrMode)),
         }
 
         Ok(bytes)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        assembler::{assemble, Options},
+        lexer::parse_source,
+    };
+
+    fn error(source: &str) -> String {
+        assemble(parse_source(source).unwrap(), &Options::default())
+            .unwrap_err()
+            .to_string()
+    }
+
+    #[test]
+    fn suggests_a_register_suffix_or_a_close_mnemonic() {
+        assert_eq!(
+            error("         LD      5,i\n         .END\n"),
+            "line 1: LD requires a register suffix (did you mean LDA or LDX?)"
+        );
+        assert_eq!(
+            error("         LDAX    5,i\n         .END\n"),
+            "line 1: invalid instruction mnemonic: LDAX (did you mean LDX?)"
+        );
+        assert_eq!(
+            error("         FROBNICATE\n         .END\n"),
+            "line 1: invalid instruction mnemonic: FROBNICATE"
+        );
+    }
+}
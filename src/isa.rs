@@ -0,0 +1,524 @@
+use std::io::Write;
+
+use crate::{
+    address::AddrMode::{self, *},
+    register::Register::{self, *},
+    target::Target,
+};
+
+/// What an instruction does, independently of the mnemonic and encoding each
+/// target gives it.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operation {
+    STOP,
+    RETTR,
+    SRET,
+    MOVSPA,
+    MOVASP,
+    MOVFLGA,
+    MOVAFLG,
+    MOVTPC,
+    BR,
+    BRLE,
+    BRLT,
+    BREQ,
+    BRNE,
+    BRGE,
+    BRGT,
+    BRV,
+    BRC,
+    CALL,
+    SCALL,
+    LDWT,
+    NOTr,
+    NEGr,
+    ASLr,
+    ASRr,
+    ROLr,
+    RORr,
+    NOPn,
+    NOP,
+    DECI,
+    DECO,
+    HEXO,
+    STRO,
+    CHARI,
+    CHARO,
+    RETn,
+    ADDSP,
+    SUBSP,
+    ADDr,
+    SUBr,
+    ANDr,
+    ORr,
+    XORr,
+    CPr,
+    CPBr,
+    LDr,
+    LDBYTEr,
+    STr,
+    STBYTEr,
+}
+
+/// Operand of an instruction, which sets its size and the addressing mode
+/// field of its specifier.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operand {
+    None,
+    /// Operand with a one-bit `a` mode field.
+    Short,
+    /// Operand with a three-bit `aaa` mode field.
+    Long,
+}
+
+/// One mnemonic of a target's instruction set.
+#[derive(Debug)]
+pub struct Opcode {
+    pub mnemonic: &'static str,
+    /// Instruction specifier with the addressing mode field clear.
+    pub specifier: u8,
+    pub operation: Operation,
+    /// Register selected by the specifier's `r` field.
+    pub register: Option<Register>,
+    /// Value of the `n` field of `NOPn` and `RETn`.
+    pub count: Option<u8>,
+    pub operand: Operand,
+    pub modes: &'static [AddrMode],
+    /// Whether the instruction traps to the operating system.
+    pub trap: bool,
+}
+
+impl Opcode {
+    const fn unary(mnemonic: &'static str, specifier: u8, operation: Operation) -> Self {
+        Self {
+            mnemonic,
+            specifier,
+            operation,
+            register: None,
+            count: None,
+            operand: Operand::None,
+            modes: &[],
+            trap: false,
+        }
+    }
+
+    const fn branch(mnemonic: &'static str, specifier: u8, operation: Operation) -> Self {
+        Self {
+            operand: Operand::Short,
+            modes: BRANCH,
+            ..Self::unary(mnemonic, specifier, operation)
+        }
+    }
+
+    const fn nonunary(
+        mnemonic: &'static str,
+        specifier: u8,
+        operation: Operation,
+        modes: &'static [AddrMode],
+    ) -> Self {
+        Self {
+            operand: Operand::Long,
+            modes,
+            ..Self::unary(mnemonic, specifier, operation)
+        }
+    }
+
+    const fn register(self, register: Register) -> Self {
+        Self {
+            register: Some(register),
+            ..self
+        }
+    }
+
+    const fn count(self, count: u8) -> Self {
+        Self {
+            count: Some(count),
+            ..self
+        }
+    }
+
+    const fn trap(self) -> Self {
+        Self { trap: true, ..self }
+    }
+
+    pub fn byte_size(&self) -> usize {
+        match self.operand {
+            Operand::None => 1,
+            Operand::Short | Operand::Long => 3,
+        }
+    }
+
+    /// Bits of the specifier holding the addressing mode.
+    fn mode_mask(&self) -> u8 {
+        match self.operand {
+            Operand::None => 0,
+            Operand::Short => 0b1,
+            Operand::Long => 0b111,
+        }
+    }
+}
+
+const BRANCH: &[AddrMode] = &[Immediate, Indexed];
+const IMMEDIATE: &[AddrMode] = &[Immediate];
+const ALL: &[AddrMode] = &[
+    Immediate,
+    Direct,
+    Indirect,
+    StackRelative,
+    StackRelativeDeferred,
+    Indexed,
+    StackIndexed,
+    StackIndexedDeferred,
+];
+const NON_IMMEDIATE: &[AddrMode] = &[
+    Direct,
+    Indirect,
+    StackRelative,
+    StackRelativeDeferred,
+    Indexed,
+    StackIndexed,
+    StackIndexedDeferred,
+];
+const PEP8_STRO: &[AddrMode] = &[Direct, Indirect, StackRelativeDeferred];
+const PEP9_STRO: &[AddrMode] = &[
+    Direct,
+    Indirect,
+    StackRelative,
+    StackRelativeDeferred,
+    Indexed,
+];
+
+use Operation::*;
+
+pub const PEP8: &[Opcode] = &[
+    Opcode::unary("STOP", 0x00, STOP),
+    Opcode::unary("RETTR", 0x01, RETTR),
+    Opcode::unary("MOVSPA", 0x02, MOVSPA),
+    Opcode::unary("MOVFLGA", 0x03, MOVFLGA),
+    Opcode::branch("BR", 0x04, BR),
+    Opcode::branch("BRLE", 0x06, BRLE),
+    Opcode::branch("BRLT", 0x08, BRLT),
+    Opcode::branch("BREQ", 0x0A, BREQ),
+    Opcode::branch("BRNE", 0x0C, BRNE),
+    Opcode::branch("BRGE", 0x0E, BRGE),
+    Opcode::branch("BRGT", 0x10, BRGT),
+    Opcode::branch("BRV", 0x12, BRV),
+    Opcode::branch("BRC", 0x14, BRC),
+    Opcode::branch("CALL", 0x16, CALL),
+    Opcode::unary("NOTA", 0x18, NOTr).register(Accumulator),
+    Opcode::unary("NOTX", 0x19, NOTr).register(IndexRegister),
+    Opcode::unary("NEGA", 0x1A, NEGr).register(Accumulator),
+    Opcode::unary("NEGX", 0x1B, NEGr).register(IndexRegister),
+    Opcode::unary("ASLA", 0x1C, ASLr).register(Accumulator),
+    Opcode::unary("ASLX", 0x1D, ASLr).register(IndexRegister),
+    Opcode::unary("ASRA", 0x1E, ASRr).register(Accumulator),
+    Opcode::unary("ASRX", 0x1F, ASRr).register(IndexRegister),
+    Opcode::unary("ROLA", 0x20, ROLr).register(Accumulator),
+    Opcode::unary("ROLX", 0x21, ROLr).register(IndexRegister),
+    Opcode::unary("RORA", 0x22, RORr).register(Accumulator),
+    Opcode::unary("RORX", 0x23, RORr).register(IndexRegister),
+    Opcode::unary("NOP0", 0x24, NOPn).count(0).trap(),
+    Opcode::unary("NOP1", 0x25, NOPn).count(1).trap(),
+    Opcode::unary("NOP2", 0x26, NOPn).count(2).trap(),
+    Opcode::unary("NOP3", 0x27, NOPn).count(3).trap(),
+    Opcode::nonunary("NOP", 0x28, NOP, IMMEDIATE).trap(),
+    Opcode::nonunary("DECI", 0x30, DECI, NON_IMMEDIATE).trap(),
+    Opcode::nonunary("DECO", 0x38, DECO, ALL).trap(),
+    Opcode::nonunary("STRO", 0x40, STRO, PEP8_STRO).trap(),
+    Opcode::nonunary("CHARI", 0x48, CHARI, NON_IMMEDIATE),
+    Opcode::nonunary("CHARO", 0x50, CHARO, ALL),
+    Opcode::unary("RET0", 0x58, RETn).count(0),
+    Opcode::unary("RET1", 0x59, RETn).count(1),
+    Opcode::unary("RET2", 0x5A, RETn).count(2),
+    Opcode::unary("RET3", 0x5B, RETn).count(3),
+    Opcode::unary("RET4", 0x5C, RETn).count(4),
+    Opcode::unary("RET5", 0x5D, RETn).count(5),
+    Opcode::unary("RET6", 0x5E, RETn).count(6),
+    Opcode::unary("RET7", 0x5F, RETn).count(7),
+    Opcode::nonunary("ADDSP", 0x60, ADDSP, ALL),
+    Opcode::nonunary("SUBSP", 0x68, SUBSP, ALL),
+    Opcode::nonunary("ADDA", 0x70, ADDr, ALL).register(Accumulator),
+    Opcode::nonunary("ADDX", 0x78, ADDr, ALL).register(IndexRegister),
+    Opcode::nonunary("SUBA", 0x80, SUBr, ALL).register(Accumulator),
+    Opcode::nonunary("SUBX", 0x88, SUBr, ALL).register(IndexRegister),
+    Opcode::nonunary("ANDA", 0x90, ANDr, ALL).register(Accumulator),
+    Opcode::nonunary("ANDX", 0x98, ANDr, ALL).register(IndexRegister),
+    Opcode::nonunary("ORA", 0xA0, ORr, ALL).register(Accumulator),
+    Opcode::nonunary("ORX", 0xA8, ORr, ALL).register(IndexRegister),
+    Opcode::nonunary("CPA", 0xB0, CPr, ALL).register(Accumulator),
+    Opcode::nonunary("CPX", 0xB8, CPr, ALL).register(IndexRegister),
+    Opcode::nonunary("LDA", 0xC0, LDr, ALL).register(Accumulator),
+    Opcode::nonunary("LDX", 0xC8, LDr, ALL).register(IndexRegister),
+    Opcode::nonunary("LDBYTEA", 0xD0, LDBYTEr, ALL).register(Accumulator),
+    Opcode::nonunary("LDBYTEX", 0xD8, LDBYTEr, ALL).register(IndexRegister),
+    Opcode::nonunary("STA", 0xE0, STr, NON_IMMEDIATE).register(Accumulator),
+    Opcode::nonunary("STX", 0xE8, STr, NON_IMMEDIATE).register(IndexRegister),
+    Opcode::nonunary("STBYTEA", 0xF0, STBYTEr, NON_IMMEDIATE).register(Accumulator),
+    Opcode::nonunary("STBYTEX", 0xF8, STBYTEr, NON_IMMEDIATE).register(IndexRegister),
+];
+
+pub const PEP9: &[Opcode] = &[
+    Opcode::unary("STOP", 0x00, STOP),
+    Opcode::unary("RET", 0x01, RETn).count(0),
+    Opcode::unary("RETTR", 0x02, RETTR),
+    Opcode::unary("MOVSPA", 0x03, MOVSPA),
+    Opcode::unary("MOVFLGA", 0x04, MOVFLGA),
+    Opcode::unary("MOVAFLG", 0x05, MOVAFLG),
+    Opcode::unary("NOTA", 0x06, NOTr).register(Accumulator),
+    Opcode::unary("NOTX", 0x07, NOTr).register(IndexRegister),
+    Opcode::unary("NEGA", 0x08, NEGr).register(Accumulator),
+    Opcode::unary("NEGX", 0x09, NEGr).register(IndexRegister),
+    Opcode::unary("ASLA", 0x0A, ASLr).register(Accumulator),
+    Opcode::unary("ASLX", 0x0B, ASLr).register(IndexRegister),
+    Opcode::unary("ASRA", 0x0C, ASRr).register(Accumulator),
+    Opcode::unary("ASRX", 0x0D, ASRr).register(IndexRegister),
+    Opcode::unary("ROLA", 0x0E, ROLr).register(Accumulator),
+    Opcode::unary("ROLX", 0x0F, ROLr).register(IndexRegister),
+    Opcode::unary("RORA", 0x10, RORr).register(Accumulator),
+    Opcode::unary("RORX", 0x11, RORr).register(IndexRegister),
+    Opcode::branch("BR", 0x12, BR),
+    Opcode::branch("BRLE", 0x14, BRLE),
+    Opcode::branch("BRLT", 0x16, BRLT),
+    Opcode::branch("BREQ", 0x18, BREQ),
+    Opcode::branch("BRNE", 0x1A, BRNE),
+    Opcode::branch("BRGE", 0x1C, BRGE),
+    Opcode::branch("BRGT", 0x1E, BRGT),
+    Opcode::branch("BRV", 0x20, BRV),
+    Opcode::branch("BRC", 0x22, BRC),
+    Opcode::branch("CALL", 0x24, CALL),
+    Opcode::unary("NOP0", 0x26, NOPn).count(0).trap(),
+    Opcode::unary("NOP1", 0x27, NOPn).count(1).trap(),
+    Opcode::nonunary("NOP", 0x28, NOP, IMMEDIATE).trap(),
+    Opcode::nonunary("DECI", 0x30, DECI, NON_IMMEDIATE).trap(),
+    Opcode::nonunary("DECO", 0x38, DECO, ALL).trap(),
+    Opcode::nonunary("HEXO", 0x40, HEXO, ALL).trap(),
+    Opcode::nonunary("STRO", 0x48, STRO, PEP9_STRO).trap(),
+    Opcode::nonunary("ADDSP", 0x50, ADDSP, ALL),
+    Opcode::nonunary("SUBSP", 0x58, SUBSP, ALL),
+    Opcode::nonunary("ADDA", 0x60, ADDr, ALL).register(Accumulator),
+    Opcode::nonunary("ADDX", 0x68, ADDr, ALL).register(IndexRegister),
+    Opcode::nonunary("SUBA", 0x70, SUBr, ALL).register(Accumulator),
+    Opcode::nonunary("SUBX", 0x78, SUBr, ALL).register(IndexRegister),
+    Opcode::nonunary("ANDA", 0x80, ANDr, ALL).register(Accumulator),
+    Opcode::nonunary("ANDX", 0x88, ANDr, ALL).register(IndexRegister),
+    Opcode::nonunary("ORA", 0x90, ORr, ALL).register(Accumulator),
+    Opcode::nonunary("ORX", 0x98, ORr, ALL).register(IndexRegister),
+    Opcode::nonunary("CPWA", 0xA0, CPr, ALL).register(Accumulator),
+    Opcode::nonunary("CPWX", 0xA8, CPr, ALL).register(IndexRegister),
+    Opcode::nonunary("CPBA", 0xB0, CPBr, ALL).register(Accumulator),
+    Opcode::nonunary("CPBX", 0xB8, CPBr, ALL).register(IndexRegister),
+    Opcode::nonunary("LDWA", 0xC0, LDr, ALL).register(Accumulator),
+    Opcode::nonunary("LDWX", 0xC8, LDr, ALL).register(IndexRegister),
+    Opcode::nonunary("LDBA", 0xD0, LDBYTEr, ALL).register(Accumulator),
+    Opcode::nonunary("LDBX", 0xD8, LDBYTEr, ALL).register(IndexRegister),
+    Opcode::nonunary("STWA", 0xE0, STr, NON_IMMEDIATE).register(Accumulator),
+    Opcode::nonunary("STWX", 0xE8, STr, NON_IMMEDIATE).register(IndexRegister),
+    Opcode::nonunary("STBA", 0xF0, STBYTEr, NON_IMMEDIATE).register(Accumulator),
+    Opcode::nonunary("STBX", 0xF8, STBYTEr, NON_IMMEDIATE).register(IndexRegister),
+];
+
+pub const PEP10: &[Opcode] = &[
+    Opcode::unary("RET", 0x00, RETn).count(0),
+    Opcode::unary("SRET", 0x01, SRET),
+    Opcode::unary("MOVSPA", 0x02, MOVSPA),
+    Opcode::unary("MOVASP", 0x03, MOVASP),
+    Opcode::unary("MOVFLGA", 0x04, MOVFLGA),
+    Opcode::unary("MOVAFLG", 0x05, MOVAFLG),
+    Opcode::unary("MOVTPC", 0x06, MOVTPC),
+    Opcode::unary("NOP", 0x07, NOPn).count(0),
+    Opcode::unary("NOTA", 0x10, NOTr).register(Accumulator),
+    Opcode::unary("NOTX", 0x11, NOTr).register(IndexRegister),
+    Opcode::unary("NEGA", 0x12, NEGr).register(Accumulator),
+    Opcode::unary("NEGX", 0x13, NEGr).register(IndexRegister),
+    Opcode::unary("ASLA", 0x14, ASLr).register(Accumulator),
+    Opcode::unary("ASLX", 0x15, ASLr).register(IndexRegister),
+    Opcode::unary("ASRA", 0x16, ASRr).register(Accumulator),
+    Opcode::unary("ASRX", 0x17, ASRr).register(IndexRegister),
+    Opcode::unary("ROLA", 0x18, ROLr).register(Accumulator),
+    Opcode::unary("ROLX", 0x19, ROLr).register(IndexRegister),
+    Opcode::unary("RORA", 0x1A, RORr).register(Accumulator),
+    Opcode::unary("RORX", 0x1B, RORr).register(IndexRegister),
+    Opcode::branch("BR", 0x1C, BR),
+    Opcode::branch("BRLE", 0x1E, BRLE),
+    Opcode::branch("BRLT", 0x20, BRLT),
+    Opcode::branch("BREQ", 0x22, BREQ),
+    Opcode::branch("BRNE", 0x24, BRNE),
+    Opcode::branch("BRGE", 0x26, BRGE),
+    Opcode::branch("BRGT", 0x28, BRGT),
+    Opcode::branch("BRV", 0x2A, BRV),
+    Opcode::branch("BRC", 0x2C, BRC),
+    Opcode::branch("CALL", 0x2E, CALL),
+    Opcode::nonunary("SCALL", 0x30, SCALL, ALL).trap(),
+    Opcode::nonunary("LDWT", 0x38, LDWT, ALL),
+    Opcode::nonunary("ADDSP", 0x40, ADDSP, ALL),
+    Opcode::nonunary("SUBSP", 0x48, SUBSP, ALL),
+    Opcode::nonunary("ADDA", 0x50, ADDr, ALL).register(Accumulator),
+    Opcode::nonunary("ADDX", 0x58, ADDr, ALL).register(IndexRegister),
+    Opcode::nonunary("SUBA", 0x60, SUBr, ALL).register(Accumulator),
+    Opcode::nonunary("SUBX", 0x68, SUBr, ALL).register(IndexRegister),
+    Opcode::nonunary("ANDA", 0x70, ANDr, ALL).register(Accumulator),
+    Opcode::nonunary("ANDX", 0x78, ANDr, ALL).register(IndexRegister),
+    Opcode::nonunary("ORA", 0x80, ORr, ALL).register(Accumulator),
+    Opcode::nonunary("ORX", 0x88, ORr, ALL).register(IndexRegister),
+    Opcode::nonunary("XORA", 0x90, XORr, ALL).register(Accumulator),
+    Opcode::nonunary("XORX", 0x98, XORr, ALL).register(IndexRegister),
+    Opcode::nonunary("CPWA", 0xA0, CPr, ALL).register(Accumulator),
+    Opcode::nonunary("CPWX", 0xA8, CPr, ALL).register(IndexRegister),
+    Opcode::nonunary("CPBA", 0xB0, CPBr, ALL).register(Accumulator),
+    Opcode::nonunary("CPBX", 0xB8, CPBr, ALL).register(IndexRegister),
+    Opcode::nonunary("LDWA", 0xC0, LDr, ALL).register(Accumulator),
+    Opcode::nonunary("LDWX", 0xC8, LDr, ALL).register(IndexRegister),
+    Opcode::nonunary("LDBA", 0xD0, LDBYTEr, ALL).register(Accumulator),
+    Opcode::nonunary("LDBX", 0xD8, LDBYTEr, ALL).register(IndexRegister),
+    Opcode::nonunary("STWA", 0xE0, STr, NON_IMMEDIATE).register(Accumulator),
+    Opcode::nonunary("STWX", 0xE8, STr, NON_IMMEDIATE).register(IndexRegister),
+    Opcode::nonunary("STBA", 0xF0, STBYTEr, NON_IMMEDIATE).register(Accumulator),
+    Opcode::nonunary("STBX", 0xF8, STBYTEr, NON_IMMEDIATE).register(IndexRegister),
+];
+
+/// Opcode of the target spelled `mnemonic`, in any case.
+pub fn lookup(mnemonic: &str, target: Target) -> Option<&'static Opcode> {
+    target
+        .opcodes()
+        .iter()
+        .find(|opcode| opcode.mnemonic.eq_ignore_ascii_case(mnemonic))
+}
+
+/// Splits an instruction specifier into its opcode and the addressing mode
+/// it selects, or `None` if no instruction of the target has it.
+pub fn decode(specifier: u8, target: Target) -> Option<(&'static Opcode, Option<AddrMode>)> {
+    let opcode = target
+        .opcodes()
+        .iter()
+        .find(|opcode| specifier & !opcode.mode_mask() == opcode.specifier)?;
+    let field = specifier & opcode.mode_mask();
+    let mode = match opcode.operand {
+        Operand::None => None,
+        Operand::Short => Some(BRANCH[field as usize].clone()),
+        Operand::Long => Some(ALL[field as usize].clone()),
+    };
+
+    Some((opcode, mode))
+}
+
+/// Writes the instruction set of the target as a Markdown table, with the
+/// `a` bits of each specifier selecting the addressing mode.
+pub fn write_table<W: Write>(target: Target, writer: &mut W) -> std::io::Result<()> {
+    writeln!(
+        writer,
+        "| Mnemonic | Specifier | Size | Addressing modes | Trap |"
+    )?;
+    writeln!(writer, "| --- | --- | --- | --- | --- |")?;
+
+    for opcode in target.opcodes() {
+        let bits = format!("{:08b}", opcode.specifier);
+        let mode_bits = opcode.mode_mask().count_ones() as usize;
+        let modes: Vec<_> = opcode
+            .modes
+            .iter()
+            .map(|mode| target.mode_name(mode))
+            .collect();
+
+        writeln!(
+            writer,
+            "| {} | {}{} | {} | {} | {} |",
+            opcode.mnemonic,
+            &bits[..8 - mode_bits],
+            "a".repeat(mode_bits),
+            opcode.byte_size(),
+            modes.join(", "),
+            if opcode.trap { "yes" } else { "" }
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TARGETS: [Target; 3] = [Target::Pep8, Target::Pep9, Target::Pep10];
+
+    fn decoded(specifier: u8, target: Target) -> Option<(&'static str, Option<String>)> {
+        decode(specifier, target)
+            .map(|(opcode, mode)| (opcode.mnemonic, mode.map(|mode| target.mode_name(&mode))))
+    }
+
+    #[test]
+    fn decodes_the_specifier_of_every_opcode_and_mode() {
+        for target in TARGETS {
+            for opcode in target.opcodes() {
+                let modes = match opcode.operand {
+                    Operand::None => &[][..],
+                    Operand::Short => BRANCH,
+                    Operand::Long => ALL,
+                };
+
+                assert_eq!(
+                    decode(opcode.specifier, target).map(|(opcode, _)| opcode.mnemonic),
+                    Some(opcode.mnemonic)
+                );
+
+                for (field, mode) in modes.iter().enumerate() {
+                    let (decoded, decoded_mode) =
+                        decode(opcode.specifier | field as u8, target).unwrap();
+
+                    assert_eq!(decoded.mnemonic, opcode.mnemonic);
+                    assert_eq!(decoded_mode.as_ref(), Some(mode));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn decodes_specifiers_in_the_encoding_of_the_target() {
+        assert_eq!(decoded(0xC1, Target::Pep8), Some(("LDA", Some("d".into()))));
+        assert_eq!(
+            decoded(0xC1, Target::Pep9),
+            Some(("LDWA", Some("d".into())))
+        );
+        assert_eq!(
+            decoded(0xC1, Target::Pep10),
+            Some(("LDWA", Some("d".into())))
+        );
+        assert_eq!(decoded(0x05, Target::Pep8), Some(("BR", Some("x".into()))));
+        assert_eq!(decoded(0x12, Target::Pep9), Some(("BR", Some("i".into()))));
+        assert_eq!(decoded(0x01, Target::Pep9), Some(("RET", None)));
+        assert_eq!(decoded(0x1D, Target::Pep10), Some(("BR", Some("x".into()))));
+        assert_eq!(decoded(0x5F, Target::Pep8), Some(("RET7", None)));
+        assert_eq!(
+            decoded(0xB7, Target::Pep9),
+            Some(("CPBA", Some("sfx".into())))
+        );
+        assert_eq!(
+            decoded(0x31, Target::Pep10),
+            Some(("SCALL", Some("d".into())))
+        );
+        assert_eq!(decoded(0x08, Target::Pep10), None);
+    }
+
+    #[test]
+    fn writes_the_instruction_set_as_a_table() {
+        let mut table = vec![];
+
+        write_table(Target::Pep9, &mut table).unwrap();
+
+        let table = String::from_utf8(table).unwrap();
+        let rows: Vec<&str> = table.lines().collect();
+
+        assert_eq!(rows.len(), 2 + Target::Pep9.opcodes().len());
+        assert_eq!(
+            rows[..3],
+            [
+                "| Mnemonic | Specifier | Size | Addressing modes | Trap |",
+                "| --- | --- | --- | --- | --- |",
+                "| STOP | 00000000 | 1 |  |  |",
+            ]
+        );
+        assert!(rows.contains(&"| BR | 0001001a | 3 | i, x |  |"));
+        assert!(rows.contains(&"| DECI | 00110aaa | 3 | d, n, s, sf, x, sx, sfx | yes |"));
+        assert!(rows.contains(&"| LDWA | 11000aaa | 3 | i, d, n, s, sf, x, sx, sfx |  |"));
+    }
+}
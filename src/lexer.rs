@@ -1,7 +1,10 @@
 use std::fmt::Display;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::iter::Peekable;
+use std::ops::Range;
 use std::path::Path;
+use std::str::Chars;
+
+use crate::formatter::layout;
 
 #[derive(Debug)]
 pub enum Error {
@@ -12,6 +15,8 @@ pub enum Error {
     InvalidNumber,
     InvalidString,
     InvalidDotCommand,
+    NonAsciiChar { value: char, column: usize },
+    AtLine(usize, Box<Error>),
 }
 
 impl Display for Error {
@@ -24,6 +29,10 @@ impl Display for Error {
             Self::InvalidNumber => write!(f, "invalid number"),
             Self::InvalidString => write!(f, "invalid string"),
             Self::InvalidDotCommand => write!(f, "invalid dot command"),
+            Self::NonAsciiChar { value, column } => {
+                write!(f, "non-ASCII character {value:?} at column {column}")
+            }
+            Self::AtLine(line, error) => write!(f, "line {line}: {error}"),
         }
     }
 }
@@ -32,24 +41,215 @@ impl std::error::Error for Error {}
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum Token {
-    Char(char),
-    String(String),
+    Char(u8),
+    String(Vec<u8>),
     Number(u16),
     Comma,
     DotCommand(String),
     Label(String),
     Identifier(String),
+    /// Macro invocation such as `@DECI`, without the `@`.
+    Macro(String),
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Char(value) => write!(f, "'{}'", escape(*value, b'\'')),
+            Self::String(value) => {
+                let escaped: String = value.iter().map(|byte| escape(*byte, b'"')).collect();
+
+                write!(f, "\"{escaped}\"")
+            }
+            Self::Number(value) => write!(f, "{value}"),
+            Self::Comma => write!(f, ","),
+            Self::DotCommand(name) | Self::Identifier(name) => write!(f, "{name}"),
+            Self::Label(name) => write!(f, "{name}:"),
+            Self::Macro(name) => write!(f, "@{name}"),
+        }
+    }
+}
+
+/// Spells a byte inside a character or string literal delimited by `quote`,
+/// escaping the quote, backslashes and unprintable characters.
+pub fn escape(byte: u8, quote: u8) -> String {
+    match byte {
+        0x08 => String::from("\\b"),
+        0x0C => String::from("\\f"),
+        b'\n' => String::from("\\n"),
+        b'\r' => String::from("\\r"),
+        b'\t' => String::from("\\t"),
+        0x0B => String::from("\\v"),
+        b'\\' => String::from("\\\\"),
+        _ if byte == quote => format!("\\{}", quote as char),
+        b' '..=b'~' => (byte as char).to_string(),
+        _ => format!("\\x{byte:02X}"),
+    }
+}
+
+/// Joins operand tokens, attaching commas to the operand before them and
+/// separating the others with a space.
+pub fn join_operands(tokens: &[Token]) -> String {
+    let mut operands = String::new();
+
+    for (index, token) in tokens.iter().enumerate() {
+        if index > 0 && *token != Token::Comma && tokens[index - 1] != Token::Comma {
+            operands.push(' ');
+        }
+
+        operands.push_str(&token.to_string());
+    }
+
+    operands
+}
+
+/// How the Pep/8 IDE displays a traced value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TraceFormat {
+    Char,
+    Decimal,
+    Hex,
 }
 
-fn parse_escaped_char<I: Iterator<Item = char>>(chars: &mut I) -> Result<char, Error> {
+/// Symbol trace tag written in a comment, telling the Pep/8 IDE how to draw
+/// the memory reserved by the line.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TraceTag {
+    /// `#2d`, or `#2d4a` for an array of 4 values.
+    Format {
+        size: u8,
+        format: TraceFormat,
+        count: usize,
+    },
+    /// `#name`, referring to a symbol that has a format tag.
+    Symbol(String),
+}
+
+impl TraceTag {
+    /// Parses the text following a `#` the way the Pep/8 IDE does: a format
+    /// tag is one of `1c`, `1d`, `1h`, `2d` and `2h`, optionally followed by
+    /// an array count such as `4a`, and must end the word. A symbol tag is a
+    /// letter followed by letters, digits or underscores. Anything else is
+    /// not a tag.
+    fn parse(text: &str) -> Option<Self> {
+        let end = text.find(char::is_whitespace).unwrap_or(text.len());
+        let format = match text.get(..2) {
+            Some("1c") => Some((1, TraceFormat::Char)),
+            Some("1d") => Some((1, TraceFormat::Decimal)),
+            Some("1h") => Some((1, TraceFormat::Hex)),
+            Some("2d") => Some((2, TraceFormat::Decimal)),
+            Some("2h") => Some((2, TraceFormat::Hex)),
+            _ => None,
+        };
+
+        if let Some((size, format)) = format {
+            let count = match &text[2..end] {
+                "" => Some(1),
+                array => array
+                    .strip_suffix('a')
+                    .filter(|count| count.bytes().all(|byte| byte.is_ascii_digit()))
+                    .and_then(|count| count.parse().ok())
+                    .filter(|count| *count > 0),
+            };
+
+            return count.map(|count| Self::Format {
+                size,
+                format,
+                count,
+            });
+        }
+
+        let end = text
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+            .unwrap_or(text.len());
+
+        text.starts_with(|c: char| c.is_ascii_alphabetic())
+            .then(|| Self::Symbol(String::from(&text[..end])))
+    }
+
+    /// Bytes described by a format tag.
+    pub fn byte_size(&self) -> Option<usize> {
+        match self {
+            Self::Format { size, count, .. } => Some(*size as usize * count),
+            _ => None,
+        }
+    }
+}
+
+/// Trace tags in a comment, skipping any `#` that does not start one, as in
+/// `; print #1`.
+fn parse_trace_tags(comment: &str) -> Vec<TraceTag> {
+    comment
+        .split('#')
+        .skip(1)
+        .filter_map(TraceTag::parse)
+        .collect()
+}
+
+/// Tokens of a source line, along with its 1-based line number and the trivia
+/// needed to reproduce it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SourceLine {
+    pub number: usize,
+    pub tokens: Vec<Token>,
+    /// Byte range of each token in `text`.
+    pub spans: Vec<Range<usize>>,
+    /// Text following the `;` of a comment, kept verbatim.
+    pub comment: Option<String>,
+    /// Trace tags found in the comment.
+    pub trace_tags: Vec<TraceTag>,
+    pub text: String,
+}
+
+/// Renders the tokens of the line in canonical form on the Pep/8 IDE
+/// columns, along with its comment.
+impl Display for SourceLine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (label, tokens) = match &self.tokens[..] {
+            [Token::Label(label), tokens @ ..] => (Some(label.as_str()), tokens),
+            tokens => (None, tokens),
+        };
+        let (mnemonic, operands) = match tokens {
+            [mnemonic, operands @ ..] => (Some(mnemonic.to_string()), join_operands(operands)),
+            [] => (None, String::new()),
+        };
+
+        write!(
+            f,
+            "{}",
+            layout(
+                label,
+                mnemonic.as_deref(),
+                &operands,
+                self.comment.as_deref()
+            )
+        )
+    }
+}
+
+/// Converts the source character just taken from `chars` to its byte value,
+/// rejecting characters outside of ASCII. The column reported counts
+/// characters, not bytes, from 1.
+fn ascii_byte(line: &str, chars: &Peekable<Chars>, c: char) -> Result<u8, Error> {
+    if c.is_ascii() {
+        Ok(c as u8)
+    } else {
+        Err(Error::NonAsciiChar {
+            value: c,
+            column: line[..position(line, chars)].chars().count(),
+        })
+    }
+}
+
+fn parse_escaped_char<I: Iterator<Item = char>>(chars: &mut I) -> Result<u8, Error> {
     if let Some(c) = chars.next() {
         match c {
-            'b' => Ok('\x08'),
-            'f' => Ok('\x0C'),
-            'n' => Ok('\n'),
-            'r' => Ok('\r'),
-            't' => Ok('\t'),
-            'v' => Ok('\x0B'),
+            'b' => Ok(0x08),
+            'f' => Ok(0x0C),
+            'n' => Ok(b'\n'),
+            'r' => Ok(b'\r'),
+            't' => Ok(b'\t'),
+            'v' => Ok(0x0B),
             'x' | 'X' => {
                 let mut hex_str = String::new();
 
@@ -60,17 +260,11 @@ fn parse_escaped_char<I: Iterator<Item = char>>(chars: &mut I) -> Result<char, E
                     }
                 }
 
-                match u32::from_str_radix(&hex_str.as_str(), 16) {
-                    Ok(value) => match std::char::from_u32(value) {
-                        None => Err(Error::InvalidHexValue),
-                        Some(c) => Ok(c),
-                    },
-                    Err(_) => Err(Error::InvalidHexValue),
-                }
+                u8::from_str_radix(hex_str.as_str(), 16).or(Err(Error::InvalidHexValue))
             }
-            '\"' => Ok('\"'),
-            '\'' => Ok('\''),
-            '\\' => Ok('\\'),
+            '\"' => Ok(b'"'),
+            '\'' => Ok(b'\''),
+            '\\' => Ok(b'\\'),
             _ => Err(Error::InvalidEscapedChar),
         }
     } else {
@@ -78,13 +272,29 @@ fn parse_escaped_char<I: Iterator<Item = char>>(chars: &mut I) -> Result<char, E
     }
 }
 
-pub fn parse_line(line: &str) -> Result<Vec<Token>, Error> {
+/// Byte offset in the line of the next character of `chars`.
+fn position(line: &str, chars: &Peekable<Chars>) -> usize {
+    line.len() - chars.clone().map(char::len_utf8).sum::<usize>()
+}
+
+pub fn parse_line(number: usize, line: &str) -> Result<SourceLine, Error> {
     let mut tokens = vec![];
+    let mut spans = vec![];
+    let mut comment = None;
     let mut chars = line.chars().peekable();
 
-    while let Some(c) = chars.next() {
+    loop {
+        let start = position(line, &chars);
+
+        let Some(c) = chars.next() else {
+            break;
+        };
+
         match c {
-            ';' => break,
+            ';' => {
+                comment = Some(line[start + 1..].to_string());
+                break;
+            }
             ' ' | '\t' => continue,
             ',' => tokens.push(Token::Comma),
             '\'' => {
@@ -93,7 +303,7 @@ pub fn parse_line(line: &str) -> Result<Vec<Token>, Error> {
                     Some(next_c) => match next_c {
                         '\'' => return Err(Error::InvalidChar),
                         '\\' => parse_escaped_char(&mut chars)?,
-                        _ => next_c,
+                        _ => ascii_byte(line, &chars, next_c)?,
                     },
                 };
 
@@ -103,7 +313,7 @@ pub fn parse_line(line: &str) -> Result<Vec<Token>, Error> {
                 }
             }
             '"' => {
-                let mut value = String::new();
+                let mut value = vec![];
 
                 loop {
                     match chars.next() {
@@ -111,7 +321,7 @@ pub fn parse_line(line: &str) -> Result<Vec<Token>, Error> {
                         Some(next_c) => match next_c {
                             '\"' => break,
                             '\\' => value.push(parse_escaped_char(&mut chars)?),
-                            _ => value.push(next_c),
+                            _ => value.push(ascii_byte(line, &chars, next_c)?),
                         },
                     }
                 }
@@ -176,29 +386,97 @@ pub fn parse_line(line: &str) -> Result<Vec<Token>, Error> {
                     tokens.push(Token::Identifier(value));
                 }
             }
-            _ => return Err(Error::InvalidChar),
+            '@' => {
+                let mut value = String::new();
+
+                while let Some(next_c) = chars.peek() {
+                    match next_c {
+                        'a'..='z' | 'A'..='Z' | '0'..='9' | '_' => {
+                            value.push(chars.next().expect("peeked character should not be None"));
+                        }
+                        _ => break,
+                    }
+                }
+
+                if value.is_empty() {
+                    return Err(Error::InvalidChar);
+                }
+
+                tokens.push(Token::Macro(value));
+            }
+            _ => {
+                ascii_byte(line, &chars, c)?;
+
+                return Err(Error::InvalidChar);
+            }
+        }
+
+        if spans.len() < tokens.len() {
+            spans.push(start..position(line, &chars));
         }
     }
 
-    Ok(tokens)
+    let trace_tags = comment.as_deref().map(parse_trace_tags).unwrap_or_default();
+
+    Ok(SourceLine {
+        number,
+        tokens,
+        spans,
+        comment,
+        trace_tags,
+        text: line.to_string(),
+    })
 }
 
-pub fn parse_file<P>(file_path: P) -> Result<Vec<Vec<Token>>, Box<dyn std::error::Error>>
+/// Splits the source into lines, keeping blank and comment-only lines.
+pub fn parse_source(source: &str) -> Result<Vec<SourceLine>, Error> {
+    source
+        .lines()
+        .enumerate()
+        .map(|(index, line)| {
+            parse_line(index + 1, line).map_err(|error| Error::AtLine(index + 1, Box::new(error)))
+        })
+        .collect()
+}
+
+pub fn parse_file<P>(file_path: P) -> Result<Vec<SourceLine>, Box<dyn std::error::Error>>
 where
     P: AsRef<Path>,
 {
-    let file = File::open(file_path)?;
-    let reader = BufReader::new(file);
+    let source = std::fs::read_to_string(file_path)?;
 
-    let mut token_lines = vec![];
+    Ok(parse_source(&source)?)
+}
 
-    for line in reader.lines() {
-        let tokens = parse_line(&line?)?;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        if !tokens.is_empty() {
-            token_lines.push(tokens);
-        }
+    #[test]
+    fn parses_only_trace_tags_of_the_ide_grammar() {
+        assert_eq!(
+            parse_trace_tags("#2d4a #1c, #retVal #x"),
+            [
+                TraceTag::Format {
+                    size: 2,
+                    format: TraceFormat::Decimal,
+                    count: 4
+                },
+                TraceTag::Symbol(String::from("retVal")),
+                TraceTag::Symbol(String::from("x")),
+            ]
+        );
+        assert_eq!(parse_trace_tags(" print #1, issue # 5, #3d #2d0a #2dx"), []);
     }
 
-    Ok(token_lines)
+    #[test]
+    fn reports_the_character_column_of_the_offending_character() {
+        let error = parse_line(1, "é:   .ASCII \"ab\\\"é\"").unwrap_err();
+
+        assert_eq!(error.to_string(), "non-ASCII character 'é' at column 1");
+
+        let error = parse_line(1, "     .ASCII \"ab\\\"é\" ;é").unwrap_err();
+
+        assert_eq!(error.to_string(), "non-ASCII character 'é' at column 18");
+    }
 }
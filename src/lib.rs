@@ -0,0 +1,33 @@
+pub mod address;
+pub mod assembler;
+pub mod builder;
+pub mod cfg;
+pub mod dap;
+pub mod diagnostic;
+pub mod disassembler;
+pub mod dot;
+pub mod dotcommand;
+pub mod embed;
+pub mod formatter;
+pub mod gdb;
+pub mod hexfile;
+pub mod image;
+pub mod instruction;
+pub mod isa;
+pub mod lexer;
+pub mod linker;
+pub mod lint;
+pub mod lsp;
+pub mod macros;
+pub mod message;
+pub mod migrate;
+pub mod object;
+pub mod register;
+pub mod simulator;
+pub mod stack;
+pub mod suggest;
+pub mod symbols;
+pub mod target;
+pub mod trace;
+pub mod traps;
+pub mod types;
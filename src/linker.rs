@@ -0,0 +1,226 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+};
+
+use crate::{object::ObjectFile, types::Pep8Word};
+
+#[derive(Debug)]
+pub enum Error {
+    DuplicateSymbol {
+        symbol: String,
+        first: String,
+        second: String,
+    },
+    UndefinedSymbol {
+        symbol: String,
+        object: String,
+    },
+    RelocationOutOfBounds {
+        offset: u16,
+        object: String,
+    },
+    ImageTooLarge(usize),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::DuplicateSymbol {
+                symbol,
+                first,
+                second,
+            } => write!(
+                f,
+                "duplicate symbol: {symbol} is defined in both {first} and {second}"
+            ),
+            Self::UndefinedSymbol { symbol, object } => {
+                write!(f, "undefined symbol: {symbol} referenced from {object}")
+            }
+            Self::RelocationOutOfBounds { offset, object } => {
+                write!(
+                    f,
+                    "relocation at {offset:04X} is outside the code of {object}"
+                )
+            }
+            Self::ImageTooLarge(size) => {
+                write!(f, "linked image is {size} bytes, which exceeds 64 KiB")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Errors collected while linking, so that every duplicate and missing
+/// symbol can be reported at once.
+#[derive(Debug)]
+pub struct Errors(pub Vec<Error>);
+
+impl Display for Errors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let messages: Vec<String> = self.0.iter().map(|error| error.to_string()).collect();
+
+        write!(f, "{}", messages.join("\n"))
+    }
+}
+
+impl std::error::Error for Errors {}
+
+/// Places the objects one after the other starting at address 0 and patches
+/// every relocation with the final address of its symbol, or moves the
+/// address it holds along with its object.
+///
+/// Each object is paired with a name used in error messages.
+pub fn link(objects: &[(String, ObjectFile)]) -> Result<Vec<u8>, Errors> {
+    let mut errors = vec![];
+    let mut bases = vec![];
+    let mut symbols: HashMap<&str, (Pep8Word, &str)> = HashMap::new();
+    let mut size = 0;
+
+    for (name, object) in objects {
+        bases.push(size);
+
+        for symbol in &object.exports {
+            let value = if symbol.absolute {
+                symbol.value
+            } else {
+                Pep8Word::new(symbol.value.as_u16().wrapping_add(size as u16))
+            };
+
+            match symbols.get(symbol.name.as_str()) {
+                Some((_, first)) => errors.push(Error::DuplicateSymbol {
+                    symbol: symbol.name.clone(),
+                    first: first.to_string(),
+                    second: name.clone(),
+                }),
+                None => {
+                    symbols.insert(&symbol.name, (value, name));
+                }
+            }
+        }
+
+        size += object.code.len();
+    }
+
+    if size > 0x10000 {
+        errors.push(Error::ImageTooLarge(size));
+
+        return Err(Errors(errors));
+    }
+
+    let mut image = Vec::with_capacity(size);
+    let mut reported = HashSet::new();
+
+    for ((name, object), base) in objects.iter().zip(bases) {
+        let mut code = object.code.clone();
+
+        for relocation in &object.relocations {
+            let offset = relocation.offset as usize;
+
+            let Some(field) = code.get_mut(offset..offset + 2) else {
+                errors.push(Error::RelocationOutOfBounds {
+                    offset: relocation.offset,
+                    object: name.clone(),
+                });
+                continue;
+            };
+
+            let Some(symbol) = &relocation.symbol else {
+                let address = u16::from_be_bytes([field[0], field[1]]).wrapping_add(base as u16);
+
+                field.copy_from_slice(&address.to_be_bytes());
+                continue;
+            };
+
+            match symbols.get(symbol.as_str()) {
+                Some((value, _)) => field.copy_from_slice(&value.as_bytes()),
+                None => {
+                    if reported.insert((name, symbol)) {
+                        errors.push(Error::UndefinedSymbol {
+                            symbol: symbol.clone(),
+                            object: name.clone(),
+                        });
+                    }
+                }
+            }
+        }
+
+        debug_assert_eq!(image.len(), base);
+        image.extend(code);
+    }
+
+    if errors.is_empty() {
+        Ok(image)
+    } else {
+        Err(Errors(errors))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        assembler::{assemble, assemble_object, Options},
+        lexer::parse_source,
+    };
+
+    fn object(source: &str) -> ObjectFile {
+        let lines = parse_source(source).unwrap();
+
+        assemble_object(lines, &Options::default()).unwrap().0
+    }
+
+    #[test]
+    fn labels_are_private_unless_exported() {
+        let main = object(
+            "         .EXPORT num
+         BR      main
+num:     .BLOCK  2
+main:    CALL    print
+loop:    BR      loop
+         .END
+",
+        );
+        let library = object(
+            "         .EXPORT print
+print:   DECO    num,d
+loop:    BR      loop
+         .END
+",
+        );
+        let image = link(&[("main".into(), main), ("library".into(), library)]).unwrap();
+        let whole = assemble(
+            parse_source(
+                "         BR      main
+num:     .BLOCK  2
+main:    CALL    print
+loop:    BR      loop
+print:   DECO    num,d
+again:   BR      again
+         .END
+",
+            )
+            .unwrap(),
+            &Options::default(),
+        )
+        .unwrap();
+
+        assert_eq!(image, whole.byte_code);
+    }
+
+    #[test]
+    fn reports_missing_and_duplicate_exports() {
+        let first = object("         .EXPORT main\nmain:    CALL    print\n         .END\n");
+        let second = object("         .EXPORT main\nmain:    STOP\n         .END\n");
+        let errors = link(&[("first".into(), first), ("second".into(), second)])
+            .unwrap_err()
+            .to_string();
+
+        assert_eq!(
+            errors,
+            "duplicate symbol: main is defined in both first and second\n\
+             undefined symbol: print referenced from first"
+        );
+    }
+}
@@ -0,0 +1,395 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    address::{AddrLocation, AddrMode, Address, SymbolKind},
+    assembler::{Assembly, Statement, StatementLine},
+    cfg::{Exit, Graph},
+    diagnostic::Warning,
+    dotcommand::DotCommand,
+    instruction::Instruction,
+    isa::Operation,
+    lexer::SourceLine,
+    stack,
+};
+
+/// Names of the checks, as given to `lint: allow(...)` pragmas.
+const FALLTHROUGH: &str = "fallthrough";
+const NO_STOP: &str = "no-stop";
+const BRANCH_TO_DATA: &str = "branch-to-data";
+const STORE_TO_CODE: &str = "store-to-code";
+const ADDRESS_OF_EQUATE: &str = "address-of-equate";
+const BYTE_OF_WORD: &str = "byte-of-word";
+const STACK_BALANCE: &str = "stack-balance";
+const UNUSED_TRAP: &str = "unused-trap";
+
+/// Checks named by a `lint: allow(name, ...)` pragma in a comment.
+fn allowed_checks(comment: &str) -> Vec<String> {
+    let Some((_, pragma)) = comment.split_once("lint:") else {
+        return vec![];
+    };
+    let Some(names) = pragma.trim_start().strip_prefix("allow(") else {
+        return vec![];
+    };
+    let names = names.split(')').next().unwrap_or_default();
+
+    names
+        .split(',')
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// Checks suppressed on each line. A pragma applies to its own line, or to
+/// the next statement when it is on a line of its own.
+fn suppressions(lines: &[SourceLine]) -> HashMap<usize, HashSet<String>> {
+    let mut suppressions: HashMap<usize, HashSet<String>> = HashMap::new();
+    let mut pending = vec![];
+
+    for line in lines {
+        if let Some(comment) = &line.comment {
+            pending.extend(allowed_checks(comment));
+        }
+
+        if !line.tokens.is_empty() {
+            suppressions
+                .entry(line.number)
+                .or_default()
+                .extend(pending.drain(..));
+        }
+    }
+
+    suppressions
+}
+
+/// Label named by an operand in direct or indexed mode, which is the memory
+/// the instruction accesses.
+fn accessed_label(address: &Address) -> Option<&String> {
+    match (&address.location, &address.mode) {
+        (AddrLocation::Label(label), AddrMode::Direct | AddrMode::Indexed) => Some(label),
+        _ => None,
+    }
+}
+
+struct Linter<'a> {
+    assembly: &'a Assembly,
+    graph: Graph<'a>,
+    /// Warnings along with the name of the check reporting them.
+    warnings: Vec<(&'static str, Warning)>,
+}
+
+impl<'a> Linter<'a> {
+    fn warn(&mut self, line: usize, check: &'static str, message: String) {
+        self.warnings
+            .push((check, Warning::new(line, format!("{message} [{check}]"))));
+    }
+
+    fn definition_kind(&self, label: &String) -> Option<SymbolKind> {
+        self.assembly
+            .address_table
+            .definition(label)
+            .map(|definition| definition.kind)
+    }
+
+    /// Statement defining `label`.
+    fn defining_statement(&self, label: &String) -> Option<&'a Statement> {
+        let definition = self.assembly.address_table.definition(label)?;
+
+        self.assembly
+            .statement_at_line(definition.line)
+            .map(StatementLine::statement)
+    }
+
+    fn check_fallthrough(&mut self, index: usize) {
+        let Some(Exit::Next | Exit::Branch(_) | Exit::Call(_)) = self.graph.exit(index) else {
+            return;
+        };
+        let line = self.assembly.statement_lines[index].line();
+
+        match self.graph.next(index) {
+            None => self.warn(
+                line,
+                FALLTHROUGH,
+                String::from("execution can run past the end of the program"),
+            ),
+            Some(next) if self.graph.instruction(next).is_none() => {
+                let next_line = self.assembly.statement_lines[next].line();
+
+                self.warn(
+                    line,
+                    FALLTHROUGH,
+                    format!("execution can fall through into data on line {next_line}"),
+                );
+            }
+            Some(_) => {}
+        }
+    }
+
+    fn check_stop(&mut self) {
+        let Some(entry) = self.graph.statement_at(0) else {
+            return;
+        };
+        let reachable = self.graph.reachable(entry, true);
+        let stops = (0..reachable.len())
+            .any(|index| reachable[index] && self.graph.exit(index) == Some(Exit::Stop));
+
+        if !stops {
+            self.warn(
+                self.assembly.statement_lines[entry].line(),
+                NO_STOP,
+                String::from("no STOP instruction is reachable from the start of the program"),
+            );
+        }
+    }
+
+    fn check_instruction(&mut self, statement_line: &StatementLine, instruction: &Instruction) {
+        let line = statement_line.line();
+
+        let Some(address) = instruction.address() else {
+            return;
+        };
+
+        match instruction.operation {
+            Operation::BR
+            | Operation::BRLE
+            | Operation::BRLT
+            | Operation::BREQ
+            | Operation::BRNE
+            | Operation::BRGE
+            | Operation::BRGT
+            | Operation::BRV
+            | Operation::BRC
+            | Operation::CALL => {
+                if let (AddrLocation::Label(label), AddrMode::Immediate) =
+                    (&address.location, &address.mode)
+                {
+                    if self.definition_kind(label) == Some(SymbolKind::DataLabel) {
+                        self.warn(
+                            line,
+                            BRANCH_TO_DATA,
+                            format!("branch to data label {label}"),
+                        );
+                    }
+                }
+            }
+            Operation::STr | Operation::STBYTEr => {
+                if let Some(label) = accessed_label(address) {
+                    if self.definition_kind(label) == Some(SymbolKind::CodeLabel) {
+                        self.warn(
+                            line,
+                            STORE_TO_CODE,
+                            format!("store into code label {label}"),
+                        );
+                    }
+                }
+            }
+            Operation::LDBYTEr => {
+                if let Some(label) = accessed_label(address) {
+                    if let Some(Statement::DotCommand(DotCommand::WORD(_))) =
+                        self.defining_statement(label)
+                    {
+                        self.warn(
+                            line,
+                            BYTE_OF_WORD,
+                            format!("LDBYTE reads the high byte of .WORD {label}"),
+                        );
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Looks for likely bugs in an assembled program, skipping the checks
+/// suppressed by `lint: allow(...)` pragmas in the source comments.
+pub fn lint(lines: &[SourceLine], assembly: &Assembly) -> Vec<Warning> {
+    let mut linter = Linter {
+        assembly,
+        graph: Graph::new(assembly),
+        warnings: vec![],
+    };
+
+    for (index, statement_line) in assembly.statement_lines.iter().enumerate() {
+        match statement_line.statement() {
+            Statement::Instruction(instruction) => {
+                linter.check_fallthrough(index);
+                linter.check_instruction(statement_line, instruction);
+            }
+            Statement::DotCommand(DotCommand::ADDRSS(label)) => {
+                if linter.definition_kind(label) == Some(SymbolKind::Equate) {
+                    linter.warn(
+                        statement_line.line(),
+                        ADDRESS_OF_EQUATE,
+                        format!(".ADDRSS of .EQUATE symbol {label}, which is not an address"),
+                    );
+                }
+            }
+            Statement::DotCommand(_) => {}
+        }
+    }
+
+    let used_traps: HashSet<&String> = assembly
+        .statement_lines
+        .iter()
+        .filter_map(|statement_line| match statement_line.statement() {
+            Statement::Instruction(instruction) => instruction.trap.as_ref(),
+            Statement::DotCommand(_) => None,
+        })
+        .collect();
+
+    for statement_line in &assembly.statement_lines {
+        if let Statement::DotCommand(DotCommand::TRAP(trap)) = statement_line.statement() {
+            if !used_traps.contains(&trap.mnemonic) {
+                linter.warn(
+                    statement_line.line(),
+                    UNUSED_TRAP,
+                    format!("trap {} is never used", trap.mnemonic),
+                );
+            }
+        }
+    }
+
+    linter.check_stop();
+
+    for warning in stack::check(assembly) {
+        linter.warn(warning.line, STACK_BALANCE, warning.message);
+    }
+
+    let suppressions = suppressions(lines);
+    let mut warnings: Vec<_> = linter
+        .warnings
+        .into_iter()
+        .filter(|(check, warning)| {
+            !suppressions
+                .get(&warning.line)
+                .is_some_and(|allowed| allowed.contains(*check))
+        })
+        .map(|(_, warning)| warning)
+        .collect();
+
+    warnings.sort_by_key(|warning| warning.line);
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        assembler::{assemble, Options},
+        lexer::parse_source,
+    };
+
+    fn warnings(source: &str) -> Vec<String> {
+        let lines = parse_source(source).unwrap();
+        let assembly = assemble(lines.clone(), &Options::default()).unwrap();
+
+        lint(&lines, &assembly)
+            .iter()
+            .map(|warning| format!("{}: {}", warning.line, warning.message))
+            .collect()
+    }
+
+    #[test]
+    fn reports_execution_falling_into_data() {
+        assert_eq!(
+            warnings(
+                "main:    LDA     1,i
+num:     .WORD   1
+         .END
+"
+            ),
+            [
+                "1: execution can fall through into data on line 2 [fallthrough]",
+                "1: no STOP instruction is reachable from the start of the program [no-stop]",
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_a_missing_stop() {
+        assert_eq!(
+            warnings(
+                "main:    DECI    0x0100,d
+         BR      main
+         .END
+"
+            ),
+            ["1: no STOP instruction is reachable from the start of the program [no-stop]"]
+        );
+    }
+
+    #[test]
+    fn reports_a_branch_to_data() {
+        assert_eq!(
+            warnings(
+                "         LDA     0,i
+         BREQ    num
+         STOP
+num:     .WORD   0
+         .END
+"
+            ),
+            ["2: branch to data label num [branch-to-data]"]
+        );
+    }
+
+    #[test]
+    fn reports_a_store_to_code() {
+        assert_eq!(
+            warnings(
+                "main:    LDA     1,i
+         STA     main,d
+         STOP
+         .END
+"
+            ),
+            ["2: store into code label main [store-to-code]"]
+        );
+    }
+
+    #[test]
+    fn reports_the_address_of_an_equate() {
+        assert_eq!(
+            warnings(
+                "         STOP
+size:    .EQUATE 2
+ptr:     .ADDRSS size
+         .END
+"
+            ),
+            ["3: .ADDRSS of .EQUATE symbol size, which is not an address [address-of-equate]"]
+        );
+    }
+
+    #[test]
+    fn reports_a_byte_access_of_a_word() {
+        assert_eq!(
+            warnings(
+                "         LDBYTEA num,d
+         STOP
+num:     .WORD   1
+         .END
+"
+            ),
+            ["1: LDBYTE reads the high byte of .WORD num [byte-of-word]"]
+        );
+    }
+
+    #[test]
+    fn skips_checks_allowed_by_a_pragma() {
+        let source = "main:    LDA     1,i
+         STA     main,d      ;lint: allow(store-to-code)
+;lint: allow(byte-of-word, fallthrough)
+         LDBYTEA num,d
+         STA     main,d      ;lint: allow(fallthrough)
+         STOP
+num:     .WORD   1
+         .END
+";
+
+        assert_eq!(
+            warnings(source),
+            ["5: store into code label main [store-to-code]"]
+        );
+    }
+}
@@ -0,0 +1,625 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, Write},
+};
+
+use clap::ValueEnum;
+use serde_json::{json, Value};
+
+use crate::{
+    address::{AddressTable, SymbolKind},
+    assembler::{assemble, locate_error, symbol_table, Assembly, Options, StatementLine},
+    instruction::addr_modes,
+    lexer::{parse_line, SourceLine, Token},
+    message::{read_message, write_message, Error},
+    target::Target,
+    traps,
+};
+
+const METHOD_NOT_FOUND: i64 = -32601;
+
+const SEVERITY_ERROR: u8 = 1;
+const SEVERITY_WARNING: u8 = 2;
+
+const COMPLETION_KEYWORD: u8 = 14;
+const COMPLETION_VARIABLE: u8 = 6;
+const COMPLETION_CONSTANT: u8 = 21;
+const COMPLETION_ENUM_MEMBER: u8 = 20;
+
+/// What the server knows about an open document.
+struct Document {
+    target: Target,
+    lines: Vec<SourceLine>,
+    symbols: Option<AddressTable>,
+    assembly: Option<Assembly>,
+    diagnostics: Vec<Value>,
+}
+
+/// Column of the byte offset in UTF-16 code units, which LSP positions
+/// count.
+fn utf16_column(text: &str, offset: usize) -> usize {
+    text[..offset].encode_utf16().count()
+}
+
+/// Byte offset of the UTF-16 column in the text, or its length if the column
+/// is past the end.
+fn byte_offset(text: &str, column: usize) -> usize {
+    let mut units = 0;
+
+    for (offset, c) in text.char_indices() {
+        if units >= column {
+            return offset;
+        }
+
+        units += c.len_utf16();
+    }
+
+    text.len()
+}
+
+fn line_range(line: usize, text: &str) -> Value {
+    json!({
+        "start": { "line": line, "character": 0 },
+        "end": { "line": line, "character": utf16_column(text, text.len()) },
+    })
+}
+
+fn token_range(line: &SourceLine, span: &std::ops::Range<usize>) -> Value {
+    json!({
+        "start": { "line": line.number - 1, "character": utf16_column(&line.text, span.start) },
+        "end": { "line": line.number - 1, "character": utf16_column(&line.text, span.end) },
+    })
+}
+
+impl Document {
+    /// Lexes and assembles the text, keeping going past bad lines so that
+    /// every syntax error is reported and the other lines stay navigable.
+    fn analyze(text: &str, target: Target) -> Self {
+        let mut lines = vec![];
+        let mut diagnostics = vec![];
+        let mut valid_lines = vec![];
+
+        for (index, line_text) in text.lines().enumerate() {
+            let line = match parse_line(index + 1, line_text) {
+                Ok(line) => line,
+                Err(error) => {
+                    diagnostics.push(json!({
+                        "range": line_range(index, line_text),
+                        "severity": SEVERITY_ERROR,
+                        "source": "pep8",
+                        "message": error.to_string(),
+                    }));
+
+                    SourceLine {
+                        number: index + 1,
+                        tokens: vec![],
+                        spans: vec![],
+                        comment: None,
+                        trace_tags: vec![],
+                        text: line_text.to_string(),
+                    }
+                }
+            };
+
+            lines.push(line);
+        }
+
+        let traps = traps::collect(&lines, &[], target).unwrap_or_default();
+        let options = Options {
+            target,
+            ..Options::default()
+        };
+
+        for (index, line) in lines.iter().enumerate() {
+            if !line.tokens.is_empty() {
+                match StatementLine::from_tokens(&line.tokens, line.number, target, &traps) {
+                    Ok(_) => valid_lines.push(line.clone()),
+                    Err(error) => diagnostics.push(json!({
+                        "range": line_range(index, &line.text),
+                        "severity": SEVERITY_ERROR,
+                        "source": "pep8",
+                        "message": error.to_string(),
+                    })),
+                }
+            }
+        }
+
+        let (symbols, assembly) = match assemble(valid_lines.clone(), &options) {
+            Ok(assembly) => {
+                for warning in &assembly.warnings {
+                    diagnostics.push(json!({
+                        "range": line_range(warning.line - 1, &lines[warning.line - 1].text),
+                        "severity": SEVERITY_WARNING,
+                        "source": "pep8",
+                        "message": warning.message,
+                    }));
+                }
+
+                (Some(assembly.address_table.clone()), Some(assembly))
+            }
+            Err(error) => {
+                if let Some((line, message)) = locate_error(error.as_ref()) {
+                    diagnostics.push(json!({
+                        "range": line_range(line - 1, &lines[line - 1].text),
+                        "severity": SEVERITY_ERROR,
+                        "source": "pep8",
+                        "message": message,
+                    }));
+                }
+
+                (symbol_table(valid_lines, &options).ok(), None)
+            }
+        };
+
+        Self {
+            target,
+            lines,
+            symbols,
+            assembly,
+            diagnostics,
+        }
+    }
+
+    /// Token under the cursor, along with its line and span.
+    fn token_at(&self, position: &Value) -> Option<(&SourceLine, &Token, &std::ops::Range<usize>)> {
+        let line = self.lines.get(position["line"].as_u64()? as usize)?;
+        let character = byte_offset(&line.text, position["character"].as_u64()? as usize);
+
+        line.tokens
+            .iter()
+            .zip(&line.spans)
+            .find(|(_, span)| span.start <= character && character <= span.end)
+            .map(|(token, span)| (line, token, span))
+    }
+
+    /// Name of the symbol under the cursor, if it is a label or an operand.
+    fn symbol_at(&self, position: &Value) -> Option<&String> {
+        match self.token_at(position)? {
+            (_, Token::Label(name) | Token::Identifier(name), _) => {
+                self.symbols.as_ref()?.definition(name).map(|_| name)
+            }
+            _ => None,
+        }
+    }
+
+    fn definition_location(&self, uri: &str, name: &String) -> Option<Value> {
+        let definition = self.symbols.as_ref()?.definition(name)?;
+        let line = self.lines.get(definition.line - 1)?;
+        let span = line.spans.first()?;
+
+        Some(json!({
+            "uri": uri,
+            "range": token_range(line, &(span.start..span.end - 1)),
+        }))
+    }
+
+    fn reference_locations(&self, uri: &str, name: &String) -> Vec<Value> {
+        let Some(definition) = self
+            .symbols
+            .as_ref()
+            .and_then(|symbols| symbols.definition(name))
+        else {
+            return vec![];
+        };
+        let mut locations = vec![];
+
+        for line_number in &definition.references {
+            let line = &self.lines[line_number - 1];
+
+            for (token, span) in line.tokens.iter().zip(&line.spans) {
+                if token == &Token::Identifier(name.clone()) {
+                    locations.push(json!({ "uri": uri, "range": token_range(line, span) }));
+                }
+            }
+        }
+
+        locations
+    }
+
+    fn hover(&self, position: &Value) -> Option<Value> {
+        let (line, token, span) = self.token_at(position)?;
+
+        let contents = match token {
+            Token::Label(name) | Token::Identifier(name)
+                if self.symbols.as_ref()?.definition(name).is_some() =>
+            {
+                let definition = self.symbols.as_ref()?.definition(name)?;
+                let value = definition.value.as_u16();
+
+                match definition.kind {
+                    SymbolKind::Equate => {
+                        format!("**{name}**: equate, value `0x{value:04X}` ({value})")
+                    }
+                    kind => format!("**{name}**: {kind} label, address `0x{value:04X}`"),
+                }
+            }
+            Token::Identifier(_) | Token::DotCommand(_) => {
+                let assembly = self.assembly.as_ref()?;
+                let statement_line = assembly.statement_at_line(line.number)?;
+                let bytes: Vec<String> = assembly
+                    .bytes_of(statement_line)
+                    .iter()
+                    .map(|byte| format!("{byte:02X}"))
+                    .collect();
+
+                format!(
+                    "address `0x{:04X}`, encoding `{}`",
+                    statement_line.address(),
+                    bytes.join(" ")
+                )
+            }
+            _ => return None,
+        };
+
+        Some(json!({
+            "contents": { "kind": "markdown", "value": contents },
+            "range": token_range(line, span),
+        }))
+    }
+
+    fn completions(&self, position: &Value) -> Option<Vec<Value>> {
+        let line = self.lines.get(position["line"].as_u64()? as usize)?;
+        let character = byte_offset(&line.text, position["character"].as_u64()? as usize);
+        let before: Vec<&Token> = line
+            .tokens
+            .iter()
+            .zip(&line.spans)
+            .filter(|(token, span)| {
+                span.end < character
+                    || (span.end == character && matches!(token, Token::Comma | Token::Label(_)))
+            })
+            .map(|(token, _)| token)
+            .filter(|token| !matches!(token, Token::Label(_)))
+            .collect();
+
+        let items = match before.as_slice() {
+            [] => self
+                .target
+                .mnemonics()
+                .chain(self.target.dot_commands().iter().copied())
+                .map(|keyword| json!({ "label": keyword, "kind": COMPLETION_KEYWORD }))
+                .collect(),
+            [Token::Identifier(mnemonic), .., Token::Comma] => addr_modes(mnemonic, self.target)
+                .iter()
+                .map(|mode| self.target.mode_name(mode))
+                .map(|mode| json!({ "label": mode, "kind": COMPLETION_ENUM_MEMBER }))
+                .collect(),
+            [Token::Identifier(_) | Token::DotCommand(_)] => self
+                .symbols
+                .as_ref()?
+                .iter()
+                .map(|(name, definition)| {
+                    let kind = match definition.kind {
+                        SymbolKind::Equate => COMPLETION_CONSTANT,
+                        _ => COMPLETION_VARIABLE,
+                    };
+
+                    json!({ "label": name, "kind": kind })
+                })
+                .collect(),
+            _ => vec![],
+        };
+
+        Some(items)
+    }
+}
+
+/// Language server for Pep/8 source files, speaking LSP over any reader and
+/// writer pair.
+pub struct Server<W: Write> {
+    writer: W,
+    /// Machine the documents are assembled for, which the client may change
+    /// with a `target` initialization option.
+    target: Target,
+    documents: HashMap<String, Document>,
+}
+
+impl<W: Write> Server<W> {
+    pub fn new(writer: W, target: Target) -> Self {
+        Self {
+            writer,
+            target,
+            documents: HashMap::new(),
+        }
+    }
+
+    /// Handles messages until the client sends `exit` or closes the input.
+    pub fn run<R: BufRead>(&mut self, mut reader: R) -> Result<(), Error> {
+        while let Some(message) = read_message(&mut reader)? {
+            if message["method"] == "exit" {
+                break;
+            }
+
+            self.handle(&message)?;
+        }
+
+        Ok(())
+    }
+
+    fn handle(&mut self, message: &Value) -> Result<(), Error> {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+
+        let result = match method {
+            "initialize" => {
+                let target = params["initializationOptions"]["target"].as_str();
+
+                if let Some(target) = target.and_then(|target| Target::from_str(target, true).ok())
+                {
+                    self.target = target;
+                }
+
+                json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "definitionProvider": true,
+                    "referencesProvider": true,
+                    "hoverProvider": true,
+                    "completionProvider": { "triggerCharacters": [","] },
+                },
+                "serverInfo": { "name": "pep8-assembler" },
+                })
+            }
+            "textDocument/didOpen" => {
+                return self.update(uri, params["textDocument"]["text"].as_str());
+            }
+            "textDocument/didChange" => {
+                let changes = params["contentChanges"].as_array();
+                let text = changes.and_then(|changes| changes.last()?["text"].as_str());
+
+                return self.update(uri, text);
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+
+                return self.publish_diagnostics(uri, vec![]);
+            }
+            "textDocument/definition" => self
+                .documents
+                .get(uri)
+                .and_then(|document| {
+                    let name = document.symbol_at(&params["position"])?;
+
+                    document.definition_location(uri, name)
+                })
+                .unwrap_or(Value::Null),
+            "textDocument/references" => self
+                .documents
+                .get(uri)
+                .and_then(|document| {
+                    let name = document.symbol_at(&params["position"])?;
+                    let mut locations = vec![];
+
+                    if params["context"]["includeDeclaration"] == true {
+                        locations.extend(document.definition_location(uri, name));
+                    }
+
+                    locations.extend(document.reference_locations(uri, name));
+
+                    Some(Value::from(locations))
+                })
+                .unwrap_or(Value::Null),
+            "textDocument/hover" => self
+                .documents
+                .get(uri)
+                .and_then(|document| document.hover(&params["position"]))
+                .unwrap_or(Value::Null),
+            "textDocument/completion" => self
+                .documents
+                .get(uri)
+                .and_then(|document| document.completions(&params["position"]))
+                .map(Value::from)
+                .unwrap_or(Value::Null),
+            "shutdown" => Value::Null,
+            _ if message.get("id").is_none() => return Ok(()),
+            _ => {
+                return write_message(
+                    &mut self.writer,
+                    &json!({
+                        "jsonrpc": "2.0",
+                        "id": message["id"],
+                        "error": {
+                            "code": METHOD_NOT_FOUND,
+                            "message": format!("unsupported method: {method}"),
+                        },
+                    }),
+                );
+            }
+        };
+
+        if message.get("id").is_none() {
+            return Ok(());
+        }
+
+        write_message(
+            &mut self.writer,
+            &json!({ "jsonrpc": "2.0", "id": message["id"], "result": result }),
+        )
+    }
+
+    fn update(&mut self, uri: &str, text: Option<&str>) -> Result<(), Error> {
+        let Some(text) = text else {
+            return Ok(());
+        };
+        let document = Document::analyze(text, self.target);
+        let diagnostics = document.diagnostics.clone();
+
+        self.documents.insert(uri.to_string(), document);
+
+        self.publish_diagnostics(uri, diagnostics)
+    }
+
+    fn publish_diagnostics(&mut self, uri: &str, diagnostics: Vec<Value>) -> Result<(), Error> {
+        write_message(
+            &mut self.writer,
+            &json!({
+                "jsonrpc": "2.0",
+                "method": "textDocument/publishDiagnostics",
+                "params": { "uri": uri, "diagnostics": diagnostics },
+            }),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const URI: &str = "file:///program.pep";
+
+    /// Runs the server over the requests and returns every message it sends.
+    fn transcript(target: Target, requests: &[Value]) -> Vec<Value> {
+        let mut input = vec![];
+
+        for request in requests {
+            write_message(&mut input, request).unwrap();
+        }
+
+        let mut output = vec![];
+
+        Server::new(&mut output, target)
+            .run(input.as_slice())
+            .unwrap();
+
+        let mut reader = output.as_slice();
+        let mut messages = vec![];
+
+        while let Some(message) = read_message(&mut reader).unwrap() {
+            messages.push(message);
+        }
+
+        messages
+    }
+
+    fn open(text: &str) -> Value {
+        json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": { "textDocument": { "uri": URI, "languageId": "pep8", "version": 1, "text": text } },
+        })
+    }
+
+    fn request(id: u64, method: &str, line: u64, character: u64) -> Value {
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": {
+                "textDocument": { "uri": URI },
+                "position": { "line": line, "character": character },
+                "context": { "includeDeclaration": true },
+            },
+        })
+    }
+
+    fn range(line: u64, start: u64, end: u64) -> Value {
+        json!({
+            "start": { "line": line, "character": start },
+            "end": { "line": line, "character": end },
+        })
+    }
+
+    const PROGRAM: &str = "         BR      main
+num:     .WORD   5           ;Ünïcödé
+main:    LDA     num,d
+         ADDA    num,d
+         STOP
+         .END
+";
+
+    #[test]
+    fn answers_a_session() {
+        let messages = transcript(
+            Target::Pep8,
+            &[
+                json!({ "jsonrpc": "2.0", "id": 0, "method": "initialize", "params": {} }),
+                open(PROGRAM),
+                request(1, "textDocument/definition", 2, 18),
+                request(2, "textDocument/references", 1, 1),
+                request(3, "textDocument/hover", 1, 0),
+                request(4, "textDocument/hover", 3, 10),
+                request(5, "textDocument/completion", 3, 17),
+                json!({ "jsonrpc": "2.0", "method": "exit" }),
+            ],
+        );
+
+        assert_eq!(messages.len(), 7);
+        assert_eq!(messages[0]["result"]["capabilities"]["hoverProvider"], true);
+        assert_eq!(
+            messages[1]["params"],
+            json!({ "uri": URI, "diagnostics": [] })
+        );
+        assert_eq!(
+            messages[2]["result"],
+            json!({ "uri": URI, "range": range(1, 0, 3) })
+        );
+        assert_eq!(
+            messages[3]["result"],
+            json!([
+                { "uri": URI, "range": range(1, 0, 3) },
+                { "uri": URI, "range": range(2, 17, 20) },
+                { "uri": URI, "range": range(3, 17, 20) },
+            ])
+        );
+        assert_eq!(
+            messages[4]["result"]["contents"]["value"],
+            "**num**: data label, address `0x0003`"
+        );
+        assert_eq!(
+            messages[5]["result"]["contents"]["value"],
+            "address `0x0008`, encoding `71 00 03`"
+        );
+
+        let completions = messages[6]["result"].as_array().unwrap();
+
+        assert!(completions.contains(&json!({ "label": "num", "kind": COMPLETION_VARIABLE })));
+        assert!(completions.contains(&json!({ "label": "main", "kind": COMPLETION_VARIABLE })));
+    }
+
+    #[test]
+    fn reports_diagnostics_in_utf16_columns() {
+        let messages = transcript(
+            Target::Pep8,
+            &[open("         LDA     x,q ;🦀é\n         .END\n")],
+        );
+        let diagnostics = messages[0]["params"]["diagnostics"].as_array().unwrap();
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0]["range"], range(0, 0, 25));
+        assert_eq!(diagnostics[0]["severity"], SEVERITY_ERROR);
+    }
+
+    #[test]
+    fn assembles_for_the_target_given_by_the_client() {
+        let program = "         LDWA    0x0003,d\n         RET\n         .END\n";
+        let messages = transcript(Target::Pep8, &[open(program)]);
+
+        assert_eq!(
+            messages[0]["params"]["diagnostics"]
+                .as_array()
+                .unwrap()
+                .len(),
+            2
+        );
+
+        let messages = transcript(
+            Target::Pep8,
+            &[
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": 0,
+                    "method": "initialize",
+                    "params": { "initializationOptions": { "target": "pep9" } },
+                }),
+                open(program),
+            ],
+        );
+
+        assert_eq!(messages[1]["params"]["diagnostics"], json!([]));
+        assert_eq!(
+            transcript(Target::Pep9, &[open(program)])[0]["params"]["diagnostics"],
+            json!([])
+        );
+    }
+}
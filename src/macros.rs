@@ -0,0 +1,179 @@
+use std::fmt::Display;
+
+use crate::{
+    lexer::{SourceLine, Token},
+    suggest::suggest,
+    target::Target,
+};
+
+#[derive(Debug)]
+pub enum Error {
+    UnknownMacro {
+        name: String,
+        suggestion: Option<String>,
+    },
+    Unsupported(Target),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownMacro {
+                name,
+                suggestion: Some(suggestion),
+            } => write!(f, "unknown macro @{name} (did you mean @{suggestion}?)"),
+            Self::UnknownMacro {
+                name,
+                suggestion: None,
+            } => write!(f, "unknown macro @{name}"),
+            Self::Unsupported(target) => write!(f, "macros are not supported on {target}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Names of the system calls available as macros: those of the target's
+/// operating system and those declared with `.SCALL` in the source.
+fn system_calls(lines: &[SourceLine], target: Target) -> Vec<String> {
+    let mut names: Vec<String> = target
+        .system_calls()
+        .iter()
+        .map(|(name, _)| name.to_string())
+        .collect();
+
+    for line in lines {
+        if let [.., Token::DotCommand(command), Token::Identifier(name)] = &line.tokens[..] {
+            if command.eq_ignore_ascii_case(".SCALL") && !names.contains(name) {
+                names.push(name.clone());
+            }
+        }
+    }
+
+    names
+}
+
+/// Expands the system call macros of Pep/10. `@NAME operand,mode` loads the
+/// system call number `NAME` into the T register and then executes
+/// `SCALL operand,mode`, so both statements share the line of the macro.
+pub fn expand(lines: Vec<SourceLine>, target: Target) -> Result<Vec<SourceLine>, (usize, Error)> {
+    let names = system_calls(&lines, target);
+    let mut expanded = vec![];
+
+    for line in lines {
+        let (label, tokens) = match &line.tokens[..] {
+            [Token::Label(label), tokens @ ..] => (Some(label), tokens),
+            tokens => (None, tokens),
+        };
+        let [Token::Macro(name), operands @ ..] = tokens else {
+            expanded.push(line);
+            continue;
+        };
+
+        if target != Target::Pep10 {
+            return Err((line.number, Error::Unsupported(target)));
+        }
+
+        let Some(system_call) = names
+            .iter()
+            .find(|system_call| system_call.eq_ignore_ascii_case(name))
+        else {
+            return Err((
+                line.number,
+                Error::UnknownMacro {
+                    name: name.clone(),
+                    suggestion: suggest(name, names.iter().map(String::as_str)).map(String::from),
+                },
+            ));
+        };
+
+        let mut load = label
+            .map(|label| vec![Token::Label(label.clone())])
+            .unwrap_or_default();
+
+        load.extend([
+            Token::Identifier(String::from("LDWT")),
+            Token::Identifier(system_call.clone()),
+            Token::Comma,
+            Token::Identifier(String::from("i")),
+        ]);
+
+        let mut call = vec![Token::Identifier(String::from("SCALL"))];
+
+        call.extend(operands.iter().cloned());
+
+        expanded.push(SourceLine {
+            tokens: load,
+            spans: vec![],
+            trace_tags: vec![],
+            ..line.clone()
+        });
+        expanded.push(SourceLine {
+            tokens: call,
+            spans: vec![],
+            ..line
+        });
+    }
+
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::parse_source;
+
+    fn expand_source(source: &str, target: Target) -> Result<Vec<String>, String> {
+        let lines = expand(parse_source(source).unwrap(), target)
+            .map_err(|(line, error)| format!("{line}: {error}"))?;
+
+        Ok(lines
+            .iter()
+            .map(|line| {
+                let tokens: Vec<String> = line.tokens.iter().map(Token::to_string).collect();
+
+                format!("{}: {}", line.number, tokens.join(" "))
+            })
+            .collect())
+    }
+
+    #[test]
+    fn expands_system_calls_into_ldwt_and_scall() {
+        let source = "main:    @DECI   num,d
+         @stro   msg,d
+         .SCALL  double
+         @Double 0,i
+         .END
+";
+
+        assert_eq!(
+            expand_source(source, Target::Pep10).unwrap(),
+            [
+                "1: main: LDWT DECI , i",
+                "1: SCALL num , d",
+                "2: LDWT STRO , i",
+                "2: SCALL msg , d",
+                "3: .SCALL double",
+                "4: LDWT double , i",
+                "4: SCALL 0 , i",
+                "5: .END",
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_macros() {
+        assert_eq!(
+            expand_source("         @STR    msg,d\n", Target::Pep10),
+            Err(String::from("1: unknown macro @STR (did you mean @STRO?)"))
+        );
+        assert_eq!(
+            expand_source("         @FROBNICATE\n", Target::Pep10),
+            Err(String::from("1: unknown macro @FROBNICATE"))
+        );
+        assert_eq!(
+            expand_source("         @DECI   num,d\n", Target::Pep9),
+            Err(String::from("1: macros are not supported on Pep/9"))
+        );
+    }
+}
@@ -1,44 +1,670 @@
-use assembler::assemble;
-use clap::Parser;
-use lexer::parse_file;
-use std::{fs::File, io::Write, path::PathBuf};
-
-mod address;
-mod assembler;
-mod dotcommand;
-mod instruction;
-mod lexer;
-mod register;
-mod types;
+use assembler::{
+    assembler::{assemble, assemble_object, Options},
+    cfg, dap, disassembler, dot, embed, formatter, gdb, hexfile, image, isa,
+    lexer::{parse_file, parse_source},
+    linker::link,
+    lint, lsp, migrate,
+    object::ObjectFile,
+    simulator, symbols, target, traps,
+};
+use clap::{ArgGroup, Args, Parser, Subcommand};
+use std::{
+    fmt::Display,
+    fs::File,
+    io::{BufReader, Write},
+    net::TcpListener,
+    path::{Path, PathBuf},
+};
 
 #[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+#[command(
+    version,
+    about,
+    long_about = None,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true,
+    group(ArgGroup::new("memory_image").args(["image", "region_map"]).multiple(true))
+)]
 struct CommandLineArguments {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Assembler source file
     #[arg(required = true)]
-    input_file: PathBuf,
+    input_file: Option<PathBuf>,
 
     /// Assembler output file
     #[arg(short, long)]
     output_file: Option<PathBuf>,
+
+    /// Emit a relocatable object instead of a Pep/8 image
+    #[arg(short = 'c', long)]
+    object: bool,
+
+    /// Write the symbol table as a text table to this file
+    #[arg(long, value_name = "FILE")]
+    symbols: Option<PathBuf>,
+
+    /// Write the symbol table as JSON to this file
+    #[arg(long, value_name = "FILE")]
+    symbols_json: Option<PathBuf>,
+
+    /// Write a cross-reference of symbol definitions and uses to this file
+    #[arg(long, value_name = "FILE")]
+    xref: Option<PathBuf>,
+
+    #[command(flatten)]
+    machine: MachineArguments,
+
+    /// Warn about symbols longer than the 8 characters allowed by Pep/8
+    #[arg(long)]
+    compatibility: bool,
+
+    /// Address the program is assembled to run at
+    #[arg(
+        long,
+        value_name = "ADDRESS",
+        value_parser = parse_word,
+        default_value = "0",
+        conflicts_with = "object"
+    )]
+    load_address: u16,
+
+    /// Write a 64 KiB memory image with the program at its load address to
+    /// this file
+    #[arg(long, value_name = "FILE", conflicts_with = "object")]
+    image: Option<PathBuf>,
+
+    /// Operating system ROM to place at the top of memory in the memory image
+    #[arg(long, value_name = "FILE", requires = "memory_image")]
+    os: Option<PathBuf>,
+
+    /// Value of the memory image bytes outside of the program and operating
+    /// system
+    #[arg(
+        long,
+        value_name = "BYTE",
+        value_parser = parse_byte,
+        default_value = "0",
+        requires = "image"
+    )]
+    fill: u8,
+
+    /// Write the code, data and operating system regions of the memory image
+    /// to this file
+    #[arg(long, value_name = "FILE", conflicts_with = "object")]
+    region_map: Option<PathBuf>,
+
+    /// Format of the assembler output
+    #[arg(long, value_enum, default_value_t = hexfile::Format::Binary, conflicts_with = "object")]
+    format: hexfile::Format,
+
+    /// Address of the first byte in Intel HEX or S-record output, by default
+    /// the load address
+    #[arg(long, value_name = "ADDRESS", value_parser = parse_word)]
+    base_address: Option<u16>,
+
+    /// Most data bytes in one Intel HEX or S-record record
+    #[arg(
+        long,
+        value_name = "BYTES",
+        value_parser = clap::value_parser!(u8).range(1..=hexfile::MAX_RECORD_LENGTH as i64),
+        default_value = "16"
+    )]
+    record_length: u8,
+
+    /// Write the assembled bytes as source in this language, commented with
+    /// their addresses and source lines
+    #[arg(long, value_name = "LANGUAGE", value_enum, conflicts_with_all = ["object", "format"])]
+    emit: Option<embed::Language>,
+
+    /// Name of the array written by --emit
+    #[arg(long, value_name = "NAME", value_parser = parse_identifier, default_value = "program")]
+    array_name: String,
 }
 
-fn main() {
-    let args = CommandLineArguments::parse();
+/// Machine a source file is assembled for, along with its custom traps.
+#[derive(Args, Debug)]
+struct MachineArguments {
+    /// Machine to assemble for
+    #[arg(long, value_enum, default_value_t)]
+    target: target::Target,
 
-    let r = parse_file(args.input_file).unwrap();
+    /// Read custom trap mnemonics from this file, one definition per line
+    #[arg(long, value_name = "FILE")]
+    traps: Option<PathBuf>,
+}
 
-    for l in &r {
-        println!("{:?}", l);
+impl MachineArguments {
+    /// Options assembling for the machine with the custom traps, exiting if
+    /// the trap file cannot be read.
+    fn options(&self) -> Options {
+        let traps = self
+            .traps
+            .as_ref()
+            .map(|path| {
+                let lines = parse_file(path).unwrap_or_else(|error| exit_with_error(error));
+
+                traps::parse(&lines, self.target).unwrap_or_else(|(line, error)| {
+                    exit_with_error(format!("{}: line {line}: {error}", path.display()))
+                })
+            })
+            .unwrap_or_default();
+
+        Options {
+            target: self.target,
+            traps,
+            ..Options::default()
+        }
     }
+}
 
-    let byte_code = assemble(r).unwrap();
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Link relocatable objects into a Pep/8 image
+    Link {
+        /// Object files, placed in memory in the order given
+        #[arg(required = true)]
+        object_files: Vec<PathBuf>,
 
-    match args.output_file {
+        /// Linked image output file
+        #[arg(short, long)]
+        output_file: Option<PathBuf>,
+    },
+    /// Align source files on the Pep/8 IDE columns, rewriting them in place
+    Fmt {
+        /// Assembler source files
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// List files that are not formatted instead of rewriting them, and
+        /// exit with an error if there are any
+        #[arg(long)]
+        check: bool,
+    },
+    /// Write the control-flow graph of each routine and the call graph as
+    /// Graphviz DOT
+    Cfg {
+        /// Assembler source file
+        input_file: PathBuf,
+
+        /// Write each graph to its own file in this directory, `calls.dot`
+        /// for the call graph and `routine-ADDRESS-NAME.dot` for each
+        /// routine, instead of printing them all
+        #[arg(short, long, value_name = "DIR")]
+        output_dir: Option<PathBuf>,
+
+        #[command(flatten)]
+        machine: MachineArguments,
+    },
+    /// Rewrite Pep/8 source files for Pep/9, reporting what needs to be
+    /// checked by hand
+    Migrate {
+        /// Pep/8 source files
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// Write each migrated file to this directory under its own name
+        /// instead of printing it
+        #[arg(short, long, value_name = "DIR")]
+        output_dir: Option<PathBuf>,
+    },
+    /// Report likely bugs in source files
+    Lint {
+        /// Assembler source files
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        #[command(flatten)]
+        machine: MachineArguments,
+    },
+    /// Disassemble a program image into source
+    Disasm {
+        /// Program image, or Intel HEX or S-record file
+        input_file: PathBuf,
+
+        /// Format of the input file, by default guessed from its extension
+        #[arg(long, value_enum)]
+        format: Option<hexfile::Format>,
+
+        /// Machine the image was assembled for
+        #[arg(long, value_enum, default_value_t)]
+        target: target::Target,
+    },
+    /// Print the instruction set of a machine as a Markdown table
+    Isa {
+        /// Machine whose instruction set is printed
+        #[arg(long, value_enum, default_value_t)]
+        target: target::Target,
+    },
+    /// Run a language server speaking LSP over stdin and stdout
+    Lsp {
+        /// Machine the documents are assembled for, unless the client gives
+        /// a `target` initialization option
+        #[arg(long, value_enum, default_value_t)]
+        target: target::Target,
+    },
+    /// Run a debug adapter speaking DAP over stdin and stdout
+    Dap,
+    /// Debug a program with any client of the GDB remote serial protocol
+    Gdb {
+        /// Assembler source file, or Intel HEX or S-record file
+        input_file: PathBuf,
+
+        /// Listen for a debugger on this local TCP port instead of speaking
+        /// the protocol over stdin and stdout
+        #[arg(short, long)]
+        port: Option<u16>,
+
+        /// File whose contents are read by CHARI and DECI
+        #[arg(short, long, value_name = "FILE")]
+        input: Option<PathBuf>,
+
+        #[command(flatten)]
+        machine: MachineArguments,
+    },
+}
+
+fn write_byte_code(byte_code: &[u8], output_file: Option<PathBuf>) {
+    match output_file {
         None => println!("{:02X?}", byte_code),
         Some(output_file_path) => {
             let mut output_file = File::create(output_file_path).unwrap();
-            output_file.write(&byte_code).unwrap();
+            output_file.write_all(byte_code).unwrap();
+        }
+    }
+}
+
+/// Reads a program file in the given format, or in the one its extension
+/// suggests, and otherwise as a bare image.
+fn read_program(path: &Path, format: Option<hexfile::Format>) -> hexfile::Program {
+    let format = format
+        .or_else(|| hexfile::Format::from_path(path))
+        .unwrap_or(hexfile::Format::Binary);
+
+    hexfile::read(path, format)
+        .unwrap_or_else(|error| exit_with_error(format!("{}: {error}", path.display())))
+}
+
+/// Parses a decimal or `0x`-prefixed hexadecimal number.
+fn parse_number(value: &str) -> Result<u32, String> {
+    match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => value.parse(),
+    }
+    .map_err(|error| error.to_string())
+}
+
+fn parse_word(value: &str) -> Result<u16, String> {
+    u16::try_from(parse_number(value)?).map_err(|error| error.to_string())
+}
+
+fn parse_byte(value: &str) -> Result<u8, String> {
+    u8::try_from(parse_number(value)?).map_err(|error| error.to_string())
+}
+
+/// Accepts names that are identifiers in C, Rust and Verilog alike.
+fn parse_identifier(value: &str) -> Result<String, String> {
+    let mut chars = value.chars();
+
+    if chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        Ok(value.to_string())
+    } else {
+        Err(format!("{value} is not an identifier"))
+    }
+}
+
+fn exit_with_error(error: impl Display) -> ! {
+    eprintln!("error: {error}");
+    std::process::exit(1);
+}
+
+fn main() {
+    let args = CommandLineArguments::parse();
+
+    if let Some(Command::Lsp { target }) = args.command {
+        let mut server = lsp::Server::new(std::io::stdout().lock(), target);
+
+        server
+            .run(std::io::stdin().lock())
+            .unwrap_or_else(|error| exit_with_error(error));
+
+        return;
+    }
+
+    if let Some(Command::Dap) = args.command {
+        let mut server = dap::Server::new(std::io::stdout().lock());
+
+        server
+            .run(BufReader::new(std::io::stdin()))
+            .unwrap_or_else(|error| exit_with_error(error));
+
+        return;
+    }
+
+    if let Some(Command::Disasm {
+        input_file,
+        format,
+        target,
+    }) = args.command
+    {
+        let program = read_program(&input_file, format);
+
+        print!(
+            "{}",
+            disassembler::disassemble(&program.bytes, program.origin, target)
+        );
+
+        return;
+    }
+
+    if let Some(Command::Isa { target }) = args.command {
+        match isa::write_table(target, &mut std::io::stdout()) {
+            Err(error) if error.kind() != std::io::ErrorKind::BrokenPipe => exit_with_error(error),
+            _ => {}
+        }
+
+        return;
+    }
+
+    if let Some(Command::Gdb {
+        input_file,
+        port,
+        input,
+        machine,
+    }) = args.command
+    {
+        // The simulator only implements the Pep/8 instruction set.
+        if machine.target != target::Target::Pep8 {
+            exit_with_error(format!("cannot simulate {} programs", machine.target));
+        }
+
+        let program = match hexfile::Format::from_path(&input_file) {
+            Some(format) => read_program(&input_file, Some(format)),
+            None => {
+                let lines = parse_file(input_file).unwrap_or_else(|error| exit_with_error(error));
+                let assembly = assemble(lines, &machine.options())
+                    .unwrap_or_else(|error| exit_with_error(error));
+
+                hexfile::Program {
+                    origin: assembly.origin,
+                    bytes: assembly.byte_code,
+                }
+            }
+        };
+        let input = input
+            .map(|path| std::fs::read(path).unwrap())
+            .unwrap_or_default();
+        let simulator = simulator::Simulator::load(&program.bytes, program.origin, &input)
+            .unwrap_or_else(|error| exit_with_error(error));
+        let mut stub = gdb::Stub::new(simulator);
+
+        let result = match port {
+            Some(port) => {
+                let listener = TcpListener::bind(("127.0.0.1", port)).unwrap();
+                eprintln!("listening on {}", listener.local_addr().unwrap());
+                let (stream, _) = listener.accept().unwrap();
+                stub.serve(BufReader::new(stream.try_clone().unwrap()), &stream)
+            }
+            None => stub.serve(BufReader::new(std::io::stdin()), std::io::stdout().lock()),
+        };
+
+        result.unwrap_or_else(|error| exit_with_error(error));
+
+        return;
+    }
+
+    if let Some(Command::Fmt { files, check }) = args.command {
+        let mut unformatted = false;
+
+        for file in files {
+            let source = std::fs::read_to_string(&file)
+                .unwrap_or_else(|error| exit_with_error(format!("{}: {error}", file.display())));
+            let lines = parse_source(&source)
+                .unwrap_or_else(|error| exit_with_error(format!("{}: {error}", file.display())));
+            let formatted = formatter::format_source(&lines, formatter::newline(&source));
+
+            if formatted != source {
+                if check {
+                    println!("{}", file.display());
+                    unformatted = true;
+                } else {
+                    std::fs::write(&file, formatted).unwrap_or_else(|error| {
+                        exit_with_error(format!("{}: {error}", file.display()))
+                    });
+                }
+            }
+        }
+
+        if unformatted {
+            std::process::exit(1);
+        }
+
+        return;
+    }
+
+    if let Some(Command::Cfg {
+        input_file,
+        output_dir,
+        machine,
+    }) = args.command
+    {
+        let lines = parse_file(input_file).unwrap_or_else(|error| exit_with_error(error));
+        let assembly = assemble(lines.clone(), &machine.options())
+            .unwrap_or_else(|error| exit_with_error(error));
+        let mut graphs = vec![(String::from("calls"), dot::write_call_graph(&assembly))];
+
+        // Routine graphs are named after their address as well, so that a
+        // subroutine labelled `main` or `calls` overwrites neither the main
+        // program's graph nor the call graph.
+        for routine in cfg::Graph::new(&assembly).routines() {
+            let graph = dot::write_routine(&lines, &assembly, &routine);
+            let address = assembly.statement_lines[routine.entry].address();
+
+            graphs.push((format!("routine-{address:04X}-{}", routine.name), graph));
+        }
+
+        for (name, graph) in graphs {
+            match &output_dir {
+                None => print!("{graph}"),
+                Some(output_dir) => {
+                    std::fs::write(output_dir.join(format!("{name}.dot")), graph).unwrap()
+                }
+            }
+        }
+
+        return;
+    }
+
+    if let Some(Command::Migrate { files, output_dir }) = args.command {
+        for file in files {
+            let source = std::fs::read_to_string(&file)
+                .unwrap_or_else(|error| exit_with_error(format!("{}: {error}", file.display())));
+            let migration = migrate::migrate(&source)
+                .unwrap_or_else(|error| exit_with_error(format!("{}: {error}", file.display())));
+
+            for warning in &migration.warnings {
+                eprintln!("{}: {warning}", file.display());
+            }
+
+            match &output_dir {
+                None => print!("{}", migration.source),
+                Some(output_dir) => {
+                    std::fs::write(output_dir.join(file.file_name().unwrap()), migration.source)
+                        .unwrap()
+                }
+            }
+        }
+
+        return;
+    }
+
+    if let Some(Command::Lint { files, machine }) = args.command {
+        let options = machine.options();
+        let mut warned = false;
+
+        for file in files {
+            let lines = parse_file(&file)
+                .unwrap_or_else(|error| exit_with_error(format!("{}: {error}", file.display())));
+            let assembly = assemble(lines.clone(), &options)
+                .unwrap_or_else(|error| exit_with_error(format!("{}: {error}", file.display())));
+
+            for warning in lint::lint(&lines, &assembly) {
+                println!("{}: {warning}", file.display());
+                warned = true;
+            }
+        }
+
+        if warned {
+            std::process::exit(1);
+        }
+
+        return;
+    }
+
+    if let Some(Command::Link {
+        object_files,
+        output_file,
+    }) = args.command
+    {
+        let mut objects = vec![];
+
+        for object_file in object_files {
+            let reader = BufReader::new(File::open(&object_file).unwrap());
+
+            objects.push((
+                object_file.display().to_string(),
+                ObjectFile::read(reader).unwrap(),
+            ));
+        }
+
+        let byte_code = link(&objects).unwrap_or_else(|errors| exit_with_error(errors));
+
+        write_byte_code(&byte_code, output_file);
+
+        return;
+    }
+
+    let input_file = args.input_file.unwrap();
+    let r = parse_file(&input_file).unwrap_or_else(|error| exit_with_error(error));
+
+    let options = Options {
+        compatibility: args.compatibility,
+        origin: args.load_address,
+        ..args.machine.options()
+    };
+
+    if args.object {
+        let (object, warnings) =
+            assemble_object(r, &options).unwrap_or_else(|error| exit_with_error(error));
+
+        for warning in warnings {
+            eprintln!("{warning}");
+        }
+
+        match args.output_file {
+            None => object.write(&mut std::io::stdout()).unwrap(),
+            Some(output_file_path) => {
+                let mut output_file = File::create(output_file_path).unwrap();
+                object.write(&mut output_file).unwrap();
+            }
         }
+
+        return;
+    }
+
+    let assembly = assemble(r, &options).unwrap_or_else(|error| exit_with_error(error));
+
+    for warning in &assembly.warnings {
+        eprintln!("{warning}");
+    }
+
+    if let Some(symbols_path) = args.symbols {
+        let mut symbols_file = File::create(symbols_path).unwrap();
+        symbols::write_text(&assembly.address_table, &mut symbols_file).unwrap();
+    }
+
+    if let Some(symbols_path) = args.symbols_json {
+        let mut symbols_file = File::create(symbols_path).unwrap();
+        symbols::write_json(&assembly.address_table, &mut symbols_file).unwrap();
+    }
+
+    if let Some(xref_path) = args.xref {
+        let mut xref_file = File::create(xref_path).unwrap();
+        symbols::write_cross_reference(&assembly, &mut xref_file).unwrap();
+    }
+
+    if args.image.is_some() || args.region_map.is_some() {
+        let os = args
+            .os
+            .map(|path| std::fs::read(path).unwrap_or_else(|error| exit_with_error(error)));
+        let image = image::Image::new(&assembly, os.as_deref(), args.fill)
+            .unwrap_or_else(|error| exit_with_error(error));
+
+        if let Some(image_path) = args.image {
+            std::fs::write(image_path, &image.memory).unwrap();
+        }
+
+        if let Some(region_map_path) = args.region_map {
+            let mut region_map_file = File::create(region_map_path).unwrap();
+            image.write_region_map(&mut region_map_file).unwrap();
+        }
+    }
+
+    if let Some(language) = args.emit {
+        let source = std::fs::read_to_string(&input_file).unwrap();
+        let mut embedded = vec![];
+
+        embed::write(
+            &assembly,
+            &source,
+            language,
+            &args.array_name,
+            &mut embedded,
+        )
+        .unwrap();
+
+        match args.output_file {
+            None => std::io::stdout().write_all(&embedded).unwrap(),
+            Some(output_file_path) => std::fs::write(output_file_path, embedded).unwrap(),
+        }
+
+        return;
+    }
+
+    let base_address = args.base_address.unwrap_or(assembly.origin);
+    let record_length = args.record_length as usize;
+    let mut records = vec![];
+
+    match args.format {
+        hexfile::Format::Binary => {
+            write_byte_code(&assembly.byte_code, args.output_file);
+            return;
+        }
+        hexfile::Format::Ihex => hexfile::write_intel_hex(
+            &assembly.byte_code,
+            base_address,
+            record_length,
+            &mut records,
+        ),
+        hexfile::Format::Srec => hexfile::write_srecord(
+            &assembly.byte_code,
+            base_address,
+            record_length,
+            &mut records,
+        ),
+    }
+    .unwrap();
+
+    match args.output_file {
+        None => std::io::stdout().write_all(&records).unwrap(),
+        Some(output_file_path) => std::fs::write(output_file_path, records).unwrap(),
     }
 }
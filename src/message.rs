@@ -0,0 +1,82 @@
+use std::{
+    fmt::Display,
+    io::{BufRead, Write},
+};
+
+use serde_json::Value;
+
+#[derive(Debug)]
+pub enum Error {
+    MissingContentLength,
+    InvalidHeader(String),
+    Io(std::io::Error),
+    Json(serde_json::Error),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingContentLength => write!(f, "message without Content-Length header"),
+            Self::InvalidHeader(header) => write!(f, "invalid message header: {header}"),
+            Self::Io(error) => write!(f, "{error}"),
+            Self::Json(error) => write!(f, "invalid message: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(value: serde_json::Error) -> Self {
+        Self::Json(value)
+    }
+}
+
+/// Reads one `Content-Length` framed JSON message, or `None` at end of input.
+pub fn read_message<R: BufRead>(reader: &mut R) -> Result<Option<Value>, Error> {
+    let mut content_length = None;
+
+    loop {
+        let mut header = String::new();
+
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+
+        let header = header.trim_end();
+
+        if header.is_empty() {
+            break;
+        }
+
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            content_length = Some(
+                value
+                    .trim()
+                    .parse()
+                    .map_err(|_| Error::InvalidHeader(header.to_string()))?,
+            );
+        }
+    }
+
+    let mut body = vec![0; content_length.ok_or(Error::MissingContentLength)?];
+
+    reader.read_exact(&mut body)?;
+
+    Ok(Some(serde_json::from_slice(&body)?))
+}
+
+pub fn write_message<W: Write>(writer: &mut W, message: &Value) -> Result<(), Error> {
+    let body = serde_json::to_string(message)?;
+
+    write!(writer, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    writer.flush()?;
+
+    Ok(())
+}
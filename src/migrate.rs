@@ -0,0 +1,314 @@
+use crate::{
+    diagnostic::Warning,
+    formatter::{newline, pad_to},
+    lexer::{parse_source, Error, SourceLine, Token},
+};
+
+/// Pep/8 mnemonics that Pep/9 renames.
+const RENAMES: &[(&str, &str)] = &[
+    ("LDA", "LDWA"),
+    ("LDX", "LDWX"),
+    ("STA", "STWA"),
+    ("STX", "STWX"),
+    ("CPA", "CPWA"),
+    ("CPX", "CPWX"),
+    ("LDBYTEA", "LDBA"),
+    ("LDBYTEX", "LDBX"),
+    ("STBYTEA", "STBA"),
+    ("STBYTEX", "STBX"),
+    ("RET0", "RET"),
+];
+
+const BRANCHES: &[&str] = &[
+    "BR", "BRLE", "BRLT", "BREQ", "BRNE", "BRGE", "BRGT", "BRV", "BRC", "CALL",
+];
+
+/// Distance between the mnemonic and operand columns of the Pep/8 IDE, used
+/// for operands added to a line that had none.
+const OPERAND_INDENT: usize = 8;
+
+/// Pep/8 source rewritten for Pep/9, along with the constructs that need a
+/// closer look.
+pub struct Migration {
+    pub source: String,
+    pub warnings: Vec<Warning>,
+}
+
+/// Spells `mnemonic` in lower case if the original was.
+fn cased(original: &str, mnemonic: &str) -> String {
+    if original.chars().any(|c| c.is_ascii_uppercase()) {
+        mnemonic.to_string()
+    } else {
+        mnemonic.to_lowercase()
+    }
+}
+
+/// Addressing mode of the line's instruction, if it is `sxf`, which Pep/9
+/// calls `sfx`.
+fn sxf_mode(line: &SourceLine) -> Option<&String> {
+    match &line.tokens[..] {
+        [.., Token::Comma, Token::Identifier(mode)] if mode.eq_ignore_ascii_case("sxf") => {
+            Some(mode)
+        }
+        _ => None,
+    }
+}
+
+/// Operands of the instruction whose mnemonic is token `index`, spelled as
+/// in the source except for the mode `sxf`.
+fn operands(line: &SourceLine, index: usize) -> String {
+    let (Some(first), Some(last)) = (line.spans.get(index + 1), line.spans.last()) else {
+        return String::new();
+    };
+    let text = &line.text[first.start..last.end];
+
+    match sxf_mode(line) {
+        Some(mode) => format!("{}{}", &text[..text.len() - mode.len()], cased(mode, "SFX")),
+        None => text.to_string(),
+    }
+}
+
+fn operand_column(line: &SourceLine, index: usize) -> usize {
+    line.spans
+        .get(index + 1)
+        .map_or(line.spans[index].start + OPERAND_INDENT, |span| span.start)
+}
+
+/// The line with its mnemonic and operands replaced, keeping the label, the
+/// columns of the fields and the comment.
+fn rewrite(line: &SourceLine, index: usize, mnemonic: &str, operands: &str) -> String {
+    let mut text = line.text[..line.spans[index].start].to_string();
+
+    text.push_str(mnemonic);
+
+    if !operands.is_empty() {
+        pad_to(&mut text, operand_column(line, index));
+        text.push_str(operands);
+    }
+
+    if let Some(comment) = &line.comment {
+        pad_to(&mut text, line.text.len() - comment.len() - 1);
+        text.push(';');
+        text.push_str(comment);
+    }
+
+    text
+}
+
+/// Line inserted after `line` for the second instruction of a rewrite, with
+/// its fields in the same columns.
+fn continuation(line: &SourceLine, index: usize, mnemonic: &str, operands: &str) -> String {
+    let mut text: String = line.text[..line.spans[index].start]
+        .chars()
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+
+    text.push_str(mnemonic);
+
+    if !operands.is_empty() {
+        pad_to(&mut text, operand_column(line, index));
+        text.push_str(operands);
+    }
+
+    text
+}
+
+/// Rewrites Pep/8 source for Pep/9, editing only the lines that change.
+///
+/// Renamed mnemonics and the `sxf` mode get their Pep/9 names, `RETn` pops
+/// its locals with `ADDSP` before `RET`, and `CHARI` and `CHARO` go through
+/// the accumulator to the memory-mapped ports `charIn` and `charOut`. Lines
+/// that cannot be translated are kept and reported, as are the rewrites that
+/// clobber a register or the status bits. The source keeps its line endings
+/// and its final newline, or lack of one.
+pub fn migrate(source: &str) -> Result<Migration, Error> {
+    let lines = parse_source(source)?;
+    let mut migrated = vec![];
+    let mut warnings = vec![];
+    let mut numeric_branches = vec![];
+    let mut resized = false;
+
+    for line in &lines {
+        let index = usize::from(matches!(line.tokens.first(), Some(Token::Label(_))));
+
+        if let Some(Token::Label(label)) = line.tokens.first() {
+            if label == "charIn" || label == "charOut" {
+                warnings.push(Warning::new(
+                    line.number,
+                    format!("{label} is the name of a Pep/9 I/O port and should be renamed"),
+                ));
+            }
+        }
+
+        let Some(Token::Identifier(original)) = line.tokens.get(index) else {
+            migrated.push(line.text.clone());
+            continue;
+        };
+        let mnemonic = original.to_uppercase();
+        let operands = operands(line, index);
+
+        if BRANCHES.contains(&mnemonic.as_str()) {
+            if let Some(Token::Number(address)) = line.tokens.get(index + 1) {
+                numeric_branches.push((line.number, *address));
+            }
+        }
+
+        if let Some((_, renamed)) = RENAMES.iter().find(|(old, _)| *old == mnemonic) {
+            migrated.push(rewrite(line, index, &cased(original, renamed), &operands));
+
+            if mnemonic.starts_with("LDBYTE") {
+                warnings.push(Warning::new(
+                    line.number,
+                    format!(
+                        "{renamed} clears the high byte of the register, which {mnemonic} kept"
+                    ),
+                ));
+            }
+
+            continue;
+        }
+
+        match &mnemonic[..] {
+            "RET1" | "RET2" | "RET3" | "RET4" | "RET5" | "RET6" | "RET7" => {
+                let locals = &mnemonic[3..];
+
+                migrated.push(rewrite(
+                    line,
+                    index,
+                    &cased(original, "ADDSP"),
+                    &format!("{locals},{}", cased(original, "i")),
+                ));
+                migrated.push(continuation(line, index, &cased(original, "RET"), ""));
+                warnings.push(Warning::new(
+                    line.number,
+                    format!("{mnemonic} now pops its locals with ADDSP, which sets NZVC"),
+                ));
+                resized = true;
+            }
+            "CHARI" => {
+                migrated.push(rewrite(
+                    line,
+                    index,
+                    &cased(original, "LDBA"),
+                    &format!("charIn,{}", cased(original, "d")),
+                ));
+                migrated.push(continuation(
+                    line,
+                    index,
+                    &cased(original, "STBA"),
+                    &operands,
+                ));
+                warnings.push(Warning::new(
+                    line.number,
+                    String::from("CHARI now reads charIn through the accumulator, overwriting A"),
+                ));
+                resized = true;
+            }
+            "CHARO" => {
+                migrated.push(rewrite(line, index, &cased(original, "LDBA"), &operands));
+                migrated.push(continuation(
+                    line,
+                    index,
+                    &cased(original, "STBA"),
+                    &format!("charOut,{}", cased(original, "d")),
+                ));
+                warnings.push(Warning::new(
+                    line.number,
+                    String::from("CHARO now writes charOut through the accumulator, overwriting A"),
+                ));
+                resized = true;
+            }
+            "NOP2" | "NOP3" => {
+                migrated.push(line.text.clone());
+                warnings.push(Warning::new(
+                    line.number,
+                    format!("{mnemonic} has no Pep/9 equivalent and must be rewritten by hand"),
+                ));
+            }
+            _ if sxf_mode(line).is_some() => {
+                migrated.push(rewrite(line, index, original, &operands))
+            }
+            _ => migrated.push(line.text.clone()),
+        }
+    }
+
+    if resized {
+        for (line, address) in numeric_branches {
+            warnings.push(Warning::new(
+                line,
+                format!("branch to the numeric address 0x{address:04X} may not match the migrated layout"),
+            ));
+        }
+    }
+
+    warnings.sort_by_key(|warning| warning.line);
+
+    let newline = newline(source);
+    let mut migrated = migrated.join(newline);
+
+    if source.ends_with('\n') {
+        migrated.push_str(newline);
+    }
+
+    Ok(Migration {
+        source: migrated,
+        warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_rewrites_that_change_registers_or_status_bits() {
+        let migration = migrate(
+            "main:    LDBYTEA char,d   ;read
+         RET2
+char:    .BLOCK  1
+         .END
+",
+        )
+        .unwrap();
+        let warnings: Vec<String> = migration
+            .warnings
+            .iter()
+            .map(|warning| format!("{}: {}", warning.line, warning.message))
+            .collect();
+
+        assert_eq!(
+            migration.source,
+            "main:    LDBA    char,d   ;read
+         ADDSP   2,i
+         RET
+char:    .BLOCK  1
+         .END
+"
+        );
+        assert_eq!(
+            warnings,
+            [
+                "1: LDBA clears the high byte of the register, which LDBYTEA kept",
+                "2: RET2 now pops its locals with ADDSP, which sets NZVC",
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_line_endings_and_final_newline() {
+        let source = "main:    CHARO   '!',i\r\n         STOP\r\n         .END\r\n";
+
+        assert_eq!(
+            migrate(source).unwrap().source,
+            "main:    LDBA    '!',i\r\n         STBA    charOut,d\r\n         STOP\r\n         .END\r\n"
+        );
+        assert_eq!(
+            migrate(source.trim_end()).unwrap().source,
+            "main:    LDBA    '!',i\r\n         STBA    charOut,d\r\n         STOP\r\n         .END"
+        );
+        assert_eq!(
+            migrate("         RET0\n         .END").unwrap().source,
+            "         RET\n         .END"
+        );
+    }
+}
@@ -0,0 +1,164 @@
+use std::{
+    fmt::Display,
+    io::{BufRead, Write},
+};
+
+use crate::types::Pep8Word;
+
+const MAGIC: &str = "PEP8OBJ 1";
+const BYTES_PER_CODE_LINE: usize = 16;
+
+#[derive(Debug)]
+pub enum Error {
+    MissingHeader,
+    InvalidRecord(usize, String),
+    Io(std::io::Error),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingHeader => write!(f, "missing object file header `{MAGIC}`"),
+            Self::InvalidRecord(line, record) => {
+                write!(f, "invalid object record on line {line}: {record}")
+            }
+            Self::Io(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub value: Pep8Word,
+    /// Absolute symbols, such as `.EQUATE` constants, are not moved by the
+    /// linker.
+    pub absolute: bool,
+}
+
+/// A 16-bit field of the code that must be patched with the final address
+/// of `symbol`, imported from another object, once the object has been
+/// placed in memory. Without a symbol the field holds an address within
+/// this object and is moved along with it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Relocation {
+    pub offset: u16,
+    pub symbol: Option<String>,
+}
+
+/// Relocatable object produced by assembling a single source file.
+///
+/// Exported symbol values are relative to the start of `code`. Imported
+/// symbols are referenced by relocations but defined in another object, and
+/// labels that are not exported are only known to this object.
+///
+/// The text representation is line oriented:
+///
+/// ```text
+/// PEP8OBJ 1
+/// CODE 04 00 03 ...
+/// EXPORT main 0003
+/// ABSOLUTE size 0010
+/// IMPORT print
+/// RELOC 0001
+/// RELOC 0004 print
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ObjectFile {
+    pub code: Vec<u8>,
+    pub exports: Vec<Symbol>,
+    pub imports: Vec<String>,
+    pub relocations: Vec<Relocation>,
+}
+
+impl ObjectFile {
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        writeln!(writer, "{MAGIC}")?;
+
+        for chunk in self.code.chunks(BYTES_PER_CODE_LINE) {
+            let bytes: Vec<String> = chunk.iter().map(|byte| format!("{byte:02X}")).collect();
+
+            writeln!(writer, "CODE {}", bytes.join(" "))?;
+        }
+
+        for symbol in &self.exports {
+            let record = if symbol.absolute {
+                "ABSOLUTE"
+            } else {
+                "EXPORT"
+            };
+
+            writeln!(
+                writer,
+                "{record} {} {:04X}",
+                symbol.name,
+                symbol.value.as_u16()
+            )?;
+        }
+
+        for symbol in &self.imports {
+            writeln!(writer, "IMPORT {symbol}")?;
+        }
+
+        for relocation in &self.relocations {
+            match &relocation.symbol {
+                Some(symbol) => writeln!(writer, "RELOC {:04X} {symbol}", relocation.offset)?,
+                None => writeln!(writer, "RELOC {:04X}", relocation.offset)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn read<R: BufRead>(reader: R) -> Result<Self, Error> {
+        let mut lines = reader.lines();
+
+        match lines.next().transpose()? {
+            Some(line) if line.trim_end() == MAGIC => {}
+            _ => return Err(Error::MissingHeader),
+        }
+
+        let mut object = Self::default();
+
+        for (index, line) in lines.enumerate() {
+            let line = line?;
+            let line_number = index + 2;
+            let invalid = || Error::InvalidRecord(line_number, line.clone());
+            let fields: Vec<&str> = line.split_whitespace().collect();
+
+            match fields.as_slice() {
+                [] => {}
+                ["CODE", bytes @ ..] => {
+                    for byte in bytes {
+                        object
+                            .code
+                            .push(u8::from_str_radix(byte, 16).map_err(|_| invalid())?);
+                    }
+                }
+                [record @ ("EXPORT" | "ABSOLUTE"), name, value] => object.exports.push(Symbol {
+                    name: name.to_string(),
+                    value: Pep8Word::new(u16::from_str_radix(value, 16).map_err(|_| invalid())?),
+                    absolute: *record == "ABSOLUTE",
+                }),
+                ["IMPORT", name] => object.imports.push(name.to_string()),
+                ["RELOC", offset, symbol @ ..] if symbol.len() <= 1 => {
+                    object.relocations.push(Relocation {
+                        offset: u16::from_str_radix(offset, 16).map_err(|_| invalid())?,
+                        symbol: symbol.first().map(|symbol| symbol.to_string()),
+                    })
+                }
+                _ => return Err(invalid()),
+            }
+        }
+
+        Ok(object)
+    }
+}
@@ -0,0 +1,458 @@
+use std::{collections::VecDeque, fmt::Display};
+
+use crate::{isa, target::Target};
+
+/// Stack pointer set by the Pep/8 operating system before running a program.
+pub const INITIAL_STACK_POINTER: u16 = 0xFBCF;
+
+const MEMORY_SIZE: usize = 0x10000;
+
+#[derive(Debug)]
+pub enum Error {
+    IllegalInstruction(u8, u16),
+    EndOfInput,
+    InvalidDecimalInput,
+    ProgramTooLarge(usize),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::IllegalInstruction(specifier, address) => {
+                write!(f, "illegal instruction {specifier:02X} at {address:04X}")
+            }
+            Self::EndOfInput => write!(f, "program read past the end of its input"),
+            Self::InvalidDecimalInput => write!(f, "invalid decimal input"),
+            Self::ProgramTooLarge(size) => {
+                write!(f, "program is {size} bytes, which exceeds 64 KiB")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Flags {
+    pub n: bool,
+    pub z: bool,
+    pub v: bool,
+    pub c: bool,
+}
+
+impl Flags {
+    /// Flags packed as `NZVC` in the low nibble, as read by `MOVFLGA`.
+    pub fn as_u16(&self) -> u16 {
+        (self.n as u16) << 3 | (self.z as u16) << 2 | (self.v as u16) << 1 | self.c as u16
+    }
+
+    /// Inverse of [`Flags::as_u16`], ignoring the bits above the low nibble.
+    pub fn from_u16(value: u16) -> Self {
+        Self {
+            n: value & 0b1000 != 0,
+            z: value & 0b0100 != 0,
+            v: value & 0b0010 != 0,
+            c: value & 0b0001 != 0,
+        }
+    }
+
+    fn set_nz(&mut self, value: u16) {
+        self.n = value & 0x8000 != 0;
+        self.z = value == 0;
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cpu {
+    pub a: u16,
+    pub x: u16,
+    pub sp: u16,
+    pub pc: u16,
+    pub flags: Flags,
+}
+
+impl Default for Cpu {
+    fn default() -> Self {
+        Self {
+            a: 0,
+            x: 0,
+            sp: INITIAL_STACK_POINTER,
+            pc: 0,
+            flags: Flags::default(),
+        }
+    }
+}
+
+/// Control transfer performed by the last executed instruction, for
+/// debuggers that track the call stack.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transfer {
+    None,
+    Call { return_address: u16 },
+    Return,
+}
+
+/// Pep/8 machine running a program loaded at address 0.
+///
+/// The trap instructions (`NOPn`, `NOP`, `DECI`, `DECO` and `STRO`) are
+/// executed directly rather than through an operating system, so no OS image
+/// needs to be loaded.
+pub struct Simulator {
+    pub cpu: Cpu,
+    pub memory: Vec<u8>,
+    input: VecDeque<u8>,
+    output: Vec<u8>,
+    halted: bool,
+}
+
+impl Simulator {
+    pub fn new(program: &[u8], input: &[u8]) -> Result<Self, Error> {
+        Self::load(program, 0, input)
+    }
+
+    /// Loads the program at `origin` and starts it there.
+    pub fn load(program: &[u8], origin: u16, input: &[u8]) -> Result<Self, Error> {
+        if origin as usize + program.len() > MEMORY_SIZE {
+            return Err(Error::ProgramTooLarge(program.len()));
+        }
+
+        let mut memory = vec![0; MEMORY_SIZE];
+
+        memory[origin as usize..origin as usize + program.len()].copy_from_slice(program);
+
+        Ok(Self {
+            cpu: Cpu {
+                pc: origin,
+                ..Cpu::default()
+            },
+            memory,
+            input: input.iter().copied().collect(),
+            output: vec![],
+            halted: false,
+        })
+    }
+
+    pub fn halted(&self) -> bool {
+        self.halted
+    }
+
+    /// Returns the output written since the last call.
+    pub fn take_output(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.output)
+    }
+
+    pub fn read_byte(&self, address: u16) -> u8 {
+        self.memory[address as usize]
+    }
+
+    pub fn read_word(&self, address: u16) -> u16 {
+        u16::from_be_bytes([
+            self.memory[address as usize],
+            self.memory[address.wrapping_add(1) as usize],
+        ])
+    }
+
+    pub fn write_byte(&mut self, address: u16, value: u8) {
+        self.memory[address as usize] = value;
+    }
+
+    pub fn write_word(&mut self, address: u16, value: u16) {
+        let [high, low] = value.to_be_bytes();
+
+        self.memory[address as usize] = high;
+        self.memory[address.wrapping_add(1) as usize] = low;
+    }
+
+    /// Address of the operand for the 3-bit addressing mode field `mode`.
+    fn operand_address(&self, mode: u8, specifier: u16) -> u16 {
+        let cpu = &self.cpu;
+
+        match mode {
+            0b001 => specifier,
+            0b010 => self.read_word(specifier),
+            0b011 => cpu.sp.wrapping_add(specifier),
+            0b100 => self.read_word(cpu.sp.wrapping_add(specifier)),
+            0b101 => specifier.wrapping_add(cpu.x),
+            0b110 => cpu.sp.wrapping_add(specifier).wrapping_add(cpu.x),
+            _ => self
+                .read_word(cpu.sp.wrapping_add(specifier))
+                .wrapping_add(cpu.x),
+        }
+    }
+
+    fn operand_word(&self, mode: u8, specifier: u16) -> u16 {
+        match mode {
+            0b000 => specifier,
+            _ => self.read_word(self.operand_address(mode, specifier)),
+        }
+    }
+
+    fn operand_byte(&self, mode: u8, specifier: u16) -> u8 {
+        match mode {
+            0b000 => specifier as u8,
+            _ => self.read_byte(self.operand_address(mode, specifier)),
+        }
+    }
+
+    fn register(&mut self, specifier: u8) -> &mut u16 {
+        match specifier & 0b1000 {
+            0 => &mut self.cpu.a,
+            _ => &mut self.cpu.x,
+        }
+    }
+
+    fn unary_register(&mut self, specifier: u8) -> &mut u16 {
+        match specifier & 1 {
+            0 => &mut self.cpu.a,
+            _ => &mut self.cpu.x,
+        }
+    }
+
+    /// Adds with the carry and overflow semantics of the Pep/8 adder.
+    fn add(&mut self, a: u16, b: u16, carry_in: bool) -> u16 {
+        let sum = a as u32 + b as u32 + carry_in as u32;
+        let result = sum as u16;
+
+        self.cpu.flags.c = sum > 0xFFFF;
+        self.cpu.flags.v = (a ^ result) & (b ^ result) & 0x8000 != 0;
+        self.cpu.flags.set_nz(result);
+
+        result
+    }
+
+    fn push_word(&mut self, value: u16) {
+        self.cpu.sp = self.cpu.sp.wrapping_sub(2);
+        self.write_word(self.cpu.sp, value);
+    }
+
+    fn read_input_byte(&mut self) -> Result<u8, Error> {
+        self.input.pop_front().ok_or(Error::EndOfInput)
+    }
+
+    fn read_decimal(&mut self) -> Result<u16, Error> {
+        while matches!(self.input.front(), Some(byte) if byte.is_ascii_whitespace()) {
+            self.input.pop_front();
+        }
+
+        let mut text = String::new();
+
+        if let Some(sign @ (b'-' | b'+')) = self.input.front().copied() {
+            text.push(sign as char);
+            self.input.pop_front();
+        }
+
+        while let Some(digit) = self.input.front().copied().filter(u8::is_ascii_digit) {
+            text.push(digit as char);
+            self.input.pop_front();
+        }
+
+        if text.is_empty() && self.input.is_empty() {
+            return Err(Error::EndOfInput);
+        }
+
+        match text.parse::<i32>() {
+            Ok(value @ -32768..=32767) => {
+                self.cpu.flags.v = false;
+                Ok(value as u16)
+            }
+            Ok(value) => {
+                self.cpu.flags.v = true;
+                Ok(value as u16)
+            }
+            Err(_) => Err(Error::InvalidDecimalInput),
+        }
+    }
+
+    /// Executes the instruction at the program counter.
+    pub fn step(&mut self) -> Result<Transfer, Error> {
+        if self.halted {
+            return Ok(Transfer::None);
+        }
+
+        let address = self.cpu.pc;
+        let specifier = self.read_byte(address);
+        let unary =
+            isa::decode(specifier, Target::Pep8).is_none_or(|(opcode, _)| opcode.byte_size() == 1);
+        let operand = if unary {
+            self.cpu.pc = address.wrapping_add(1);
+            0
+        } else {
+            self.cpu.pc = address.wrapping_add(3);
+            self.read_word(address.wrapping_add(1))
+        };
+        let mode = specifier & 0b111;
+        let mut transfer = Transfer::None;
+
+        match specifier {
+            0b00000000 => self.halted = true,
+            0b00000001 => return Err(Error::IllegalInstruction(specifier, address)),
+            0b00000010 => self.cpu.a = self.cpu.sp,
+            0b00000011 => self.cpu.a = self.cpu.flags.as_u16(),
+            0b00000100..=0b00010111 => {
+                let target = match specifier & 1 {
+                    0 => operand,
+                    _ => self.read_word(operand.wrapping_add(self.cpu.x)),
+                };
+                let flags = self.cpu.flags;
+                let taken = match specifier >> 1 {
+                    0b0000010 | 0b0001011 => true,
+                    0b0000011 => flags.n || flags.z,
+                    0b0000100 => flags.n,
+                    0b0000101 => flags.z,
+                    0b0000110 => !flags.z,
+                    0b0000111 => !flags.n,
+                    0b0001000 => !flags.n && !flags.z,
+                    0b0001001 => flags.v,
+                    _ => flags.c,
+                };
+
+                if specifier >> 1 == 0b0001011 {
+                    self.push_word(self.cpu.pc);
+                    transfer = Transfer::Call {
+                        return_address: self.cpu.pc,
+                    };
+                }
+
+                if taken {
+                    self.cpu.pc = target;
+                }
+            }
+            0b00011000..=0b00100011 => {
+                let mut flags = self.cpu.flags;
+                let register = self.unary_register(specifier);
+                let value = *register;
+
+                *register = match specifier >> 1 {
+                    0b0001100 => !value,
+                    0b0001101 => {
+                        flags.v = value == 0x8000;
+                        value.wrapping_neg()
+                    }
+                    0b0001110 => {
+                        flags.c = value & 0x8000 != 0;
+                        flags.v = (value ^ (value << 1)) & 0x8000 != 0;
+                        value << 1
+                    }
+                    0b0001111 => {
+                        flags.c = value & 1 != 0;
+                        ((value as i16) >> 1) as u16
+                    }
+                    0b0010000 => {
+                        let result = value << 1 | flags.c as u16;
+                        flags.c = value & 0x8000 != 0;
+                        result
+                    }
+                    _ => {
+                        let result = value >> 1 | (flags.c as u16) << 15;
+                        flags.c = value & 1 != 0;
+                        result
+                    }
+                };
+
+                if specifier >> 1 < 0b0010000 {
+                    flags.set_nz(*register);
+                }
+
+                self.cpu.flags = flags;
+            }
+            // NOP0 to NOP3 and NOP are traps without a default behaviour.
+            0b00100100..=0b00101111 => {}
+            0b00110000..=0b00110111 => {
+                let target = self.operand_address(mode, operand);
+                let value = self.read_decimal()?;
+
+                self.write_word(target, value);
+                self.cpu.flags.set_nz(value);
+            }
+            0b00111000..=0b00111111 => {
+                let value = self.operand_word(mode, operand) as i16;
+
+                self.output.extend(value.to_string().bytes());
+            }
+            0b01000000..=0b01000111 => {
+                let mut string_address = self.operand_address(mode, operand);
+
+                loop {
+                    let byte = self.read_byte(string_address);
+
+                    if byte == 0 {
+                        break;
+                    }
+
+                    self.output.push(byte);
+                    string_address = string_address.wrapping_add(1);
+                }
+            }
+            0b01001000..=0b01001111 => {
+                let target = self.operand_address(mode, operand);
+                let byte = self.read_input_byte()?;
+
+                self.write_byte(target, byte);
+            }
+            0b01010000..=0b01010111 => {
+                let byte = self.operand_byte(mode, operand);
+
+                self.output.push(byte);
+            }
+            0b01011000..=0b01011111 => {
+                self.cpu.sp = self.cpu.sp.wrapping_add((specifier & 0b111) as u16);
+                self.cpu.pc = self.read_word(self.cpu.sp);
+                self.cpu.sp = self.cpu.sp.wrapping_add(2);
+                transfer = Transfer::Return;
+            }
+            0b01100000..=0b01101111 => {
+                let value = self.operand_word(mode, operand);
+                let sp = self.cpu.sp;
+
+                self.cpu.sp = match specifier & 0b1000 {
+                    0 => self.add(sp, value, false),
+                    _ => self.add(sp, !value, true),
+                };
+            }
+            _ => {
+                let value = self.operand_word(mode, operand);
+                let register_value = *self.register(specifier);
+
+                match specifier >> 4 {
+                    0b0111 => *self.register(specifier) = self.add(register_value, value, false),
+                    0b1000 => *self.register(specifier) = self.add(register_value, !value, true),
+                    0b1001 | 0b1010 => {
+                        let result = match specifier >> 4 {
+                            0b1001 => register_value & value,
+                            _ => register_value | value,
+                        };
+
+                        *self.register(specifier) = result;
+                        self.cpu.flags.set_nz(result);
+                    }
+                    0b1011 => {
+                        self.add(register_value, !value, true);
+                    }
+                    0b1100 => {
+                        *self.register(specifier) = value;
+                        self.cpu.flags.set_nz(value);
+                    }
+                    0b1101 => {
+                        let result =
+                            register_value & 0xFF00 | self.operand_byte(mode, operand) as u16;
+
+                        *self.register(specifier) = result;
+                        self.cpu.flags.set_nz(result);
+                    }
+                    0b1110 => {
+                        let target = self.operand_address(mode, operand);
+
+                        self.write_word(target, register_value);
+                    }
+                    _ => {
+                        let target = self.operand_address(mode, operand);
+
+                        self.write_byte(target, register_value as u8);
+                    }
+                }
+            }
+        }
+
+        Ok(transfer)
+    }
+}
@@ -0,0 +1,222 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    address::{AddrMode, Address, AddressTable},
+    assembler::Assembly,
+    cfg::Graph,
+    diagnostic::Warning,
+    instruction::Instruction,
+    isa::Operation,
+};
+
+/// Value of an immediate operand, or `None` for any other addressing mode.
+fn immediate(address: &Address, address_table: &AddressTable) -> Option<i32> {
+    match address.mode {
+        AddrMode::Immediate => address_table
+            .resolve(address)
+            .map(|value| value.as_u16() as i32),
+        _ => None,
+    }
+}
+
+/// Bytes pushed on the stack by an instruction that stays in the current
+/// subroutine, or `None` if the amount is only known at run time. Calls leave
+/// the depth unchanged since the callee pops its return address.
+fn depth_change(instruction: &Instruction, address_table: &AddressTable) -> Option<i32> {
+    match (instruction.operation, instruction.address()) {
+        (Operation::SUBSP, Some(address)) => immediate(address, address_table),
+        (Operation::ADDSP, Some(address)) => {
+            immediate(address, address_table).map(|amount| -amount)
+        }
+        _ => Some(0),
+    }
+}
+
+/// Tracks the stack depth of one subroutine, relative to its return address,
+/// along every path from its entry.
+struct Analysis<'a, 'b> {
+    graph: &'b Graph<'a>,
+    assembly: &'a Assembly,
+    name: String,
+    /// Whether the routine is entered with `CALL` and left with `RETn`,
+    /// rather than being the main program.
+    called: bool,
+    warnings: Vec<Warning>,
+}
+
+impl Analysis<'_, '_> {
+    fn warn(&mut self, index: usize, message: String) {
+        let line = self.assembly.statement_lines[index].line();
+
+        self.warnings.push(Warning::new(line, message));
+    }
+
+    fn analyze(mut self, entry: usize) -> Vec<Warning> {
+        let address_table = &self.assembly.address_table;
+        let mut depths = HashMap::from([(entry, 0)]);
+        let mut pending = vec![entry];
+        let mut reported = HashSet::new();
+
+        while let Some(index) = pending.pop() {
+            let depth = depths[&index];
+            let Some(instruction) = self.graph.instruction(index) else {
+                continue;
+            };
+
+            if let (Operation::RETn, Some(count), true) =
+                (instruction.operation, instruction.count, self.called)
+            {
+                let count = count as i32;
+
+                if depth != count {
+                    self.warn(
+                        index,
+                        format!(
+                            "{} returns with {depth} bytes of locals on the stack but RET{count} pops {count}",
+                            self.name
+                        ),
+                    );
+                }
+
+                continue;
+            }
+
+            let Some(change) = depth_change(instruction, address_table) else {
+                continue;
+            };
+            let depth = depth + change;
+
+            if depth < 0 && self.called {
+                self.warn(index, format!("{} pops past its return address", self.name));
+                continue;
+            }
+
+            for successor in self.graph.successors(index) {
+                match depths.get(&successor) {
+                    None => {
+                        depths.insert(successor, depth);
+                        pending.push(successor);
+                    }
+                    Some(&other) if other != depth && reported.insert(successor) => self.warn(
+                        successor,
+                        format!(
+                            "stack depth in {} is {other} bytes on one path and {depth} on another",
+                            self.name
+                        ),
+                    ),
+                    Some(_) => {}
+                }
+            }
+        }
+
+        self.warnings
+    }
+}
+
+/// Reports the paths through the main program and each called subroutine on
+/// which `SUBSP` and `ADDSP` leave the stack unbalanced, or on which `RETn`
+/// does not pop exactly the locals allocated by the subroutine.
+///
+/// Only amounts given as immediate operands are tracked. A path stops being
+/// followed at the first `SUBSP` or `ADDSP` with any other addressing mode.
+pub fn check(assembly: &Assembly) -> Vec<Warning> {
+    let graph = Graph::new(assembly);
+    let mut warnings = vec![];
+
+    for routine in graph.routines() {
+        let analysis = Analysis {
+            graph: &graph,
+            assembly,
+            name: match routine.called {
+                true => format!("subroutine {}", routine.name),
+                false => String::from("main program"),
+            },
+            called: routine.called,
+            warnings: vec![],
+        };
+
+        warnings.extend(analysis.analyze(routine.entry));
+    }
+
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        assembler::{assemble, Options},
+        lexer::parse_source,
+    };
+
+    fn warnings(source: &str) -> Vec<String> {
+        let assembly = assemble(parse_source(source).unwrap(), &Options::default()).unwrap();
+
+        check(&assembly)
+            .iter()
+            .map(|warning| format!("{}: {}", warning.line, warning.message))
+            .collect()
+    }
+
+    #[test]
+    fn accepts_balanced_subroutines() {
+        assert_eq!(
+            warnings(
+                "         CALL    sub
+         STOP
+sub:     SUBSP   4,i
+         ADDSP   2,i
+         RET2
+         .END
+"
+            ),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn reports_popping_past_the_return_address() {
+        assert_eq!(
+            warnings(
+                "         CALL    sub
+         STOP
+sub:     SUBSP   2,i
+         ADDSP   4,i
+         RET0
+         .END
+"
+            ),
+            ["4: subroutine sub pops past its return address"]
+        );
+    }
+
+    #[test]
+    fn reports_a_return_popping_the_wrong_amount() {
+        assert_eq!(
+            warnings(
+                "         CALL    sub
+         STOP
+sub:     SUBSP   4,i
+         RET2
+         .END
+"
+            ),
+            ["4: subroutine sub returns with 4 bytes of locals on the stack but RET2 pops 2"]
+        );
+    }
+
+    #[test]
+    fn reports_paths_merging_at_different_depths() {
+        assert_eq!(
+            warnings(
+                "         LDA     0,i
+         BREQ    skip
+         SUBSP   2,i
+skip:    STOP
+         .END
+"
+            ),
+            ["4: stack depth in main program is 0 bytes on one path and 2 on another"]
+        );
+    }
+}
@@ -0,0 +1,98 @@
+/// Number of single-character insertions, deletions, substitutions and
+/// adjacent transpositions needed to turn `a` into `b`.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+
+    for (j, distance) in distances[0].iter_mut().enumerate() {
+        *distance = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+
+            distances[i][j] = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + cost);
+
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distances[i][j] = distances[i][j].min(distances[i - 2][j - 2] + 1);
+            }
+        }
+    }
+
+    distances[a.len()][b.len()]
+}
+
+fn common_suffix_length(a: &str, b: &str) -> usize {
+    a.chars()
+        .rev()
+        .zip(b.chars().rev())
+        .take_while(|(a, b)| a == b)
+        .count()
+}
+
+/// Returns the candidate closest to `word`, ignoring case, if it is close
+/// enough to be a plausible typo.
+///
+/// Ties are broken in favour of the candidate sharing the longest suffix with
+/// `word`, so that a register suffix such as the `X` of `LDAX` is kept.
+pub fn suggest<'a, I>(word: &str, candidates: I) -> Option<&'a str>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let word = word.to_uppercase();
+    let max_distance = (word.chars().count() / 3).max(1);
+
+    candidates
+        .into_iter()
+        .map(|candidate| {
+            let upper_candidate = candidate.to_uppercase();
+
+            (
+                edit_distance(&word, &upper_candidate),
+                common_suffix_length(&word, &upper_candidate),
+                candidate,
+            )
+        })
+        .filter(|(distance, _, _)| *distance <= max_distance)
+        .min_by_key(|(distance, suffix_length, _)| (*distance, std::cmp::Reverse(*suffix_length)))
+        .map(|(_, _, candidate)| candidate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_edits_and_transpositions() {
+        assert_eq!(edit_distance("main", "main"), 0);
+        assert_eq!(edit_distance("mian", "main"), 1);
+        assert_eq!(edit_distance("LDAX", "LDX"), 1);
+        assert_eq!(edit_distance("STOP", "STP"), 1);
+        assert_eq!(edit_distance("", "ADDA"), 4);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn suggests_only_close_candidates() {
+        let mnemonics = ["LDA", "LDX", "LDBYTEX", "STOP", "DECO"];
+
+        assert_eq!(suggest("LDAX", mnemonics), Some("LDX"));
+        assert_eq!(suggest("stpo", mnemonics), Some("STOP"));
+        assert_eq!(suggest("mian", ["msg", "main", "num"]), Some("main"));
+        assert_eq!(suggest("LDAX", ["STOP", "DECO"]), None);
+        assert_eq!(suggest("counter", ["main", "num"]), None);
+        // Words of up to five characters may be one edit away, longer ones
+        // one edit per three characters.
+        assert_eq!(suggest("ab", ["xy"]), None);
+        assert_eq!(suggest("abcdef", ["abxyef"]), Some("abxyef"));
+        assert_eq!(suggest("abcdef", ["axyzef"]), None);
+    }
+}
@@ -0,0 +1,250 @@
+use std::{collections::HashMap, io::Write};
+
+use serde::Serialize;
+
+use crate::{
+    address::{AddressTable, SymbolDefinition, SymbolKind},
+    assembler::{Assembly, Statement},
+};
+
+#[derive(Serialize)]
+struct SymbolRecord<'a> {
+    name: &'a str,
+    value: u16,
+    kind: SymbolKind,
+    line: usize,
+    references: &'a [usize],
+}
+
+/// Symbols defined by the source, sorted by name. Those predefined by the
+/// target are left out.
+fn symbols(address_table: &AddressTable) -> impl Iterator<Item = (&String, &SymbolDefinition)> {
+    address_table
+        .iter()
+        .filter(|(_, definition)| !definition.is_predefined())
+}
+
+/// Writes a ruled text table in the style of the Pep/8 IDE, with one symbol
+/// per row. Each row starts with the symbol's name, value, kind and line,
+/// followed by the `extra` columns, whose headings are `headings` and which
+/// take `extra_width` characters before the last one.
+fn write_table<W: Write>(
+    address_table: &AddressTable,
+    headings: &str,
+    extra_width: usize,
+    extra: impl Fn(&String, &SymbolDefinition) -> String,
+    writer: &mut W,
+) -> std::io::Result<()> {
+    let name_width = symbols(address_table)
+        .map(|(name, _)| name.len())
+        .max()
+        .unwrap_or(0)
+        .max("Symbol".len());
+    let rule = "-".repeat(name_width + 30 + extra_width);
+
+    writeln!(writer, "{rule}")?;
+    writeln!(
+        writer,
+        "{:name_width$}  Value  Kind      Line  {headings}",
+        "Symbol"
+    )?;
+    writeln!(writer, "{rule}")?;
+
+    for (name, definition) in symbols(address_table) {
+        let row = format!(
+            "{name:name_width$}  {:04X}   {:8}  {:4}  {}",
+            definition.value.as_u16(),
+            definition.kind.to_string(),
+            definition.line,
+            extra(name, definition)
+        );
+
+        writeln!(writer, "{}", row.trim_end())?;
+    }
+
+    writeln!(writer, "{rule}")
+}
+
+/// Writes the symbol table as a ruled text table in the style of the Pep/8
+/// IDE, with one symbol per row.
+pub fn write_text<W: Write>(address_table: &AddressTable, writer: &mut W) -> std::io::Result<()> {
+    write_table(
+        address_table,
+        "References",
+        10,
+        |_, definition| {
+            let references: Vec<String> = definition
+                .references
+                .iter()
+                .map(|line| line.to_string())
+                .collect();
+
+            references.join(", ")
+        },
+        writer,
+    )
+}
+
+/// Writes the symbol table as a JSON array of symbol objects.
+pub fn write_json<W: Write>(
+    address_table: &AddressTable,
+    writer: &mut W,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let records: Vec<SymbolRecord> = symbols(address_table)
+        .map(|(name, definition)| SymbolRecord {
+            name,
+            value: definition.value.as_u16(),
+            kind: definition.kind,
+            line: definition.line,
+            references: &definition.references,
+        })
+        .collect();
+
+    serde_json::to_writer_pretty(&mut *writer, &records)?;
+    writeln!(writer)?;
+
+    Ok(())
+}
+
+/// Writes a cross-reference of every symbol: its definition, each line using
+/// it with the addressing mode of the use, and the distinct modes used, so
+/// that symbols accessed inconsistently stand out.
+pub fn write_cross_reference<W: Write>(assembly: &Assembly, writer: &mut W) -> std::io::Result<()> {
+    let mut uses: HashMap<&String, Vec<(usize, String)>> = HashMap::new();
+
+    for statement_line in &assembly.statement_lines {
+        let usage = match statement_line.statement() {
+            Statement::Instruction(instruction) => instruction
+                .address()
+                .zip(statement_line.statement().symbol_reference())
+                .map(|(address, (_, symbol))| (symbol, address.mode.to_string())),
+            Statement::DotCommand(_) => statement_line
+                .statement()
+                .symbol_reference()
+                .map(|(_, symbol)| (symbol, String::from(".ADDRSS"))),
+        };
+
+        if let Some((symbol, mode)) = usage {
+            uses.entry(symbol)
+                .or_default()
+                .push((statement_line.line(), mode));
+        }
+    }
+
+    write_table(
+        &assembly.address_table,
+        "Modes         References",
+        30,
+        |name, _| {
+            let uses = uses.get(name).map(Vec::as_slice).unwrap_or_default();
+            let mut modes: Vec<&str> = uses.iter().map(|(_, mode)| mode.as_str()).collect();
+
+            modes.sort();
+            modes.dedup();
+
+            let references: Vec<String> = uses
+                .iter()
+                .map(|(line, mode)| format!("{line} ({mode})"))
+                .collect();
+
+            format!("{:12}  {}", modes.join(", "), references.join(", "))
+        },
+        writer,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        assembler::{assemble, Options},
+        lexer::parse_source,
+        target::Target,
+    };
+
+    const SOURCE: &str = "         BR      main
+size:    .EQUATE 2
+num:     .BLOCK  2
+main:    LDBA    charIn,d
+         STBA    num,d
+         LDWA    size,i
+         STOP
+         .END
+";
+
+    fn assembly() -> Assembly {
+        let options = Options {
+            target: Target::Pep9,
+            ..Options::default()
+        };
+
+        assemble(parse_source(SOURCE).unwrap(), &options).unwrap()
+    }
+
+    #[test]
+    fn writes_a_text_table_without_predefined_symbols() {
+        let mut text = vec![];
+
+        write_text(&assembly().address_table, &mut text).unwrap();
+
+        assert_eq!(
+            String::from_utf8(text).unwrap(),
+            "----------------------------------------------
+Symbol  Value  Kind      Line  References
+----------------------------------------------
+main    0005   code         4  1
+num     0003   data         3  5
+size    0002   equate       2  6
+----------------------------------------------
+"
+        );
+    }
+
+    #[test]
+    fn writes_json_records_without_predefined_symbols() {
+        let mut json = vec![];
+
+        write_json(&assembly().address_table, &mut json).unwrap();
+
+        let records: serde_json::Value = serde_json::from_slice(&json).unwrap();
+
+        assert_eq!(
+            records,
+            serde_json::json!([
+                {"name": "main", "value": 5, "kind": "code_label", "line": 4, "references": [1]},
+                {"name": "num", "value": 3, "kind": "data_label", "line": 3, "references": [5]},
+                {"name": "size", "value": 2, "kind": "equate", "line": 2, "references": [6]},
+            ])
+        );
+    }
+
+    #[test]
+    fn writes_the_definition_and_uses_of_each_symbol() {
+        let mut text = vec![];
+
+        let source = "         BR      main
+num:     .BLOCK  2
+ptr:     .ADDRSS num
+main:    LDA     num,d
+         STA     num,n
+         LDX     ptr,d
+         STOP
+         .END
+";
+        let assembly = assemble(parse_source(source).unwrap(), &Options::default()).unwrap();
+
+        write_cross_reference(&assembly, &mut text).unwrap();
+
+        assert_eq!(
+            String::from_utf8(text).unwrap(),
+            "------------------------------------------------------------------
+Symbol  Value  Kind      Line  Modes         References
+------------------------------------------------------------------
+main    0007   code         4  i             1 (i)
+num     0003   data         2  .ADDRSS, d, n  3 (.ADDRSS), 4 (d), 5 (n)
+ptr     0005   data         3  d             6 (d)
+------------------------------------------------------------------
+"
+        );
+    }
+}
@@ -0,0 +1,86 @@
+use std::fmt::Display;
+
+use clap::ValueEnum;
+
+use crate::{
+    address::AddrMode,
+    dotcommand::{DOT_COMMANDS, PEP10_DOT_COMMANDS},
+    isa::{self, Opcode},
+};
+
+/// Machine the assembler generates code for.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Target {
+    #[default]
+    Pep8,
+    Pep9,
+    Pep10,
+}
+
+impl Display for Target {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Pep8 => write!(f, "Pep/8"),
+            Self::Pep9 => write!(f, "Pep/9"),
+            Self::Pep10 => write!(f, "Pep/10"),
+        }
+    }
+}
+
+impl Target {
+    /// The target's instruction set.
+    pub fn opcodes(&self) -> &'static [Opcode] {
+        match self {
+            Self::Pep8 => isa::PEP8,
+            Self::Pep9 => isa::PEP9,
+            Self::Pep10 => isa::PEP10,
+        }
+    }
+
+    /// Mnemonics of the target's instruction set.
+    pub fn mnemonics(&self) -> impl Iterator<Item = &'static str> {
+        self.opcodes().iter().map(|opcode| opcode.mnemonic)
+    }
+
+    /// Spelling of an addressing mode in the target's assembly language.
+    pub fn mode_name(&self, mode: &AddrMode) -> String {
+        match (self, mode) {
+            (Self::Pep9 | Self::Pep10, AddrMode::StackIndexedDeferred) => String::from("sfx"),
+            _ => mode.to_string(),
+        }
+    }
+
+    /// Dot commands of the target's assembler.
+    pub fn dot_commands(&self) -> &'static [&'static str] {
+        match self {
+            Self::Pep8 | Self::Pep9 => DOT_COMMANDS,
+            Self::Pep10 => PEP10_DOT_COMMANDS,
+        }
+    }
+
+    /// Memory-mapped I/O ports defined by the target's operating system,
+    /// which programs use without defining them.
+    pub fn io_ports(&self) -> &'static [(&'static str, u16)] {
+        match self {
+            Self::Pep8 => &[],
+            Self::Pep9 => &[("charIn", 0xFC15), ("charOut", 0xFC16)],
+            Self::Pep10 => &[],
+        }
+    }
+
+    /// System calls of the target's operating system, along with the number
+    /// loaded into the T register before `SCALL`, in the order the standard
+    /// operating system declares them.
+    pub fn system_calls(&self) -> &'static [(&'static str, u16)] {
+        match self {
+            Self::Pep8 | Self::Pep9 => &[],
+            Self::Pep10 => &[
+                ("DECI", 0),
+                ("DECO", 1),
+                ("HEXO", 2),
+                ("STRO", 3),
+                ("SNOP", 4),
+            ],
+        }
+    }
+}
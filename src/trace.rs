@@ -0,0 +1,554 @@
+use std::collections::HashMap;
+
+use crate::{
+    address::{AddrMode, AddressTable, SymbolKind},
+    assembler::{Assembly, Statement, StatementLine},
+    diagnostic::Warning,
+    dotcommand::DotCommand,
+    isa::Operation,
+    lexer::{TraceFormat, TraceTag},
+    simulator::{self, Simulator, Transfer},
+};
+
+/// Format tag of a line, if its comment has exactly one.
+fn format_tag(statement_line: &StatementLine) -> Option<&TraceTag> {
+    let mut formats = statement_line
+        .trace_tags()
+        .iter()
+        .filter(|tag| matches!(tag, TraceTag::Format { .. }));
+
+    match (formats.next(), formats.next()) {
+        (Some(tag), None) => Some(tag),
+        _ => None,
+    }
+}
+
+/// Bytes described by the format tag of each symbol defined on a line with
+/// exactly one format tag.
+fn symbol_sizes(statement_lines: &[StatementLine]) -> HashMap<&String, usize> {
+    statement_lines
+        .iter()
+        .filter_map(|statement_line| {
+            Some((
+                statement_line.label()?,
+                format_tag(statement_line)?.byte_size()?,
+            ))
+        })
+        .collect()
+}
+
+/// Checks the trace tags the way the Pep/8 IDE does: tags naming symbols
+/// without a format, and tags describing a different number of bytes than
+/// reserved by `.BLOCK`, `.BYTE` or `.WORD` or than allocated by `SUBSP` and
+/// deallocated by `ADDSP`.
+///
+/// Like the IDE, only format tags on lines reserving memory or defining an
+/// `.EQUATE`, and symbol tags on `SUBSP`, `ADDSP` and `CALL`, are looked at.
+/// Any other `#` in a comment is ordinary text.
+pub fn check(statement_lines: &[StatementLine], address_table: &AddressTable) -> Vec<Warning> {
+    let sizes = symbol_sizes(statement_lines);
+    let mut warnings = vec![];
+
+    for statement_line in statement_lines {
+        let line = statement_line.line();
+        let formats: Vec<&TraceTag> = statement_line
+            .trace_tags()
+            .iter()
+            .filter(|tag| matches!(tag, TraceTag::Format { .. }))
+            .collect();
+        let symbols: Vec<&String> = statement_line
+            .trace_tags()
+            .iter()
+            .filter_map(|tag| match tag {
+                TraceTag::Symbol(symbol) => Some(symbol),
+                TraceTag::Format { .. } => None,
+            })
+            .collect();
+
+        let (total, size, what) = match statement_line.statement() {
+            Statement::DotCommand(DotCommand::EQUATE(_)) => {
+                if formats.len() > 1 {
+                    warnings.push(Warning::new(
+                        line,
+                        String::from(".EQUATE must have a single format trace tag such as #2d"),
+                    ));
+                }
+
+                continue;
+            }
+            Statement::DotCommand(command @ DotCommand::BLOCK(_))
+            | Statement::DotCommand(command @ DotCommand::BYTE(_))
+            | Statement::DotCommand(command @ DotCommand::WORD(_)) => {
+                if formats.is_empty() {
+                    continue;
+                }
+
+                let name = match command {
+                    DotCommand::BLOCK(_) => ".BLOCK",
+                    DotCommand::BYTE(_) => ".BYTE",
+                    _ => ".WORD",
+                };
+                let total = formats.iter().filter_map(|tag| tag.byte_size()).sum();
+
+                (
+                    Some(total),
+                    Some(command.byte_size()),
+                    format!("{name} reserves"),
+                )
+            }
+            Statement::Instruction(instruction)
+                if matches!(
+                    instruction.operation,
+                    Operation::SUBSP | Operation::ADDSP | Operation::CALL
+                ) =>
+            {
+                if symbols.is_empty() {
+                    continue;
+                }
+
+                let mut total = Some(0);
+
+                for symbol in symbols {
+                    let size = sizes.get(symbol).copied();
+
+                    if size.is_none() {
+                        warnings.push(Warning::new(
+                            line,
+                            format!("trace tag #{symbol} does not name a symbol with a format tag"),
+                        ));
+                    }
+
+                    total = total.zip(size).map(|(total, size)| total + size);
+                }
+
+                let verb = match instruction.operation {
+                    Operation::SUBSP => "SUBSP allocates",
+                    Operation::ADDSP => "ADDSP deallocates",
+                    // The heap cells of a `CALL` are allocated by the callee.
+                    _ => continue,
+                };
+                let amount = instruction
+                    .address()
+                    .filter(|address| address.mode == AddrMode::Immediate)
+                    .and_then(|address| address_table.resolve(address))
+                    .map(|amount| amount.as_u16() as usize);
+
+                (total, amount, String::from(verb))
+            }
+            _ => continue,
+        };
+
+        if let (Some(total), Some(size)) = (total, size) {
+            if size != total {
+                warnings.push(Warning::new(
+                    line,
+                    format!("trace tags describe {total} bytes but {what} {size}"),
+                ));
+            }
+        }
+    }
+
+    warnings
+}
+
+/// Named memory drawn by the memory trace, holding `count` values of `size`
+/// bytes each.
+#[derive(Clone, Debug)]
+pub struct Cell {
+    pub name: String,
+    pub address: u16,
+    pub size: u8,
+    pub format: TraceFormat,
+    pub count: usize,
+}
+
+impl Cell {
+    fn new(name: String, address: u16, tag: &TraceTag) -> Option<Self> {
+        match *tag {
+            TraceTag::Format {
+                size,
+                format,
+                count,
+            } => Some(Self {
+                name,
+                address,
+                size,
+                format,
+                count,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Value of the cell formatted like the Pep/8 IDE, with arrays in
+    /// brackets.
+    pub fn value(&self, simulator: &Simulator) -> String {
+        let values: Vec<_> = (0..self.count)
+            .map(|index| {
+                let address = self
+                    .address
+                    .wrapping_add((index * self.size as usize) as u16);
+                let value = match self.size {
+                    1 => simulator.read_byte(address) as u16,
+                    _ => simulator.read_word(address),
+                };
+
+                match (self.format, self.size) {
+                    (TraceFormat::Decimal, 1) => (value as u8 as i8).to_string(),
+                    (TraceFormat::Decimal, _) => (value as i16).to_string(),
+                    (TraceFormat::Char, _) => format!("{:?}", value as u8 as char),
+                    (TraceFormat::Hex, 1) => format!("0x{value:02X}"),
+                    (TraceFormat::Hex, _) => format!("0x{value:04X}"),
+                }
+            })
+            .collect();
+
+        match values.as_slice() {
+            [value] => value.clone(),
+            _ => format!("[{}]", values.join(", ")),
+        }
+    }
+}
+
+/// Cells pushed by the main program or by one subroutine call, the return
+/// address being the first cell of a call's frame.
+#[derive(Clone, Debug)]
+pub struct Frame {
+    pub name: String,
+    pub cells: Vec<Cell>,
+}
+
+/// Symbolic view of the globals, run-time stack and heap of a program running
+/// in the simulator, named and formatted by its trace tags.
+///
+/// The symbol tags of a `SUBSP` name the cells it allocates, the first tag
+/// being pushed first and so lying at the highest address. The symbol tags of
+/// a `CALL` name the cells allocated on the heap by the subroutine, whose
+/// address it returns in the index register, as `new` does in the Pep/8 book.
+pub struct MemoryTrace {
+    globals: Vec<Cell>,
+    frames: Vec<Frame>,
+    heap: Vec<Cell>,
+    /// Named format tags listed by each `SUBSP` and `CALL`, by address.
+    layouts: HashMap<u16, Vec<(String, TraceTag)>>,
+    /// Code labels by address, to name frames.
+    routines: HashMap<u16, String>,
+    /// Return address and layout of the calls allocating heap cells.
+    allocations: Vec<(u16, Vec<(String, TraceTag)>)>,
+}
+
+impl MemoryTrace {
+    pub fn new(assembly: &Assembly) -> Self {
+        let statement_lines = &assembly.statement_lines;
+        let formats: HashMap<&String, &TraceTag> = statement_lines
+            .iter()
+            .filter_map(|statement_line| {
+                Some((statement_line.label()?, format_tag(statement_line)?))
+            })
+            .collect();
+        let mut globals = vec![];
+        let mut layouts = HashMap::new();
+
+        for statement_line in statement_lines {
+            let address = statement_line.address();
+
+            match (statement_line.statement(), statement_line.label()) {
+                (Statement::DotCommand(DotCommand::EQUATE(_)), _) => {}
+                (Statement::DotCommand(_), Some(label)) => {
+                    if let Some(tag) = formats.get(label) {
+                        globals.extend(Cell::new(label.clone(), address, tag));
+                    }
+                }
+                (Statement::Instruction(instruction), _)
+                    if matches!(instruction.operation, Operation::SUBSP | Operation::CALL) =>
+                {
+                    let layout: Option<Vec<_>> = statement_line
+                        .trace_tags()
+                        .iter()
+                        .filter_map(|tag| match tag {
+                            TraceTag::Symbol(symbol) => Some(symbol),
+                            TraceTag::Format { .. } => None,
+                        })
+                        .map(|symbol| {
+                            formats
+                                .get(symbol)
+                                .map(|format| (symbol.clone(), (*format).clone()))
+                        })
+                        .collect();
+
+                    if let Some(layout) = layout.filter(|layout| !layout.is_empty()) {
+                        layouts.insert(address, layout);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let routines = assembly
+            .address_table
+            .iter()
+            .filter(|(_, definition)| definition.kind == SymbolKind::CodeLabel)
+            .map(|(name, definition)| (definition.value.as_u16(), name.clone()))
+            .collect();
+
+        Self {
+            globals,
+            frames: vec![Frame {
+                name: String::from("main"),
+                cells: vec![],
+            }],
+            heap: vec![],
+            layouts,
+            routines,
+            allocations: vec![],
+        }
+    }
+
+    /// Globals with a format tag, in address order.
+    pub fn globals(&self) -> &[Cell] {
+        &self.globals
+    }
+
+    /// Frames of the run-time stack, from the main program to the innermost
+    /// call.
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    pub fn heap(&self) -> &[Cell] {
+        &self.heap
+    }
+
+    /// Pushes the cells described by `layout` from `top` down, or a single
+    /// unnamed cell if the layout does not cover `size` bytes.
+    fn push_cells(&mut self, layout: Option<&Vec<(String, TraceTag)>>, top: u16, size: u16) {
+        let frame = self.frames.last_mut().expect("main frame is never popped");
+        let covered = layout.is_some_and(|layout| {
+            layout
+                .iter()
+                .map(|(_, tag)| tag.byte_size().unwrap_or_default())
+                .sum::<usize>()
+                == size as usize
+        });
+
+        match layout.filter(|_| covered) {
+            Some(layout) => {
+                let mut address = top;
+
+                for (name, tag) in layout {
+                    address = address.wrapping_sub(tag.byte_size().unwrap_or_default() as u16);
+                    frame.cells.extend(Cell::new(name.clone(), address, tag));
+                }
+            }
+            None => {
+                let address = top.wrapping_sub(size);
+
+                frame.cells.push(Cell {
+                    name: format!("{address:04X}"),
+                    address,
+                    size: 1,
+                    format: TraceFormat::Hex,
+                    count: size as usize,
+                });
+            }
+        }
+    }
+
+    /// Executes one instruction, pushing the cells it allocates and popping
+    /// the cells left above the stack pointer.
+    pub fn step(&mut self, simulator: &mut Simulator) -> Result<Transfer, simulator::Error> {
+        let pc = simulator.cpu.pc;
+        let sp = simulator.cpu.sp;
+        let specifier = simulator.read_byte(pc);
+
+        if let Some(index) = self
+            .allocations
+            .iter()
+            .rposition(|(return_address, _)| *return_address == pc)
+        {
+            let (_, layout) = self.allocations.remove(index);
+            let mut address = simulator.cpu.x;
+
+            for (name, tag) in layout {
+                self.heap.extend(Cell::new(name, address, &tag));
+                address = address.wrapping_add(tag.byte_size().unwrap_or_default() as u16);
+            }
+        }
+
+        let transfer = simulator.step()?;
+        let new_sp = simulator.cpu.sp;
+
+        match transfer {
+            Transfer::Call { return_address } => {
+                let target = simulator.cpu.pc;
+
+                self.frames.push(Frame {
+                    name: self
+                        .routines
+                        .get(&target)
+                        .cloned()
+                        .unwrap_or_else(|| format!("{target:04X}")),
+                    cells: vec![Cell {
+                        name: String::from("retAddr"),
+                        address: new_sp,
+                        size: 2,
+                        format: TraceFormat::Hex,
+                        count: 1,
+                    }],
+                });
+
+                if let Some(layout) = self.layouts.get(&pc) {
+                    self.allocations.push((return_address, layout.clone()));
+                }
+            }
+            // SUBSP, allocating the bytes between the old and new stack
+            // pointers.
+            Transfer::None if specifier & 0b11111000 == 0b01101000 && new_sp < sp => {
+                let layout = self.layouts.get(&pc).cloned();
+
+                self.push_cells(layout.as_ref(), sp, sp - new_sp);
+            }
+            _ => {}
+        }
+
+        while let Some(frame) = self.frames.last_mut() {
+            while frame.cells.last().is_some_and(|cell| cell.address < new_sp) {
+                frame.cells.pop();
+            }
+
+            if frame.cells.is_empty() && self.frames.len() > 1 {
+                self.frames.pop();
+            } else {
+                break;
+            }
+        }
+
+        Ok(transfer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        assembler::{assemble, Options},
+        lexer::parse_source,
+    };
+
+    fn warnings(source: &str) -> Vec<String> {
+        let assembly = assemble(parse_source(source).unwrap(), &Options::default()).unwrap();
+
+        check(&assembly.statement_lines, &assembly.address_table)
+            .iter()
+            .map(|warning| format!("{}: {}", warning.line, warning.message))
+            .collect()
+    }
+
+    #[test]
+    fn checks_tags_against_reserved_and_allocated_bytes() {
+        assert_eq!(
+            warnings(
+                "         BR      main
+arr:     .BLOCK  6           ;#2d4a
+ch:      .WORD   1           ;#1c
+retVal:  .EQUATE 2           ;#2d
+x:       .EQUATE 2           ;#2h
+main:    SUBSP   4,i         ;#retVal #x
+         ADDSP   4,i         ;#retVal #nope
+         LDA     arr,d
+         LDA     ch,d
+         STOP
+         .END
+"
+            ),
+            [
+                "2: trace tags describe 8 bytes but .BLOCK reserves 6",
+                "3: trace tags describe 1 bytes but .WORD reserves 2",
+                "7: trace tag #nope does not name a symbol with a format tag",
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_other_hashes_in_comments() {
+        assert_eq!(
+            warnings(
+                "main:    LDA     1,i         ;print #1 and #note
+         CALL    main        ;#2d
+         STOP                ;see #x
+arr:     .BLOCK  2           ;#3d
+         .END
+"
+            ),
+            Vec::<String>::new()
+        );
+    }
+
+    const PROGRAM: &str = "         BR      main
+glob:    .WORD   7           ;#2d
+letter:  .BYTE   65          ;#1c
+local:   .EQUATE 0           ;#2d
+value:   .EQUATE 0           ;#2h
+next:    .EQUATE 2           ;#2d
+main:    SUBSP   2,i         ;#local
+         LDA     5,i
+         STA     local,s
+         CALL    new         ;#value #next
+         STOP
+new:     LDX     heap,i
+         RET0
+heap:    .WORD   18
+         .WORD   3
+         .END
+";
+
+    /// Name and value of each cell.
+    fn values(cells: &[Cell], simulator: &Simulator) -> Vec<String> {
+        cells
+            .iter()
+            .map(|cell| format!("{} = {}", cell.name, cell.value(simulator)))
+            .collect()
+    }
+
+    #[test]
+    fn draws_globals_frames_and_heap_from_trace_tags() {
+        let assembly = assemble(parse_source(PROGRAM).unwrap(), &Options::default()).unwrap();
+        let mut simulator = Simulator::new(&assembly.byte_code, b"").unwrap();
+        let mut trace = MemoryTrace::new(&assembly);
+        let new = assembly.address_table.get(&String::from("new")).unwrap();
+
+        assert_eq!(
+            values(trace.globals(), &simulator),
+            ["glob = 7", "letter = 'A'"]
+        );
+
+        while simulator.cpu.pc != new.as_u16() {
+            trace.step(&mut simulator).unwrap();
+        }
+
+        let frames: Vec<(&str, Vec<String>)> = trace
+            .frames()
+            .iter()
+            .map(|frame| (frame.name.as_str(), values(&frame.cells, &simulator)))
+            .collect();
+
+        assert_eq!(
+            frames,
+            [
+                ("main", vec![String::from("local = 5")]),
+                ("new", vec![String::from("retAddr = 0x0012")]),
+            ]
+        );
+        assert!(trace.heap().is_empty());
+
+        while !simulator.halted() {
+            trace.step(&mut simulator).unwrap();
+        }
+
+        assert_eq!(trace.frames().len(), 1);
+        assert_eq!(
+            values(trace.heap(), &simulator),
+            ["value = 0x0012", "next = 3"]
+        );
+    }
+}
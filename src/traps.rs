@@ -0,0 +1,285 @@
+use std::{fmt::Display, str::FromStr};
+
+use crate::{
+    address::AddrMode,
+    isa::{self, Opcode, Operand},
+    lexer::{SourceLine, Token},
+    target::Target,
+};
+
+#[derive(Debug)]
+pub enum Error {
+    InvalidDefinition,
+    InvalidAddrMode(String),
+    NotATrap { slot: String, target: Target },
+    MnemonicInUse(String),
+    DuplicateTrap(String),
+    MissingAddrModes(&'static str),
+    UnexpectedAddrModes(&'static str),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidDefinition => write!(
+                f,
+                "trap definitions are written as a mnemonic, a trap slot and its addressing modes"
+            ),
+            Self::InvalidAddrMode(mode) => write!(f, "invalid addressing mode: {mode}"),
+            Self::NotATrap { slot, target } => write!(f, "{slot} is not a {target} trap"),
+            Self::MnemonicInUse(mnemonic) => {
+                write!(f, "{mnemonic} is already an instruction mnemonic")
+            }
+            Self::DuplicateTrap(mnemonic) => write!(f, "trap {mnemonic} is defined more than once"),
+            Self::MissingAddrModes(slot) => {
+                write!(
+                    f,
+                    "{slot} takes an operand, so its traps need addressing modes"
+                )
+            }
+            Self::UnexpectedAddrModes(slot) => {
+                write!(f, "{slot} is unary, so its traps take no addressing modes")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Mnemonic given by an operating system to one of the target's trap
+/// opcodes, such as `MULA` for `NOP`. It is encoded as the trap it replaces,
+/// but accepts its own addressing modes.
+#[derive(Clone, Debug)]
+pub struct Trap {
+    pub mnemonic: String,
+    pub slot: &'static Opcode,
+    /// Addressing modes accepted by the trap, empty if the slot is unary.
+    pub modes: Vec<AddrMode>,
+    /// Target whose spelling of the addressing modes the trap is written in.
+    pub target: Target,
+}
+
+impl Display for Trap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.mnemonic, self.slot.mnemonic)?;
+
+        if !self.modes.is_empty() {
+            let modes: Vec<_> = self
+                .modes
+                .iter()
+                .map(|mode| self.target.mode_name(mode))
+                .collect();
+
+            write!(f, " {}", modes.join(","))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Trap {
+    /// Parses a definition such as `MULA NOP d,n,s`: the new mnemonic, the
+    /// trap it replaces and, if that trap takes an operand, the addressing
+    /// modes allowed.
+    pub fn from_tokens(tokens: &[Token], target: Target) -> Result<Self, Error> {
+        let [Token::Identifier(mnemonic), Token::Identifier(slot), mode_tokens @ ..] = tokens
+        else {
+            return Err(Error::InvalidDefinition);
+        };
+        let mnemonic = mnemonic.to_uppercase();
+        let slot = isa::lookup(slot, target)
+            .filter(|opcode| opcode.trap)
+            .ok_or_else(|| Error::NotATrap {
+                slot: slot.to_uppercase(),
+                target,
+            })?;
+
+        if isa::lookup(&mnemonic, target).is_some() {
+            return Err(Error::MnemonicInUse(mnemonic));
+        }
+
+        if mode_tokens.len() % 2 == 0 && !mode_tokens.is_empty() {
+            return Err(Error::InvalidDefinition);
+        }
+
+        let mut modes = vec![];
+
+        for (index, token) in mode_tokens.iter().enumerate() {
+            match (index % 2, token) {
+                (0, Token::Identifier(mode)) => modes.push(
+                    AddrMode::from_str(mode).map_err(|_| Error::InvalidAddrMode(mode.clone()))?,
+                ),
+                (1, Token::Comma) => {}
+                _ => return Err(Error::InvalidDefinition),
+            }
+        }
+
+        match (slot.operand, modes.is_empty()) {
+            (Operand::None, false) => Err(Error::UnexpectedAddrModes(slot.mnemonic)),
+            (Operand::Short | Operand::Long, true) => Err(Error::MissingAddrModes(slot.mnemonic)),
+            _ => Ok(Self {
+                mnemonic,
+                slot,
+                modes,
+                target,
+            }),
+        }
+    }
+}
+
+fn add(traps: &mut Vec<Trap>, trap: Trap) -> Result<(), Error> {
+    if traps.iter().any(|other| other.mnemonic == trap.mnemonic) {
+        return Err(Error::DuplicateTrap(trap.mnemonic));
+    }
+
+    traps.push(trap);
+
+    Ok(())
+}
+
+/// Reads a trap file, with one definition per line.
+pub fn parse(lines: &[SourceLine], target: Target) -> Result<Vec<Trap>, (usize, Error)> {
+    let mut traps = vec![];
+
+    for line in lines.iter().filter(|line| !line.tokens.is_empty()) {
+        Trap::from_tokens(&line.tokens, target)
+            .and_then(|trap| add(&mut traps, trap))
+            .map_err(|error| (line.number, error))?;
+    }
+
+    Ok(traps)
+}
+
+/// Traps available to a program: those given to the assembler followed by
+/// those defined with `.TRAP` anywhere in the source.
+pub fn collect(
+    lines: &[SourceLine],
+    traps: &[Trap],
+    target: Target,
+) -> Result<Vec<Trap>, (usize, Error)> {
+    let mut traps = traps.to_vec();
+
+    for line in lines {
+        let tokens = match &line.tokens[..] {
+            [Token::Label(_), tokens @ ..] => tokens,
+            tokens => tokens,
+        };
+
+        if let [Token::DotCommand(command), definition @ ..] = tokens {
+            if command.eq_ignore_ascii_case(".TRAP") {
+                Trap::from_tokens(definition, target)
+                    .and_then(|trap| add(&mut traps, trap))
+                    .map_err(|error| (line.number, error))?;
+            }
+        }
+    }
+
+    Ok(traps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        assembler::{assemble, Options},
+        lexer::parse_source,
+    };
+
+    fn parsed(source: &str, target: Target) -> Result<Vec<String>, String> {
+        parse(&parse_source(source).unwrap(), target)
+            .map(|traps| traps.iter().map(Trap::to_string).collect())
+            .map_err(|(line, error)| format!("{line}: {error}"))
+    }
+
+    #[test]
+    fn parses_a_trap_file() {
+        assert_eq!(
+            parsed("mula nop d,n,s\n\nHALT NOP0\n", Target::Pep8).unwrap(),
+            ["MULA NOP d,n,s", "HALT NOP0"]
+        );
+        assert_eq!(
+            parsed("PUSH HEXO i,sfx\n", Target::Pep9).unwrap(),
+            ["PUSH HEXO i,sfx"]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_definitions() {
+        let rejected = |definition: &str, target| {
+            parsed(&format!("HALT NOP0\n{definition}\n"), target).unwrap_err()
+        };
+
+        assert_eq!(
+            rejected("MULA LDA d", Target::Pep8),
+            "2: LDA is not a Pep/8 trap"
+        );
+        assert_eq!(
+            rejected("MULA NOP3", Target::Pep9),
+            "2: NOP3 is not a Pep/9 trap"
+        );
+        assert_eq!(
+            rejected("LDA NOP d", Target::Pep8),
+            "2: LDA is already an instruction mnemonic"
+        );
+        assert_eq!(
+            rejected("HALT NOP1", Target::Pep8),
+            "2: trap HALT is defined more than once"
+        );
+        assert_eq!(
+            rejected("MULA NOP", Target::Pep8),
+            "2: NOP takes an operand, so its traps need addressing modes"
+        );
+        assert_eq!(
+            rejected("STEP NOP1 i", Target::Pep8),
+            "2: NOP1 is unary, so its traps take no addressing modes"
+        );
+        assert_eq!(
+            rejected("MULA NOP d,q", Target::Pep8),
+            "2: invalid addressing mode: q"
+        );
+        assert_eq!(
+            rejected("MULA NOP d,", Target::Pep8),
+            "2: trap definitions are written as a mnemonic, a trap slot and its addressing modes"
+        );
+        assert_eq!(
+            rejected("MULA NOP d n", Target::Pep8),
+            "2: trap definitions are written as a mnemonic, a trap slot and its addressing modes"
+        );
+    }
+
+    #[test]
+    fn collects_given_traps_before_trap_declarations() {
+        let given = parse(&parse_source("HALT NOP0\n").unwrap(), Target::Pep8).unwrap();
+        let source = "main:    MULA    5,d
+         HALT
+         .TRAP   MULA NOP d,x
+         .END
+";
+        let lines = parse_source(source).unwrap();
+        let traps: Vec<String> = collect(&lines, &given, Target::Pep8)
+            .unwrap()
+            .iter()
+            .map(Trap::to_string)
+            .collect();
+
+        assert_eq!(traps, ["HALT NOP0", "MULA NOP d,x"]);
+
+        let redefined = parse_source("         .TRAP   HALT NOP1\n").unwrap();
+        let (line, error) = collect(&redefined, &given, Target::Pep8).unwrap_err();
+
+        assert_eq!(
+            format!("{line}: {error}"),
+            "1: trap HALT is defined more than once"
+        );
+
+        let options = Options {
+            traps: given,
+            ..Options::default()
+        };
+
+        assert_eq!(
+            assemble(lines, &options).unwrap().byte_code,
+            [0x29, 0x00, 0x05, 0x24]
+        );
+    }
+}
@@ -1,17 +1,13 @@
-use std::{fmt::Display, ops::Add};
+use std::fmt::Display;
 
 #[derive(Debug)]
 pub enum Error {
-    InvalidAscii(char),
     InvalidArgumentSize(String),
 }
 
 impl Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::InvalidAscii(value) => {
-                write!(f, "invalid ascii character: {value}")
-            }
             Self::InvalidArgumentSize(message) => {
                 write!(f, "invalid argument size: {message}")
             }
@@ -32,29 +28,21 @@ impl Pep8Word {
     pub fn as_bytes(&self) -> [u8; 2] {
         self.0.to_be_bytes()
     }
-}
-
-impl TryFrom<char> for Pep8Word {
-    type Error = Error;
 
-    fn try_from(value: char) -> Result<Self, Self::Error> {
-        if value.is_ascii() {
-            Ok(Self::new(value as u16))
-        } else {
-            Err(Self::Error::InvalidAscii(value))
-        }
+    pub fn as_u16(&self) -> u16 {
+        self.0
     }
 }
 
-impl TryFrom<&char> for Pep8Word {
-    type Error = Error;
+impl Display for Pep8Word {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
 
-    fn try_from(value: &char) -> Result<Self, Self::Error> {
-        if value.is_ascii() {
-            Ok(Self::new(*value as u16))
-        } else {
-            Err(Self::Error::InvalidAscii(*value))
-        }
+impl From<&u8> for Pep8Word {
+    fn from(value: &u8) -> Self {
+        Self(*value as u16)
     }
 }
 
@@ -73,27 +61,11 @@ impl TryFrom<&[u8]> for Pep8Word {
     }
 }
 
-impl TryFrom<String> for Pep8Word {
-    type Error = Error;
-
-    fn try_from(value: String) -> Result<Self, Self::Error> {
-        Self::try_from(value.as_bytes())
-    }
-}
-
-impl TryFrom<&String> for Pep8Word {
-    type Error = Error;
-
-    fn try_from(value: &String) -> Result<Self, Self::Error> {
-        Self::try_from(value.as_bytes())
-    }
-}
-
-impl TryFrom<&str> for Pep8Word {
+impl TryFrom<&Vec<u8>> for Pep8Word {
     type Error = Error;
 
-    fn try_from(value: &str) -> Result<Self, Self::Error> {
-        Self::try_from(value.as_bytes())
+    fn try_from(value: &Vec<u8>) -> Result<Self, Self::Error> {
+        Self::try_from(value.as_slice())
     }
 }
 
@@ -107,64 +79,30 @@ impl From<&u16> for Pep8Word {
 pub struct Pep8Byte(u8);
 
 impl Pep8Byte {
-    pub fn new(value: u8) -> Self {
-        Self(value)
-    }
-
     pub fn as_byte(self) -> u8 {
         self.0
     }
 }
 
-impl Add for Pep8Byte {
-    type Output = Pep8Byte;
-
-    fn add(self, rhs: Self) -> Self::Output {
-        Self(self.0 + rhs.0)
-    }
-}
-
-impl Add<&Pep8Byte> for Pep8Byte {
-    type Output = Pep8Byte;
-
-    fn add(self, rhs: &Pep8Byte) -> Self::Output {
-        Self(self.0 + rhs.0)
-    }
-}
-
-impl PartialOrd for Pep8Byte {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
-    }
-}
-
-impl Ord for Pep8Byte {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.0.cmp(&other.0)
+impl Display for Pep8Byte {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
     }
 }
 
-impl TryFrom<&char> for Pep8Byte {
-    type Error = Error;
-
-    fn try_from(value: &char) -> Result<Self, Self::Error> {
-        if value.is_ascii() {
-            Ok(Self::new(*value as u8))
-        } else {
-            Err(Error::InvalidAscii(*value))
-        }
+impl From<&u8> for Pep8Byte {
+    fn from(value: &u8) -> Self {
+        Self(*value)
     }
 }
 
-impl TryFrom<&String> for Pep8Byte {
+impl TryFrom<&Vec<u8>> for Pep8Byte {
     type Error = Error;
 
-    fn try_from(value: &String) -> Result<Self, Self::Error> {
-        let bytes = value.as_bytes();
-
-        let value = match bytes.len() {
+    fn try_from(value: &Vec<u8>) -> Result<Self, Self::Error> {
+        let value = match value.len() {
             0 => 0,
-            1 => bytes[0] as u8,
+            1 => value[0],
             _ => {
                 return Err(Self::Error::InvalidArgumentSize(String::from(
                     "string must be at most length 1",